@@ -0,0 +1,14 @@
+-- Add migration script here
+create table stock (
+    symbol text primary key not null,
+    exchange text not null,
+    name text not null,
+    currency text not null,
+    sector text,
+    lot_size integer not null default 1,
+    status text not null default 'listed',
+    created_at text not null,
+    updated_at text not null
+);
+
+create index stock_exchange_idx on stock (exchange);
@@ -0,0 +1,12 @@
+-- Add migration script here
+create table price_bar (
+    symbol text not null references stock (symbol) on delete cascade,
+    date text not null,
+    open real not null,
+    high real not null,
+    low real not null,
+    close real not null,
+    adj_close real not null,
+    volume integer not null default 0,
+    primary key (symbol, date)
+);
@@ -0,0 +1,10 @@
+-- Add migration script here
+create table portfolio (
+    portfolio_id integer primary key not null,
+    user_id int not null references user (user_id) on delete cascade,
+    name text not null,
+    base_currency text not null,
+    created_at text not null,
+    updated_at text not null,
+    unique (user_id, name)
+);
@@ -0,0 +1,24 @@
+-- Add migration script here
+create table "transaction" (
+    transaction_id integer primary key not null,
+    portfolio_id integer not null references portfolio (portfolio_id) on delete cascade,
+    kind text not null,
+    symbol text references stock (symbol),
+    trade_date text not null,
+    quantity real,
+    price real,
+    amount real,
+    fee real not null default 0,
+    split_ratio real,
+    note text,
+    created_at text not null
+);
+
+create index transaction_portfolio_idx on "transaction" (portfolio_id, trade_date);
+
+-- The ledger is append-only, mistakes are corrected by booking offsetting entries.
+create trigger transaction_append_only
+before update on "transaction"
+begin
+    select raise(abort, 'transaction ledger is append-only');
+end;
@@ -0,0 +1,3 @@
+-- Add down migration script here
+drop table watchlist_entry;
+drop table watchlist
\ No newline at end of file
@@ -0,0 +1,19 @@
+-- Add migration script here
+create table watchlist (
+    watchlist_id integer primary key not null,
+    user_id int not null references user (user_id) on delete cascade,
+    name text not null,
+    created_at text not null,
+    updated_at text not null,
+    unique (user_id, name)
+);
+
+-- `position` is the 0-based drag order within the list, rewritten whenever entries move.
+create table watchlist_entry (
+    watchlist_id integer not null references watchlist (watchlist_id) on delete cascade,
+    symbol text not null references stock (symbol) on delete cascade,
+    position integer not null,
+    note text,
+    added_at text not null,
+    primary key (watchlist_id, symbol)
+);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+drop table alert_event;
+drop table alert_rule
\ No newline at end of file
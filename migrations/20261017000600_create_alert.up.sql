@@ -0,0 +1,23 @@
+-- Add migration script here
+create table alert_rule (
+    alert_rule_id integer primary key not null,
+    user_id int not null references user (user_id) on delete cascade,
+    symbol text not null references stock (symbol) on delete cascade,
+    condition text not null,
+    threshold real not null,
+    note text,
+    created_at text not null
+);
+
+create index alert_rule_symbol_idx on alert_rule (symbol);
+
+-- A rule fires at most once per session; the unique key is what makes delivery exactly-once
+-- when the same quote is evaluated twice, e.g. after a restart.
+create table alert_event (
+    alert_event_id integer primary key not null,
+    alert_rule_id integer not null references alert_rule (alert_rule_id) on delete cascade,
+    session text not null,
+    price real not null,
+    triggered_at text not null,
+    unique (alert_rule_id, session)
+);
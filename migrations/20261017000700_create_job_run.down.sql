@@ -0,0 +1,2 @@
+-- Add down migration script here
+drop table job_run
\ No newline at end of file
@@ -0,0 +1,14 @@
+-- Add migration script here
+-- One row per attempt of a scheduled job, a retried run adding a row per attempt.
+create table job_run (
+    job_run_id integer primary key not null,
+    job_name text not null,
+    scheduled_for text not null,
+    attempt int not null,
+    status text not null,
+    error text,
+    started_at text not null,
+    finished_at text
+);
+
+create index job_run_job_name_idx on job_run (job_name, started_at);
@@ -0,0 +1,4 @@
+-- Add down migration script here
+drop table session;
+drop index user_email_nocase_idx;
+drop index user_username_nocase_idx
\ No newline at end of file
@@ -0,0 +1,13 @@
+-- Add migration script here
+-- Emails and usernames are unique regardless of case, on top of the exact match constraints.
+create unique index user_username_nocase_idx on user (username collate nocase);
+create unique index user_email_nocase_idx on user (email collate nocase);
+
+-- Only a hash of the token is stored, so a leaked database doesn't leak live sessions.
+create table session (
+    token_hash blob primary key not null,
+    user_id int not null references user (user_id) on delete cascade,
+    created_at text not null
+);
+
+create index session_user_id_idx on session (user_id);
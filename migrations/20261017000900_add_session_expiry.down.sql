@@ -0,0 +1,10 @@
+-- Add down migration script here
+drop table session;
+
+create table session (
+    token_hash blob primary key not null,
+    user_id int not null references user (user_id) on delete cascade,
+    created_at text not null
+);
+
+create index session_user_id_idx on session (user_id)
@@ -0,0 +1,17 @@
+-- Add migration script here
+-- Sessions get an id to list and revoke them by, and expiry. Existing sessions have no sensible
+-- expiry, so the table is recreated, logging everybody out once.
+drop table session;
+
+create table session (
+    session_id integer primary key not null,
+    token_hash blob unique not null,
+    user_id int not null references user (user_id) on delete cascade,
+    user_agent text,
+    created_at text not null,
+    last_seen_at text not null,
+    expires_at text not null
+);
+
+create index session_user_id_idx on session (user_id);
+create index session_expires_at_idx on session (expires_at);
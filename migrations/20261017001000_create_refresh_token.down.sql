@@ -0,0 +1,2 @@
+-- Add down migration script here
+drop table refresh_token
\ No newline at end of file
@@ -0,0 +1,16 @@
+-- Add migration script here
+-- Refresh tokens are single use: refreshing marks the token used and issues the next one of the
+-- same family. A used token coming back means it leaked, and the whole family is revoked.
+create table refresh_token (
+    refresh_token_id integer primary key not null,
+    token_hash blob unique not null,
+    family text not null,
+    user_id int not null references user (user_id) on delete cascade,
+    created_at text not null,
+    expires_at text not null,
+    used_at text,
+    revoked_at text
+);
+
+create index refresh_token_family_idx on refresh_token (family);
+create index refresh_token_user_id_idx on refresh_token (user_id);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+alter table user drop column suspended_at;
+alter table user drop column role
\ No newline at end of file
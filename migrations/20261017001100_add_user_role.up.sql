@@ -0,0 +1,9 @@
+-- Add migration script here
+-- Roles are ordered, each one allowing what the ones before it do: user < premium < admin.
+-- The first admin has to be promoted by hand:
+--   UPDATE user SET role = 'admin' WHERE email = '...';
+alter table user add column role text not null default 'user'
+    check (role in ('user', 'premium', 'admin'));
+
+-- Suspended users keep their data but can't log in.
+alter table user add column suspended_at text;
@@ -0,0 +1,2 @@
+-- Add down migration script here
+drop table api_key
\ No newline at end of file
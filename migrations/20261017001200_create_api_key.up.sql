@@ -0,0 +1,16 @@
+-- Add migration script here
+create table api_key (
+    api_key_id integer primary key not null,
+    user_id int not null references user (user_id) on delete cascade,
+    name text not null,
+    -- The start of the key, for telling keys apart; the rest is only known to its owner.
+    prefix text not null,
+    token_hash blob unique not null,
+    -- Space separated, e.g. 'read:portfolio read:market'.
+    scopes text not null,
+    created_at text not null,
+    last_used_at text,
+    request_count int not null default 0
+);
+
+create index api_key_user_id_idx on api_key (user_id);
@@ -0,0 +1,4 @@
+-- Add down migration script here
+drop table login_challenge;
+drop table recovery_code;
+drop table totp
\ No newline at end of file
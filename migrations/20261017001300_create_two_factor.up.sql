@@ -0,0 +1,29 @@
+-- Add migration script here
+-- The TOTP secret of a user. It only protects logins once confirmed with a first code.
+create table totp (
+    user_id int primary key not null references user (user_id) on delete cascade,
+    secret blob not null,
+    created_at text not null,
+    enabled_at text,
+    -- The time step of the last accepted code, so a code can't be replayed.
+    last_used_step int
+);
+
+-- Single use codes for when the authenticator is lost, stored hashed like session tokens.
+create table recovery_code (
+    recovery_code_id integer primary key not null,
+    user_id int not null references user (user_id) on delete cascade,
+    code_hash blob unique not null,
+    used_at text
+);
+
+create index recovery_code_user_id_idx on recovery_code (user_id);
+
+-- Logins with the right password, waiting for the second factor.
+create table login_challenge (
+    login_challenge_id integer primary key not null,
+    token_hash blob unique not null,
+    user_id int not null references user (user_id) on delete cascade,
+    attempts int not null default 0,
+    expires_at text not null
+);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+drop table used_email_token;
+alter table user drop column email_verified_at
\ No newline at end of file
@@ -0,0 +1,9 @@
+-- Add migration script here
+alter table user add column email_verified_at text;
+
+-- Ids of redeemed email tokens, so each is only good once. Kept until the token would have
+-- expired anyway.
+create table used_email_token (
+    jti text primary key not null,
+    expires_at text not null
+);
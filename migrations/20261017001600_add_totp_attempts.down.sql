@@ -0,0 +1,2 @@
+-- Add down migration script here
+alter table totp drop column failed_attempts
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- Add migration script here
+-- Wrong codes in a row entered from a logged in session, e.g. to turn 2FA off. Capped like the
+-- attempts of a login challenge, so a stolen session can't guess its way past the second factor.
+alter table totp add column failed_attempts int not null default 0;
@@ -0,0 +1,6 @@
+-- Add down migration script here
+drop table "transaction";
+drop function transaction_append_only;
+drop table portfolio;
+drop table stock;
+drop table "user"
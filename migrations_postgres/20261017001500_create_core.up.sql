@@ -0,0 +1,64 @@
+-- Add migration script here
+-- The tables behind the repositories in `src/store`, mirroring their SQLite schema.
+create table "user" (
+    user_id bigint generated by default as identity primary key,
+    username text not null constraint user_username_key unique,
+    email text not null constraint user_email_key unique,
+    password_hash text not null,
+    created_at timestamptz,
+    role text not null default 'user' check (role in ('user', 'premium', 'admin')),
+    suspended_at timestamptz,
+    email_verified_at timestamptz
+);
+
+create table stock (
+    symbol text primary key,
+    exchange text not null,
+    name text not null,
+    currency text not null,
+    sector text,
+    lot_size bigint not null default 1,
+    status text not null default 'listed',
+    created_at timestamptz not null,
+    updated_at timestamptz not null
+);
+
+create index stock_exchange_idx on stock (exchange);
+
+create table portfolio (
+    portfolio_id bigint generated by default as identity primary key,
+    user_id bigint not null references "user" (user_id) on delete cascade,
+    name text not null,
+    base_currency text not null,
+    created_at timestamptz not null,
+    updated_at timestamptz not null,
+    constraint portfolio_user_id_name_key unique (user_id, name)
+);
+
+create table "transaction" (
+    transaction_id bigint generated by default as identity primary key,
+    portfolio_id bigint not null references portfolio (portfolio_id) on delete cascade,
+    kind text not null,
+    symbol text references stock (symbol),
+    trade_date date not null,
+    quantity double precision,
+    price double precision,
+    amount double precision,
+    fee double precision not null default 0,
+    split_ratio double precision,
+    note text,
+    created_at timestamptz not null
+);
+
+create index transaction_portfolio_idx on "transaction" (portfolio_id, trade_date);
+
+-- The ledger is append-only, mistakes are corrected by booking offsetting entries.
+create function transaction_append_only() returns trigger as $$
+begin
+    raise exception 'transaction ledger is append-only';
+end;
+$$ language plpgsql;
+
+create trigger transaction_append_only
+before update on "transaction"
+for each row execute function transaction_append_only();
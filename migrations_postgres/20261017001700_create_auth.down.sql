@@ -0,0 +1,10 @@
+-- Add down migration script here
+drop table login_challenge;
+drop table recovery_code;
+drop table totp;
+drop table api_key;
+drop table used_email_token;
+drop table refresh_token;
+drop table session;
+drop index user_email_lower_key;
+drop index user_username_lower_key
@@ -0,0 +1,79 @@
+-- Add migration script here
+-- Logins, API keys and second factors, mirroring their SQLite schema.
+
+-- Emails and usernames are unique regardless of case, on top of the exact match constraints.
+create unique index user_username_lower_key on "user" (lower(username));
+create unique index user_email_lower_key on "user" (lower(email));
+
+-- Only hashes of tokens and codes are stored, so a leaked database doesn't leak live logins.
+create table session (
+    session_id bigint generated by default as identity primary key,
+    token_hash bytea not null unique,
+    user_id bigint not null references "user" (user_id) on delete cascade,
+    user_agent text,
+    created_at timestamptz not null,
+    last_seen_at timestamptz not null,
+    expires_at timestamptz not null
+);
+
+create index session_user_id_idx on session (user_id);
+create index session_expires_at_idx on session (expires_at);
+
+create table refresh_token (
+    refresh_token_id bigint generated by default as identity primary key,
+    token_hash bytea not null unique,
+    family text not null,
+    user_id bigint not null references "user" (user_id) on delete cascade,
+    created_at timestamptz not null,
+    expires_at timestamptz not null,
+    used_at timestamptz,
+    revoked_at timestamptz
+);
+
+create index refresh_token_family_idx on refresh_token (family);
+create index refresh_token_user_id_idx on refresh_token (user_id);
+
+create table used_email_token (
+    jti text primary key,
+    expires_at timestamptz not null
+);
+
+create table api_key (
+    api_key_id bigint generated by default as identity primary key,
+    user_id bigint not null references "user" (user_id) on delete cascade,
+    name text not null,
+    prefix text not null,
+    token_hash bytea not null unique,
+    scopes text not null,
+    created_at timestamptz not null,
+    last_used_at timestamptz,
+    request_count bigint not null default 0
+);
+
+create index api_key_user_id_idx on api_key (user_id);
+
+create table totp (
+    user_id bigint primary key references "user" (user_id) on delete cascade,
+    secret bytea not null,
+    created_at timestamptz not null,
+    enabled_at timestamptz,
+    last_used_step bigint,
+    failed_attempts bigint not null default 0
+);
+
+create table recovery_code (
+    recovery_code_id bigint generated by default as identity primary key,
+    user_id bigint not null references "user" (user_id) on delete cascade,
+    code_hash bytea not null unique,
+    used_at timestamptz
+);
+
+create index recovery_code_user_id_idx on recovery_code (user_id);
+
+create table login_challenge (
+    login_challenge_id bigint generated by default as identity primary key,
+    token_hash bytea not null unique,
+    user_id bigint not null references "user" (user_id) on delete cascade,
+    attempts bigint not null default 0,
+    expires_at timestamptz not null
+);
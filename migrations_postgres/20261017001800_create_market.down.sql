@@ -0,0 +1,7 @@
+-- Add down migration script here
+drop table job_run;
+drop table alert_event;
+drop table alert_rule;
+drop table watchlist_entry;
+drop table watchlist;
+drop table price_bar
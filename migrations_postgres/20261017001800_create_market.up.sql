@@ -0,0 +1,66 @@
+-- Add migration script here
+-- Price bars, watchlists, alerts and job runs, mirroring their SQLite schema.
+create table price_bar (
+    symbol text not null references stock (symbol) on delete cascade,
+    date date not null,
+    open double precision not null,
+    high double precision not null,
+    low double precision not null,
+    close double precision not null,
+    adj_close double precision not null,
+    volume bigint not null default 0,
+    primary key (symbol, date)
+);
+
+create table watchlist (
+    watchlist_id bigint generated by default as identity primary key,
+    user_id bigint not null references "user" (user_id) on delete cascade,
+    name text not null,
+    created_at timestamptz not null,
+    updated_at timestamptz not null,
+    constraint watchlist_user_id_name_key unique (user_id, name)
+);
+
+create table watchlist_entry (
+    watchlist_id bigint not null references watchlist (watchlist_id) on delete cascade,
+    symbol text not null references stock (symbol) on delete cascade,
+    position bigint not null,
+    note text,
+    added_at timestamptz not null,
+    constraint watchlist_entry_pkey primary key (watchlist_id, symbol)
+);
+
+create table alert_rule (
+    alert_rule_id bigint generated by default as identity primary key,
+    user_id bigint not null references "user" (user_id) on delete cascade,
+    symbol text not null references stock (symbol) on delete cascade,
+    condition text not null,
+    threshold double precision not null,
+    note text,
+    created_at timestamptz not null
+);
+
+create index alert_rule_symbol_idx on alert_rule (symbol);
+
+-- A rule fires at most once per session, see the SQLite schema.
+create table alert_event (
+    alert_event_id bigint generated by default as identity primary key,
+    alert_rule_id bigint not null references alert_rule (alert_rule_id) on delete cascade,
+    session date not null,
+    price double precision not null,
+    triggered_at timestamptz not null,
+    unique (alert_rule_id, session)
+);
+
+create table job_run (
+    job_run_id bigint generated by default as identity primary key,
+    job_name text not null,
+    scheduled_for timestamptz not null,
+    attempt bigint not null,
+    status text not null,
+    error text,
+    started_at timestamptz not null,
+    finished_at timestamptz
+);
+
+create index job_run_job_name_idx on job_run (job_name, started_at);
@@ -0,0 +1,234 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::Router;
+use chrono::Utc;
+
+use super::model::{ManagedUser, UpdateUser, UserFilter};
+use crate::app::AppState;
+use crate::auth::model::Role;
+use crate::auth::{Admin, RequireRole};
+use crate::common::errors::{Error, Result};
+use crate::common::extract::{Json, Path, Query};
+use crate::common::paging::Paging;
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/admin/users", get(list_users))
+        .route(
+            "/api/admin/users/:id",
+            get(get_user).put(update_user).delete(delete_user),
+        )
+}
+
+/// A wrapper type for all requests/responses from these routes.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct UserBody<T> {
+    user: T,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MultipleUsersBody {
+    users: Vec<ManagedUser>,
+    users_count: usize,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(default)]
+struct ListUsersQuery {
+    role: Option<Role>,
+    suspended: Option<bool>,
+    page: Option<usize>,
+    limit: Option<usize>,
+}
+
+async fn list_users(
+    _admin: RequireRole<Admin>,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ListUsersQuery>,
+) -> Result<Json<MultipleUsersBody>> {
+    let paging = Paging {
+        page: query.page,
+        limit: query.limit,
+    };
+    let (limit, offset) = paging.limit_offset(50, 500);
+
+    let filter = UserFilter {
+        role: query.role,
+        suspended: query.suspended,
+        limit,
+        offset,
+    };
+    let users = state.store.users.list_users(&filter).await?;
+
+    Ok(Json(MultipleUsersBody {
+        users_count: users.len(),
+        users,
+    }))
+}
+
+async fn fetch_user(state: &AppState, user_id: i64) -> Result<ManagedUser> {
+    state
+        .store
+        .users
+        .managed_user(user_id)
+        .await?
+        .ok_or(Error::NotFound)
+}
+
+async fn get_user(
+    _admin: RequireRole<Admin>,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<i64>,
+) -> Result<Json<UserBody<ManagedUser>>> {
+    let user = fetch_user(&state, user_id).await?;
+
+    Ok(Json(UserBody { user }))
+}
+
+// Changes the role of a user, or suspends them. Either logs them out everywhere, so no session
+// from before a promotion gains its privileges; access tokens they already hold stay valid until
+// they expire, but can't be refreshed.
+//
+// Admins can't change their own account, so there's always one left to undo mistakes.
+async fn update_user(
+    admin: RequireRole<Admin>,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<i64>,
+    Json(req): Json<UserBody<UpdateUser>>,
+) -> Result<Json<UserBody<ManagedUser>>> {
+    if user_id == admin.user.user_id {
+        return Err(Error::unprocessable_entity([(
+            "user",
+            "can't change your own account",
+        )]));
+    }
+
+    let updated = state
+        .store
+        .users
+        .update_user(user_id, &req.user, Utc::now())
+        .await?;
+    if !updated {
+        return Err(Error::NotFound);
+    }
+
+    let user = fetch_user(&state, user_id).await?;
+    Ok(Json(UserBody { user }))
+}
+
+// Deletes the account along with everything it owns.
+async fn delete_user(
+    admin: RequireRole<Admin>,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<i64>,
+) -> Result<StatusCode> {
+    if user_id == admin.user.user_id {
+        return Err(Error::unprocessable_entity([(
+            "user",
+            "can't delete your own account",
+        )]));
+    }
+
+    let deleted = state.store.users.delete_user(user_id).await?;
+
+    if !deleted {
+        return Err(Error::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::test_session_cookie;
+    use crate::conn::test_database;
+    use crate::testing::{insert_user, insert_user_with_password, send};
+
+    #[tokio::test]
+    async fn manage_users() {
+        let db = test_database().await;
+        insert_user(&db, 1, "root", Role::Admin).await;
+        insert_user_with_password(&db, 2, "alice", "correct horse").await;
+        insert_user(&db, 3, "bob", Role::Premium).await;
+        let root = test_session_cookie(&db, 1).await;
+        let alice = test_session_cookie(&db, 2).await;
+        let bob = test_session_cookie(&db, 3).await;
+        let app = crate::app::api_router(Arc::new(AppState::new(db)));
+
+        // Premium is still no admin.
+        for cookie in [&alice, &bob] {
+            let (status, _) = send(&app, "GET", "/api/admin/users", cookie, None).await;
+            assert_eq!(status, StatusCode::FORBIDDEN);
+        }
+        let (status, _) = send(&app, "GET", "/api/admin/users", "", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = send(&app, "GET", "/api/admin/users", &root, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["usersCount"], 3);
+        let (_, body) = send(&app, "GET", "/api/admin/users?role=premium", &root, None).await;
+        assert_eq!(body["users"][0]["username"], "bob");
+
+        let suspend = serde_json::json!({"user": {"suspended": true}});
+        let (status, _) = send(
+            &app,
+            "PUT",
+            "/api/admin/users/1",
+            &root,
+            Some(suspend.clone()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let (status, body) = send(&app, "PUT", "/api/admin/users/2", &root, Some(suspend)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["user"]["suspendedAt"].is_string());
+
+        // Logged out, and can't log back in.
+        let (status, _) = send(&app, "GET", "/api/user", &alice, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let login = serde_json::json!({"user": {"email": "alice@example.com", "password": "correct horse"}});
+        let (status, _) = send(&app, "POST", "/api/auth/login", "", Some(login.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (_, body) = send(&app, "GET", "/api/admin/users?suspended=true", &root, None).await;
+        assert_eq!(body["usersCount"], 1);
+
+        let update = serde_json::json!({"user": {"suspended": false, "role": "admin"}});
+        let (_, body) = send(&app, "PUT", "/api/admin/users/2", &root, Some(update)).await;
+        assert_eq!(body["user"]["role"], "admin");
+        assert!(body["user"]["suspendedAt"].is_null());
+        let (status, _) = send(&app, "POST", "/api/auth/login", "", Some(login)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = send(&app, "DELETE", "/api/admin/users/3", &root, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(&app, "GET", "/api/admin/users/3", &root, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = send(&app, "GET", "/api/user", &bob, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn role_changes_log_out() {
+        let db = test_database().await;
+        insert_user(&db, 1, "root", Role::Admin).await;
+        insert_user(&db, 2, "alice", Role::User).await;
+        let root = test_session_cookie(&db, 1).await;
+        let alice = test_session_cookie(&db, 2).await;
+        let app = crate::app::api_router(Arc::new(AppState::new(db)));
+
+        let promote = serde_json::json!({"user": {"role": "admin"}});
+        let (status, _) = send(&app, "PUT", "/api/admin/users/2", &root, Some(promote)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // A session from before the promotion, possibly captured, doesn't get to be admin.
+        let (status, _) = send(&app, "GET", "/api/admin/users", &alice, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(&app, "GET", "/api/user", &alice, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
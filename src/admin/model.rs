@@ -0,0 +1,32 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::auth::model::{Role, User};
+
+/// A user as support staff see them.
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManagedUser {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub user: User,
+    pub suspended_at: Option<DateTime<Utc>>,
+}
+
+/// Changes to a user's access, `None` leaving it as is.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct UpdateUser {
+    pub role: Option<Role>,
+    pub suspended: Option<bool>,
+}
+
+/// Which page of which users to list, ordered by id.
+#[derive(Debug, Clone, Default)]
+pub struct UserFilter {
+    pub role: Option<Role>,
+    pub suspended: Option<bool>,
+    pub limit: i64,
+    pub offset: i64,
+}
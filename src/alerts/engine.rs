@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+
+use crate::common::errors::Result;
+use crate::common::observer::{BoxFuture, Observer};
+use crate::prices::feed::QuoteFeed;
+use crate::prices::model::Quote;
+use crate::store::AlertRepo;
+
+/// Evaluates alert rules against every quote of the `QuoteFeed` it is attached to.
+pub struct AlertEngine {
+    alerts: Arc<dyn AlertRepo>,
+    /// Last price seen per symbol, to detect crossings.
+    last_prices: Mutex<HashMap<String, f64>>,
+}
+
+impl AlertEngine {
+    pub fn new(alerts: Arc<dyn AlertRepo>) -> Arc<Self> {
+        Arc::new(Self {
+            alerts,
+            last_prices: Mutex::default(),
+        })
+    }
+
+    /// Record an event for every rule `quote` fires, returning how many were new.
+    ///
+    /// Evaluating the same quote again records nothing: each rule fires at most once per
+    /// session, enforced by the unique key of `alert_event` rather than in memory, so this
+    /// holds across restarts and concurrent engines too.
+    pub async fn evaluate(&self, quote: &Quote) -> Result<usize> {
+        let previous = self
+            .last_prices
+            .lock()
+            .unwrap()
+            .insert(quote.symbol.clone(), quote.price);
+
+        let rules = self.alerts.rules_for(&quote.symbol).await?;
+
+        let mut fired = 0;
+        for rule in rules
+            .iter()
+            .filter(|rule| rule.is_triggered(previous, quote))
+        {
+            let recorded = self
+                .alerts
+                .record_event(rule.alert_rule_id, quote.session(), quote.price, Utc::now())
+                .await?;
+
+            if recorded {
+                fired += 1;
+                tracing::info!(
+                    rule = rule.alert_rule_id,
+                    user = rule.user_id,
+                    symbol = quote.symbol,
+                    price = quote.price,
+                    "alert triggered"
+                );
+            }
+        }
+
+        Ok(fired)
+    }
+}
+
+impl Observer for AlertEngine {
+    type Subject = QuoteFeed;
+
+    fn observe<'a>(&'a self, feed: &'a QuoteFeed) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            let Some(quote) = feed.quote() else { return };
+            if let Err(e) = self.evaluate(quote).await {
+                tracing::error!("evaluating alerts on {}: {e}", quote.symbol);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Role;
+    use crate::conn::test_database;
+    use crate::store::Store;
+    use crate::testing::{insert_stock, insert_user};
+
+    #[tokio::test]
+    async fn fires_once_per_session() {
+        let db = test_database().await;
+        insert_user(&db, 1, "alice", Role::User).await;
+        insert_stock(&db, "AAPL", "XNAS", "Apple").await;
+        sqlx::query(
+            "INSERT INTO alert_rule (user_id, symbol, condition, threshold, created_at)
+             VALUES (1, 'AAPL', 'crosses_above', 200, '2024-01-01T00:00:00Z'),
+                    (1, 'AAPL', 'drops_percent', 5, '2024-01-01T00:00:00Z')",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        let engine = AlertEngine::new(Store::sqlite(db.clone()).alerts);
+
+        let quote = |price: f64, at: &str| Quote {
+            symbol: "AAPL".to_string(),
+            price,
+            open: 198.0,
+            previous_close: None,
+            at: at.parse().unwrap(),
+        };
+
+        assert_eq!(
+            engine
+                .evaluate(&quote(199.0, "2024-01-02T15:00:00Z"))
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            engine
+                .evaluate(&quote(201.0, "2024-01-02T15:01:00Z"))
+                .await
+                .unwrap(),
+            1
+        );
+        // Crosses again and drops 5% on the same day: only the drop is new.
+        assert_eq!(
+            engine
+                .evaluate(&quote(199.0, "2024-01-02T15:02:00Z"))
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            engine
+                .evaluate(&quote(201.0, "2024-01-02T15:03:00Z"))
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            engine
+                .evaluate(&quote(188.0, "2024-01-02T15:04:00Z"))
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            engine
+                .evaluate(&quote(188.0, "2024-01-02T15:04:00Z"))
+                .await
+                .unwrap(),
+            0
+        );
+
+        let events: i64 = sqlx::query_scalar("SELECT count(*) FROM alert_event")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(events, 2);
+    }
+}
@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::{delete, get};
+use axum::Router;
+use chrono::Utc;
+
+use super::model::{AlertCondition, AlertEvent, AlertRule, NewAlert};
+use crate::app::AppState;
+use crate::auth::AuthUser;
+use crate::common::errors::{Error, Result};
+use crate::common::extract::{Json, Path, Query};
+use crate::common::paging::Paging;
+use crate::stocks::{fetch_stock, normalize_code};
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/alerts", get(list_alerts).post(create_alert))
+        .route("/api/alerts/events", get(list_events))
+        .route("/api/alerts/:id", delete(delete_alert))
+}
+
+/// A wrapper type for all requests/responses from these routes.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct AlertBody<T> {
+    alert: T,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MultipleAlertsBody {
+    alerts: Vec<AlertRule>,
+    alerts_count: usize,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MultipleEventsBody {
+    events: Vec<AlertEvent>,
+    events_count: usize,
+}
+
+async fn list_alerts(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<MultipleAlertsBody>> {
+    let alerts = state.store.alerts.list_alerts(auth_user.user_id).await?;
+
+    Ok(Json(MultipleAlertsBody {
+        alerts_count: alerts.len(),
+        alerts,
+    }))
+}
+
+async fn create_alert(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<AlertBody<NewAlert>>,
+) -> Result<(StatusCode, Json<AlertBody<AlertRule>>)> {
+    let new = NewAlert {
+        symbol: normalize_code(&req.alert.symbol),
+        ..req.alert
+    };
+
+    let mut errors = Vec::new();
+    if !new.threshold.is_finite() || new.threshold <= 0.0 {
+        errors.push(("threshold", "must be a positive number"));
+    }
+    if new.condition == AlertCondition::DropsPercent && new.threshold >= 100.0 {
+        errors.push(("threshold", "must be below 100 percent"));
+    }
+    if fetch_stock(&state, &new.symbol).await?.is_none() {
+        errors.push(("symbol", "unknown symbol"));
+    }
+    if !errors.is_empty() {
+        return Err(Error::unprocessable_entity(errors));
+    }
+
+    let alert = state
+        .store
+        .alerts
+        .create_alert(auth_user.user_id, &new, Utc::now())
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(AlertBody { alert })))
+}
+
+async fn delete_alert(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(alert_rule_id): Path<i64>,
+) -> Result<StatusCode> {
+    let deleted = state
+        .store
+        .alerts
+        .delete_alert(auth_user.user_id, alert_rule_id)
+        .await?;
+
+    if !deleted {
+        return Err(Error::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Most recent first.
+async fn list_events(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Query(paging): Query<Paging>,
+) -> Result<Json<MultipleEventsBody>> {
+    let (limit, offset) = paging.limit_offset(10, 100);
+
+    let events = state
+        .store
+        .alerts
+        .list_events(auth_user.user_id, limit, offset)
+        .await?;
+
+    Ok(Json(MultipleEventsBody {
+        events_count: events.len(),
+        events,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::AlertEngine;
+    use crate::auth::{test_session_cookie, Role};
+    use crate::common::cancel::CancellationToken;
+    use crate::common::observer::Observable;
+    use crate::conn::test_database;
+    use crate::prices::feed::QuoteFeed;
+    use crate::testing::{insert_stock, insert_user, send};
+
+    #[tokio::test]
+    async fn stored_bars_trigger_alerts() {
+        let db = test_database().await;
+        insert_user(&db, 1, "alice", Role::User).await;
+        insert_user(&db, 2, "root", Role::Admin).await;
+        insert_stock(&db, "AAPL", "XNAS", "Apple").await;
+        let alice = test_session_cookie(&db, 1).await;
+        let admin = test_session_cookie(&db, 2).await;
+        let state = Arc::new(AppState::new(db.clone()));
+        let app = router()
+            .merge(crate::prices::router())
+            .with_state(state.clone());
+
+        let engine = AlertEngine::new(state.store.alerts.clone());
+        let mut feed = QuoteFeed::default();
+        feed.attach(engine.clone());
+        let running = tokio::spawn(feed.run(state.quotes.subscribe(), CancellationToken::new()));
+
+        let alert = serde_json::json!({"alert": {"symbol": "aapl", "condition": "crosses_above", "threshold": 200}});
+        let (status, body) = send(&app, "POST", "/api/alerts", &alice, Some(alert)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["alert"]["condition"], "crosses_above");
+
+        let bars = serde_json::json!({"bars": [
+            {"date": "2024-01-02", "open": 195, "high": 199, "low": 194, "close": 198},
+            {"date": "2024-01-03", "open": 198, "high": 203, "low": 197, "close": 202},
+        ]});
+        let (status, _) = send(&app, "PUT", "/api/stocks/AAPL/bars", &admin, Some(bars)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Dropping the last sender ends the feed once every quote has been evaluated.
+        drop((app, state));
+        running.await.unwrap();
+
+        let app = router().with_state(Arc::new(AppState::new(db)));
+        let (_, body) = send(&app, "GET", "/api/alerts/events", &alice, None).await;
+        assert_eq!(body["eventsCount"], 1);
+        assert_eq!(body["events"][0]["session"], "2024-01-03");
+    }
+}
@@ -0,0 +1,6 @@
+mod engine;
+mod handlers;
+pub mod model;
+
+pub use engine::AlertEngine;
+pub use handlers::router;
@@ -0,0 +1,131 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::prices::model::Quote;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// The price moves from at or below `threshold` to above it.
+    CrossesAbove,
+    /// The price moves from at or above `threshold` to below it.
+    CrossesBelow,
+    /// The price is down at least `threshold` percent from the session open.
+    DropsPercent,
+    /// The price is up at least `threshold` percent from the session open.
+    RisesPercent,
+}
+
+/// A user's condition on the price of one symbol.
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertRule {
+    #[serde(rename = "id")]
+    pub alert_rule_id: i64,
+    #[serde(skip_serializing)]
+    pub user_id: i64,
+    pub symbol: String,
+    pub condition: AlertCondition,
+    pub threshold: f64,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A rule to create, its symbol normalized.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewAlert {
+    pub symbol: String,
+    pub condition: AlertCondition,
+    pub threshold: f64,
+    pub note: Option<String>,
+}
+
+impl AlertRule {
+    /// Whether `quote` fires the rule, given the previous price seen for the symbol.
+    ///
+    /// Crossings need a previous price, falling back to the quote's previous close.
+    pub fn is_triggered(&self, previous: Option<f64>, quote: &Quote) -> bool {
+        let previous = previous.or(quote.previous_close);
+        let change = (quote.price - quote.open) / quote.open * 100.0;
+
+        match self.condition {
+            AlertCondition::CrossesAbove => {
+                previous.is_some_and(|p| p <= self.threshold) && quote.price > self.threshold
+            }
+            AlertCondition::CrossesBelow => {
+                previous.is_some_and(|p| p >= self.threshold) && quote.price < self.threshold
+            }
+            AlertCondition::DropsPercent => quote.open > 0.0 && change <= -self.threshold,
+            AlertCondition::RisesPercent => quote.open > 0.0 && change >= self.threshold,
+        }
+    }
+}
+
+/// A rule having fired, recorded at most once per rule and session.
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertEvent {
+    #[serde(rename = "id")]
+    pub alert_event_id: i64,
+    #[serde(rename = "ruleId")]
+    pub alert_rule_id: i64,
+    pub session: NaiveDate,
+    pub price: f64,
+    pub triggered_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(condition: AlertCondition, threshold: f64) -> AlertRule {
+        AlertRule {
+            alert_rule_id: 1,
+            user_id: 1,
+            symbol: "AAPL".to_string(),
+            condition,
+            threshold,
+            note: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn quote(price: f64, open: f64) -> Quote {
+        Quote {
+            symbol: "AAPL".to_string(),
+            price,
+            open,
+            previous_close: None,
+            at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn crossings_need_the_previous_price() {
+        let above = rule(AlertCondition::CrossesAbove, 200.0);
+        assert!(above.is_triggered(Some(199.0), &quote(201.0, 195.0)));
+        assert!(above.is_triggered(Some(200.0), &quote(200.5, 195.0)));
+        assert!(!above.is_triggered(Some(201.0), &quote(202.0, 195.0)));
+        assert!(!above.is_triggered(None, &quote(201.0, 195.0)));
+
+        let below = rule(AlertCondition::CrossesBelow, 200.0);
+        let from_close = Quote {
+            previous_close: Some(205.0),
+            ..quote(199.0, 204.0)
+        };
+        assert!(below.is_triggered(None, &from_close));
+    }
+
+    #[test]
+    fn intraday_moves_are_from_the_open() {
+        let drop = rule(AlertCondition::DropsPercent, 5.0);
+        assert!(drop.is_triggered(None, &quote(95.0, 100.0)));
+        assert!(!drop.is_triggered(None, &quote(95.5, 100.0)));
+
+        let rise = rule(AlertCondition::RisesPercent, 5.0);
+        assert!(rise.is_triggered(None, &quote(106.0, 100.0)));
+        assert!(!rise.is_triggered(None, &quote(94.0, 100.0)));
+    }
+}
@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use axum::middleware;
+use axum::Router;
+
+use crate::auth::JwtKeys;
+use crate::common::cancel::CancellationToken;
+use crate::common::clock::{Clock, SystemClock};
+use crate::common::errors::Error;
+use crate::mail::{FileMailer, Mailer};
+use crate::prices::bus::QuoteBus;
+use crate::store::Store;
+use crate::{admin, alerts, auth, portfolios, prices, stocks, watchlists};
+
+/// Shared state handed to every API handler via `State<Arc<AppState>>`.
+pub struct AppState {
+    /// Everything the app stores, on SQLite or Postgres.
+    pub store: Store,
+    /// Every new quote, from stored bars or market data, for streaming and background tasks.
+    pub quotes: QuoteBus,
+    /// Signs access tokens and mailed tokens; random unless configured, see `JwtKeys::random`.
+    pub jwt: JwtKeys,
+    /// The time for two-factor codes, login challenges and mailed tokens, see `common::clock`.
+    pub clock: Arc<dyn Clock>,
+    /// Sends verification and password reset emails; prints them unless configured.
+    pub mailer: Arc<dyn Mailer>,
+    /// Where the app is reached by users, for links in emails.
+    pub public_url: String,
+    /// Cancelled on shutdown, ending quote streams and background tasks.
+    pub shutdown: CancellationToken,
+}
+
+impl AppState {
+    pub fn new(store: impl Into<Store>) -> Self {
+        Self {
+            store: store.into(),
+            quotes: QuoteBus::default(),
+            jwt: JwtKeys::random(),
+            clock: Arc::new(SystemClock),
+            mailer: Arc::new(FileMailer::stdout()),
+            public_url: "http://127.0.0.1:3000".to_string(),
+            shutdown: CancellationToken::new(),
+        }
+    }
+}
+
+/// All `/api` routes, each module being responsible for setting up its own routing.
+pub fn api_router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .merge(auth::router())
+        .merge(stocks::router())
+        .merge(prices::router())
+        .merge(portfolios::router())
+        .merge(watchlists::router())
+        .merge(alerts::router())
+        .merge(admin::router())
+        .method_not_allowed_fallback(|| async { Error::MethodNotAllowed })
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_api_key_scope,
+        ))
+        .with_state(state)
+}
@@ -0,0 +1,138 @@
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderMap, Method};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use super::model::{Role, Scope, Scopes};
+use crate::common::errors::Result;
+use crate::store::Store;
+
+/// Every API key starts with this, telling them apart from access tokens in `Authorization`.
+pub(crate) const API_KEY_PREFIX: &str = "stk_";
+
+/// How much of a key is kept in the clear, to tell keys apart when listing them.
+const SHOWN_PREFIX_LEN: usize = API_KEY_PREFIX.len() + 6;
+
+/// A random API key, only ever seen by its owner when it's created.
+///
+/// Like session tokens only its SHA-256 hash is stored.
+pub(crate) struct ApiKeySecret(String);
+
+impl ApiKeySecret {
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut bytes);
+        Self(format!("{API_KEY_PREFIX}{}", URL_SAFE_NO_PAD.encode(bytes)))
+    }
+
+    pub fn hash(&self) -> Vec<u8> {
+        Sha256::digest(self.0.as_bytes()).to_vec()
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.0[..SHOWN_PREFIX_LEN]
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+/// The API key in the `Authorization: Bearer` header of a request, if it holds one rather than an
+/// access token.
+pub(crate) fn from_headers(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| token.starts_with(API_KEY_PREFIX))
+}
+
+/// The user using an API key, with the key's scopes.
+pub(crate) struct ApiKeyUser {
+    pub user_id: i64,
+    pub role: Role,
+    pub scopes: Scopes,
+}
+
+/// The user of API key `key`, counting the request against the key once it's accepted.
+///
+/// Keys of suspended users are refused, but not deleted, so they work again once the user is
+/// reinstated.
+pub(crate) async fn authenticate(store: &Store, key: &str) -> Result<Option<ApiKeyUser>> {
+    let token_hash = Sha256::digest(key.as_bytes()).to_vec();
+    let Some(owner) = store.api_keys.api_key_owner(&token_hash).await? else {
+        return Ok(None);
+    };
+    let Ok(scopes) = Scopes::try_from(owner.scopes) else {
+        return Ok(None);
+    };
+
+    store
+        .api_keys
+        .count_api_key_use(&token_hash, Utc::now())
+        .await?;
+
+    Ok(Some(ApiKeyUser {
+        user_id: owner.user_id,
+        role: owner.role,
+        scopes,
+    }))
+}
+
+/// The scope an API key needs for a route, `None` for routes no API key may use.
+///
+/// Keeping this in one place rather than in each handler means new routes are closed to API
+/// keys until they are added here.
+pub(crate) fn required_scope(method: &Method, path: &str) -> Option<Scope> {
+    let reading = method == Method::GET;
+
+    if path.starts_with("/api/portfolios/:id/transactions") && method == Method::POST {
+        Some(Scope::WriteTransactions)
+    } else if path.starts_with("/api/portfolios") && reading {
+        Some(Scope::ReadPortfolio)
+    } else if (path.starts_with("/api/stocks") || path.ends_with("/quotes")) && reading {
+        Some(Scope::ReadMarket)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes_by_route() {
+        let cases = [
+            (
+                Method::GET,
+                "/api/portfolios/:id/pnl",
+                Some(Scope::ReadPortfolio),
+            ),
+            (
+                Method::POST,
+                "/api/portfolios/:id/transactions",
+                Some(Scope::WriteTransactions),
+            ),
+            (Method::POST, "/api/portfolios", None),
+            (Method::DELETE, "/api/portfolios/:id", None),
+            (
+                Method::GET,
+                "/api/stocks/:symbol/bars",
+                Some(Scope::ReadMarket),
+            ),
+            (Method::GET, "/sse/quotes", Some(Scope::ReadMarket)),
+            (Method::PUT, "/api/stocks/:symbol", None),
+            (Method::GET, "/api/keys", None),
+            (Method::GET, "/api/admin/users", None),
+        ];
+        for (method, path, scope) in cases {
+            assert_eq!(required_scope(&method, path), scope, "{method} {path}");
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::{delete, get};
+use axum::Router;
+use chrono::Utc;
+
+use super::api_key::ApiKeySecret;
+use super::model::{ApiKey, Scope, Scopes};
+use crate::app::AppState;
+use crate::auth::AuthUser;
+use crate::common::errors::{Error, Result};
+use crate::common::extract::{Json, Path};
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/keys", get(list_api_keys).post(create_api_key))
+        .route("/api/keys/:id", delete(delete_api_key))
+}
+
+/// A wrapper type for all requests/responses from these routes.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiKeyBody<T> {
+    api_key: T,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MultipleApiKeysBody {
+    api_keys: Vec<ApiKey>,
+    api_keys_count: usize,
+}
+
+#[derive(serde::Deserialize)]
+struct NewApiKey {
+    name: String,
+    scopes: Vec<Scope>,
+}
+
+/// A key just created, the only time the key itself is shown.
+#[derive(serde::Serialize)]
+struct CreatedApiKey {
+    #[serde(flatten)]
+    api_key: ApiKey,
+    key: String,
+}
+
+async fn list_api_keys(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<MultipleApiKeysBody>> {
+    let api_keys = state
+        .store
+        .api_keys
+        .list_api_keys(auth_user.user_id)
+        .await?;
+
+    Ok(Json(MultipleApiKeysBody {
+        api_keys_count: api_keys.len(),
+        api_keys,
+    }))
+}
+
+async fn create_api_key(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<ApiKeyBody<NewApiKey>>,
+) -> Result<(StatusCode, Json<ApiKeyBody<CreatedApiKey>>)> {
+    let new = req.api_key;
+    let name = new.name.trim();
+
+    let mut errors = Vec::new();
+    if !(1..=64).contains(&name.chars().count()) {
+        errors.push(("name", "must be 1-64 characters"));
+    }
+    if new.scopes.is_empty() {
+        errors.push(("scopes", "must grant at least one scope"));
+    }
+    if !errors.is_empty() {
+        return Err(Error::unprocessable_entity(errors));
+    }
+
+    let mut scopes = Vec::new();
+    for scope in new.scopes {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    let secret = ApiKeySecret::generate();
+
+    let api_key = state
+        .store
+        .api_keys
+        .create_api_key(
+            auth_user.user_id,
+            name,
+            secret.prefix(),
+            &secret.hash(),
+            &Scopes(scopes),
+            Utc::now(),
+        )
+        .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ApiKeyBody {
+            api_key: CreatedApiKey {
+                api_key,
+                key: secret.into_string(),
+            },
+        }),
+    ))
+}
+
+async fn delete_api_key(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(api_key_id): Path<i64>,
+) -> Result<StatusCode> {
+    let deleted = state
+        .store
+        .api_keys
+        .delete_api_key(auth_user.user_id, api_key_id)
+        .await?;
+
+    if !deleted {
+        return Err(Error::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{test_session_cookie, Role};
+    use crate::conn::test_database;
+    use crate::testing::{insert_user, send_with, TestResponse};
+
+    #[tokio::test]
+    async fn scoped_api_keys() {
+        let db = test_database().await;
+        insert_user(&db, 1, "alice", Role::User).await;
+        let cookie = test_session_cookie(&db, 1).await;
+        let session = [("cookie", cookie.as_str())];
+        let app = crate::app::api_router(Arc::new(AppState::new(db.clone())));
+
+        let new = serde_json::json!({"apiKey": {"name": "backtests", "scopes": ["read:portfolio", "read:market"]}});
+        let TestResponse { status, body, .. } =
+            send_with(&app, "POST", "/api/keys", &session, Some(new)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let key = body["apiKey"]["key"].as_str().unwrap().to_string();
+        assert!(key.starts_with(body["apiKey"]["prefix"].as_str().unwrap()));
+        let bearer = format!("Bearer {key}");
+        let api_key = [("authorization", bearer.as_str())];
+
+        let portfolio = serde_json::json!({"portfolio": {"name": "Main", "baseCurrency": "USD"}});
+        let TestResponse { status, body, .. } =
+            send_with(&app, "POST", "/api/portfolios", &session, Some(portfolio)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let portfolio_id = body["portfolio"]["id"].as_i64().unwrap();
+
+        // Within its scopes, the key acts as its user.
+        let TestResponse { status, body, .. } =
+            send_with(&app, "GET", "/api/portfolios", &api_key, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["portfoliosCount"], 1);
+        let TestResponse { status, .. } =
+            send_with(&app, "GET", "/api/stocks", &api_key, None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Outside of them it's refused, keys can't manage keys either.
+        let uri = format!("/api/portfolios/{portfolio_id}/transactions");
+        let buy = serde_json::json!({"transaction": {}});
+        let TestResponse { status, .. } = send_with(&app, "POST", &uri, &api_key, Some(buy)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let TestResponse { status, .. } =
+            send_with(&app, "DELETE", "/api/portfolios/1", &api_key, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let TestResponse { status, .. } = send_with(&app, "GET", "/api/keys", &api_key, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let TestResponse { status, .. } = send_with(
+            &app,
+            "GET",
+            "/api/user",
+            &[("authorization", "Bearer stk_nope")],
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // Every request made with the key counts, refused ones included; the key itself is
+        // never shown again.
+        let TestResponse { body, .. } = send_with(&app, "GET", "/api/keys", &session, None).await;
+        assert_eq!(body["apiKeysCount"], 1);
+        let listed = &body["apiKeys"][0];
+        assert_eq!(listed["requestCount"], 5);
+        assert!(listed["lastUsedAt"].is_string());
+        assert_eq!(
+            listed["scopes"],
+            serde_json::json!(["read:portfolio", "read:market"])
+        );
+        assert!(listed.get("key").is_none());
+
+        // Keys of suspended users are refused without counting.
+        sqlx::query("UPDATE user SET suspended_at = '2024-01-02T00:00:00Z' WHERE user_id = 1")
+            .execute(&db)
+            .await
+            .unwrap();
+        let TestResponse { status, .. } =
+            send_with(&app, "GET", "/api/portfolios", &api_key, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let count: i64 = sqlx::query_scalar("SELECT request_count FROM api_key")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(count, 5);
+        sqlx::query("UPDATE user SET suspended_at = NULL")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let uri = format!("/api/keys/{}", listed["id"]);
+        let TestResponse { status, .. } = send_with(&app, "DELETE", &uri, &session, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let TestResponse { status, .. } =
+            send_with(&app, "GET", "/api/portfolios", &api_key, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // Public routes hold keys to their scopes too.
+        let new = serde_json::json!({"apiKey": {"name": "reports", "scopes": ["read:portfolio"]}});
+        let TestResponse { body, .. } =
+            send_with(&app, "POST", "/api/keys", &session, Some(new)).await;
+        let bearer = format!("Bearer {}", body["apiKey"]["key"].as_str().unwrap());
+        let api_key = [("authorization", bearer.as_str())];
+        let TestResponse { status, .. } =
+            send_with(&app, "GET", "/api/stocks", &api_key, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let TestResponse { status, .. } =
+            send_with(&app, "GET", "/api/portfolios", &api_key, None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
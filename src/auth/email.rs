@@ -0,0 +1,369 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::Router;
+
+use super::email_token::{self, Purpose};
+use super::handlers::{valid_password, PASSWORD_RULE};
+use super::password::hash_password;
+use crate::app::AppState;
+use crate::auth::AuthUser;
+use crate::common::errors::{Error, Result};
+use crate::common::extract::Json;
+use crate::mail::Email;
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/auth/verify-email", post(verify_email))
+        .route("/api/auth/verify-email/send", post(resend_verification))
+        .route("/api/auth/password-reset", post(reset_password))
+        .route(
+            "/api/auth/password-reset/request",
+            post(request_password_reset),
+        )
+}
+
+#[derive(serde::Deserialize)]
+struct TokenBody {
+    token: String,
+}
+
+#[derive(serde::Deserialize)]
+struct ResetRequest {
+    email: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NewPassword {
+    token: String,
+    new_password: String,
+}
+
+/// Mail `email` the link verifying it's the address of `user_id`.
+pub(super) async fn send_verification(
+    state: &AppState,
+    user_id: i64,
+    email: &str,
+) -> anyhow::Result<()> {
+    let token = email_token::issue(
+        &state.jwt,
+        Purpose::VerifyEmail,
+        user_id,
+        email,
+        state.clock.now(),
+    );
+
+    state
+        .mailer
+        .send(Email {
+            to: email.to_string(),
+            subject: "Verify your email address".to_string(),
+            body: format!(
+                "Follow this link to verify your email address:\n\n\
+                 {}/verify-email?token={token}\n\n\
+                 It expires in two days.",
+                state.public_url
+            ),
+        })
+        .await
+}
+
+async fn verify_email(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<TokenBody>,
+) -> Result<StatusCode> {
+    let (user_id, email) = email_token::redeem(&state, Purpose::VerifyEmail, &req.token).await?;
+
+    // Only if the address is still the one the link was sent to.
+    state
+        .store
+        .users
+        .verify_email(user_id, &email, state.clock.now())
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn resend_verification(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<StatusCode> {
+    let user = state
+        .store
+        .users
+        .user(auth_user.user_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    if user.email_verified_at.is_some() {
+        return Err(Error::unprocessable_entity([("email", "already verified")]));
+    }
+
+    send_verification(&state, user.user_id, &user.email).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Always accepted, whether the address belongs to anyone or not. The mail is sent in the
+// background so the response time doesn't tell either.
+async fn request_password_reset(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<ResetRequest>,
+) -> Result<StatusCode> {
+    let user = state.store.users.user_by_email(req.email.trim()).await?;
+
+    if let Some(user) = user {
+        let token = email_token::issue(
+            &state.jwt,
+            Purpose::ResetPassword,
+            user.user_id,
+            &user.email,
+            state.clock.now(),
+        );
+        let reset = Email {
+            to: user.email,
+            subject: "Reset your password".to_string(),
+            body: format!(
+                "Follow this link to choose a new password:\n\n\
+                 {}/reset-password?token={token}\n\n\
+                 It expires in an hour. If you didn't ask for it, you can ignore this email.",
+                state.public_url
+            ),
+        };
+
+        let mailer = state.mailer.clone();
+        tokio::spawn(async move {
+            if let Err(error) = mailer.send(reset).await {
+                tracing::error!("sending password reset email failed: {error:#}");
+            }
+        });
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+// Like changing the password, logs the user out everywhere. Having the reset link also proves
+// they own the address.
+async fn reset_password(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<NewPassword>,
+) -> Result<StatusCode> {
+    if !valid_password(&req.new_password) {
+        return Err(Error::unprocessable_entity([(
+            "newPassword",
+            PASSWORD_RULE,
+        )]));
+    }
+    let (user_id, email) = email_token::redeem(&state, Purpose::ResetPassword, &req.token).await?;
+    let password_hash = hash_password(req.new_password).await?;
+    let now = state.clock.now();
+
+    state.store.users.verify_email(user_id, &email, now).await?;
+    state
+        .store
+        .users
+        .set_password(user_id, &password_hash, None, now)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::clock::{Clock, FixedClock};
+    use crate::conn::test_database;
+    use crate::mail::FileMailer;
+    use crate::testing::send_with;
+    use chrono::{Duration, TimeZone, Utc};
+    use std::path::{Path, PathBuf};
+
+    /// The token in the `count`th email written to `dir`, waiting for it to be sent.
+    async fn mailed_token(dir: &Path, count: usize) -> String {
+        for _ in 0..100 {
+            let mut mails: Vec<PathBuf> = std::fs::read_dir(dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .collect();
+            mails.sort();
+            if let Some(mail) = mails.get(count - 1) {
+                let mail = std::fs::read_to_string(mail).unwrap();
+                let token = mail.split("token=").nth(1).unwrap();
+                return token.split_whitespace().next().unwrap().to_string();
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("no email #{count} in {}", dir.display());
+    }
+
+    #[tokio::test]
+    async fn verify_email_and_reset_password() {
+        let db = test_database().await;
+        let dir = std::env::temp_dir().join(format!("stockrs-mail-{}", rand::random::<u64>()));
+        let clock = Arc::new(FixedClock::new(
+            Utc.with_ymd_and_hms(2024, 1, 2, 9, 30, 0).unwrap(),
+        ));
+        let mut state = AppState::new(db);
+        state.mailer = Arc::new(FileMailer::dir(&dir).unwrap());
+        state.clock = clock.clone();
+        let state = Arc::new(state);
+        let app = crate::auth::router().with_state(state.clone());
+
+        let signup = serde_json::json!({"user": {"username": "alice", "email": "alice@example.com", "password": "correct horse"}});
+        let response = send_with(&app, "POST", "/api/auth/signup", &[], Some(signup)).await;
+        assert_eq!(response.status, StatusCode::CREATED);
+        assert!(response.body["user"]["emailVerifiedAt"].is_null());
+        let cookie = response.cookie.unwrap();
+
+        let verification = serde_json::json!({ "token": mailed_token(&dir, 1).await });
+        let status = send_with(
+            &app,
+            "POST",
+            "/api/auth/verify-email",
+            &[],
+            Some(verification.clone()),
+        )
+        .await
+        .status;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let status = send_with(
+            &app,
+            "POST",
+            "/api/auth/verify-email",
+            &[],
+            Some(verification),
+        )
+        .await
+        .status;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let headers = [("cookie", cookie.as_str())];
+        let response = send_with(
+            &app,
+            "POST",
+            "/api/auth/verify-email/send",
+            &headers,
+            Some(serde_json::json!({})),
+        )
+        .await;
+        assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(response.body["errors"]["email"].is_array());
+
+        // Unknown addresses get the same answer, and no mail.
+        let unknown = serde_json::json!({"email": "mallory@example.com"});
+        let status = send_with(
+            &app,
+            "POST",
+            "/api/auth/password-reset/request",
+            &[],
+            Some(unknown),
+        )
+        .await
+        .status;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let request = serde_json::json!({"email": "Alice@example.com"});
+        let status = send_with(
+            &app,
+            "POST",
+            "/api/auth/password-reset/request",
+            &[],
+            Some(request.clone()),
+        )
+        .await
+        .status;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let token = mailed_token(&dir, 2).await;
+
+        // Mailed tokens are good for nothing but their purpose.
+        let verify = email_token::issue(
+            &state.jwt,
+            Purpose::VerifyEmail,
+            1,
+            "alice@example.com",
+            clock.now(),
+        );
+        let reset =
+            |token: &str| serde_json::json!({"token": token, "newPassword": "battery staple"});
+        let status = send_with(
+            &app,
+            "POST",
+            "/api/auth/password-reset",
+            &[],
+            Some(reset(&verify)),
+        )
+        .await
+        .status;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let bearer = format!("Bearer {token}");
+        let status = send_with(
+            &app,
+            "POST",
+            "/api/auth/verify-email/send",
+            &[("authorization", &bearer)],
+            Some(serde_json::json!({})),
+        )
+        .await
+        .status;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let status = send_with(
+            &app,
+            "POST",
+            "/api/auth/password-reset",
+            &[],
+            Some(reset(&token)),
+        )
+        .await
+        .status;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let status = send_with(
+            &app,
+            "POST",
+            "/api/auth/password-reset",
+            &[],
+            Some(reset(&token)),
+        )
+        .await
+        .status;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let status = send_with(
+            &app,
+            "POST",
+            "/api/auth/verify-email/send",
+            &headers,
+            Some(serde_json::json!({})),
+        )
+        .await
+        .status;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let login = serde_json::json!({"user": {"email": "alice@example.com", "password": "battery staple"}});
+        let status = send_with(&app, "POST", "/api/auth/login", &[], Some(login))
+            .await
+            .status;
+        assert_eq!(status, StatusCode::OK);
+
+        // Reset links expire after an hour.
+        send_with(
+            &app,
+            "POST",
+            "/api/auth/password-reset/request",
+            &[],
+            Some(request),
+        )
+        .await;
+        let token = mailed_token(&dir, 3).await;
+        clock.advance(Duration::hours(1));
+        let status = send_with(
+            &app,
+            "POST",
+            "/api/auth/password-reset",
+            &[],
+            Some(reset(&token)),
+        )
+        .await
+        .status;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,104 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, Header, Validation};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use super::jwt::JwtKeys;
+use crate::app::AppState;
+use crate::common::errors::{Error, Result};
+
+/// What a mailed token lets its holder do, its audience as a JWT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Purpose {
+    VerifyEmail,
+    ResetPassword,
+}
+
+impl Purpose {
+    fn audience(self) -> &'static str {
+        match self {
+            Self::VerifyEmail => "verify_email",
+            Self::ResetPassword => "reset_password",
+        }
+    }
+
+    /// Reset links are short lived, as they give away the account to whoever reads the mail.
+    fn lifetime(self) -> Duration {
+        match self {
+            Self::VerifyEmail => Duration::days(2),
+            Self::ResetPassword => Duration::hours(1),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EmailClaims {
+    aud: String,
+    sub: String,
+    /// The address the token was mailed to, so it's void once the user's email changes.
+    email: String,
+    exp: i64,
+    /// Unique per token, recorded once the token is used.
+    jti: String,
+}
+
+/// A signed token for `purpose` to mail to `email`, the address of `user_id`.
+pub(crate) fn issue(
+    keys: &JwtKeys,
+    purpose: Purpose,
+    user_id: i64,
+    email: &str,
+    now: DateTime<Utc>,
+) -> String {
+    let mut jti = [0u8; 16];
+    rand::rngs::OsRng.fill_bytes(&mut jti);
+    let claims = EmailClaims {
+        aud: purpose.audience().to_string(),
+        sub: user_id.to_string(),
+        email: email.to_string(),
+        exp: (now + purpose.lifetime()).timestamp(),
+        jti: URL_SAFE_NO_PAD.encode(jti),
+    };
+
+    jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims, &keys.encoding)
+        .expect("HMAC signing can't fail")
+}
+
+/// The user id and email address of a valid, unused and unexpired token for `purpose`, using it
+/// up.
+pub(crate) async fn redeem(
+    state: &AppState,
+    purpose: Purpose,
+    token: &str,
+) -> Result<(i64, String)> {
+    let invalid = || Error::unprocessable_entity([("token", "is invalid or expired")]);
+
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_audience(&[purpose.audience()]);
+    validation.set_required_spec_claims(&["exp", "aud", "sub"]);
+    // Checked below against the app's clock instead.
+    validation.validate_exp = false;
+
+    let claims =
+        jsonwebtoken::decode::<EmailClaims>(token.trim(), &state.jwt.decoding, &validation)
+            .map_err(|_| invalid())?
+            .claims;
+    let expires_at = DateTime::from_timestamp(claims.exp, 0).ok_or_else(invalid)?;
+    if expires_at <= state.clock.now() {
+        return Err(invalid());
+    }
+    let user_id = claims.sub.parse().map_err(|_| invalid())?;
+
+    if !state
+        .store
+        .sessions
+        .use_email_token(&claims.jti, expires_at)
+        .await?
+    {
+        return Err(invalid());
+    }
+
+    Ok((user_id, claims.email))
+}
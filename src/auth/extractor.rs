@@ -0,0 +1,317 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use axum::async_trait;
+use axum::extract::{FromRequestParts, MatchedPath, Request, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+use axum::middleware::Next;
+use axum::response::Response;
+
+use super::api_key::{self, required_scope};
+use super::jwt::Claims;
+use super::model::{Role, Session};
+use super::session::{authenticate, SessionToken};
+use crate::app::AppState;
+use crate::common::errors::{Error, Result};
+
+/// Add this as a parameter to a handler function to require the user to be logged in.
+///
+/// Browsers are identified by the session cookie set on signup and login, API clients by the
+/// `Authorization: Bearer` access token from `/api/auth/token`, scripts by an API key in the
+/// same header. Either way handlers get the same user, so they serve all of them alike.
+///
+/// API keys are limited to the routes their scopes allow, see `api_key::required_scope`; other
+/// routes reject them with `403 Forbidden` before reaching the handler.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthUser {
+    pub user_id: i64,
+    /// Current for sessions; for access tokens, as of when they were issued.
+    pub role: Role,
+}
+
+/// Add this as a parameter to a handler function to require the user to have at least role `R`,
+/// e.g. `RequireRole<Admin>`. Rejects anyone else with `403 Forbidden`.
+///
+/// The role of an access token is checked against the user's current one, as with sessions.
+#[derive(Debug, Clone, Copy)]
+pub struct RequireRole<R> {
+    pub user: AuthUser,
+    role: PhantomData<R>,
+}
+
+/// A role as a type, to pick the one a `RequireRole` requires.
+pub trait RequiredRole {
+    const ROLE: Role;
+}
+
+/// `RequireRole<Premium>` accepts premium users and admins.
+#[derive(Debug, Clone, Copy)]
+pub struct Premium;
+
+impl RequiredRole for Premium {
+    const ROLE: Role = Role::Premium;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Admin;
+
+impl RequiredRole for Admin {
+    const ROLE: Role = Role::Admin;
+}
+
+/// Add this as a parameter to a handler function to optionally check if the user is logged in.
+///
+/// This is `Self(None)` without credentials, and with a session cookie that is no longer valid:
+/// browsers keep sending those until told otherwise. An `Authorization` header is always
+/// checked though, a bad access token is rejected rather than treated as anonymous.
+///
+/// This is in contrast to directly using `Option<AuthUser>`, which would be `None` on errors
+/// such as the database being unavailable too.
+#[derive(Debug, Clone, Copy)]
+pub struct MaybeAuthUser(pub Option<AuthUser>);
+
+impl MaybeAuthUser {
+    /// If this is `Self(Some(AuthUser))`, return `AuthUser::user_id`
+    pub fn user_id(&self) -> Option<i64> {
+        self.0.map(|auth_user| auth_user.user_id)
+    }
+}
+
+/// The session a request was made with, for handlers managing sessions themselves.
+///
+/// Looked up once per request, however many extractors need it.
+#[derive(Debug, Clone)]
+pub struct CurrentSession(pub Session);
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for CurrentSession {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self> {
+        if let Some(current) = parts.extensions.get::<Self>() {
+            return Ok(current.clone());
+        }
+
+        let token = SessionToken::from_headers(&parts.headers).ok_or(Error::Unauthorized)?;
+        let session = authenticate(&state.store, &token)
+            .await?
+            .ok_or(Error::Unauthorized)?;
+
+        parts.extensions.insert(Self(session.clone()));
+        Ok(Self(session))
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for MaybeAuthUser {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self> {
+        if let Some(maybe_auth_user) = parts.extensions.get::<Self>() {
+            return Ok(*maybe_auth_user);
+        }
+
+        // `require_api_key_scope` accepts API keys, leaving their user in the extensions, so
+        // this is a route without it.
+        if api_key::from_headers(&parts.headers).is_some() {
+            return Err(Error::Forbidden);
+        }
+
+        let auth_user = if parts.headers.contains_key(AUTHORIZATION) {
+            let claims = Claims::from_request_parts(parts, state).await?;
+            let auth_user = AuthUser {
+                user_id: claims.user_id().ok_or(Error::Unauthorized)?,
+                role: claims.role(),
+            };
+            parts.extensions.insert(claims);
+            Some(auth_user)
+        } else if SessionToken::from_headers(&parts.headers).is_some() {
+            match CurrentSession::from_request_parts(parts, state).await {
+                Ok(CurrentSession(session)) => {
+                    // Suspending a user deletes their sessions, this only closes the gap of a
+                    // request racing with the suspension.
+                    let role = state.store.users.active_role(session.user_id).await?;
+                    role.map(|role| AuthUser {
+                        user_id: session.user_id,
+                        role,
+                    })
+                }
+                Err(Error::Unauthorized) => None,
+                Err(error) => return Err(error),
+            }
+        } else {
+            None
+        };
+
+        let maybe_auth_user = Self(auth_user);
+        parts.extensions.insert(maybe_auth_user);
+        Ok(maybe_auth_user)
+    }
+}
+
+/// Route layer holding API keys to the scopes of their routes, see `api_key::required_scope`.
+///
+/// This covers every route, not just the ones whose handlers look at the caller, such as the
+/// public market data. The key's user is left for the `AuthUser` and `MaybeAuthUser` of the
+/// handler; routes without the layer refuse API keys.
+pub(crate) async fn require_api_key_scope(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    let (mut parts, body) = request.into_parts();
+
+    if let Some(key) = api_key::from_headers(&parts.headers) {
+        let user = api_key::authenticate(&state.store, key)
+            .await?
+            .ok_or(Error::Unauthorized)?;
+        let scope = parts
+            .extensions
+            .get::<MatchedPath>()
+            .and_then(|path| required_scope(&parts.method, path.as_str()));
+        if !scope.is_some_and(|scope| user.scopes.contains(scope)) {
+            return Err(Error::Forbidden);
+        }
+
+        parts.extensions.insert(MaybeAuthUser(Some(AuthUser {
+            user_id: user.user_id,
+            role: user.role,
+        })));
+    }
+
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for AuthUser {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self> {
+        let MaybeAuthUser(auth_user) = MaybeAuthUser::from_request_parts(parts, state).await?;
+        auth_user.ok_or(Error::Unauthorized)
+    }
+}
+
+#[async_trait]
+impl<R: RequiredRole + Send + Sync> FromRequestParts<Arc<AppState>> for RequireRole<R> {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self> {
+        let user = AuthUser::from_request_parts(parts, state).await?;
+        // Sessions and API keys are checked against the user as is, access tokens only carry
+        // the role as of when they were issued. A suspended or demoted user mustn't keep what
+        // roles guard until theirs expires.
+        if parts.extensions.get::<Claims>().is_some() {
+            let role = state
+                .store
+                .users
+                .active_role(user.user_id)
+                .await?
+                .ok_or(Error::Unauthorized)?;
+            if role < user.role {
+                return Err(Error::Forbidden);
+            }
+        }
+        if user.role < R::ROLE {
+            return Err(Error::Forbidden);
+        }
+
+        Ok(Self {
+            user,
+            role: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::jwt::access_token;
+    use crate::auth::test_session_cookie;
+    use crate::conn::test_database;
+    use crate::testing::{insert_user, send_with, TestResponse};
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use axum::{Json, Router};
+
+    async fn call(app: &Router, uri: &str, headers: &[(&str, &str)]) -> TestResponse {
+        send_with(app, "GET", uri, headers, None).await
+    }
+
+    #[tokio::test]
+    async fn cookie_or_bearer() {
+        let db = test_database().await;
+        insert_user(&db, 1, "alice", Role::User).await;
+        let cookie = test_session_cookie(&db, 1).await;
+        let state = Arc::new(AppState::new(db.clone()));
+        let bearer = format!("Bearer {}", access_token(&state.jwt, 1, vec![Role::User]).0);
+        let app = crate::auth::router()
+            .route(
+                "/maybe",
+                get(|user: MaybeAuthUser| async move { Json(user.user_id()) }),
+            )
+            .with_state(state);
+
+        // Browser and API client get the same user.
+        let by_cookie = call(&app, "/api/user", &[("cookie", &cookie)]).await;
+        assert_eq!(by_cookie.status, StatusCode::OK);
+        let by_bearer = call(&app, "/api/user", &[("authorization", &bearer)]).await;
+        assert_eq!(by_bearer.status, StatusCode::OK);
+        assert_eq!(by_cookie.body, by_bearer.body);
+        assert_eq!(by_cookie.body["user"]["username"], "alice");
+
+        let response = call(&app, "/api/user", &[]).await;
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+        let response = call(&app, "/maybe", &[("authorization", &bearer)]).await;
+        assert_eq!(response.body, 1);
+        let response = call(&app, "/maybe", &[]).await;
+        assert!(response.body.is_null());
+
+        // A bad token is an error, even with a valid cookie alongside.
+        let bad = [("authorization", "Bearer nope"), ("cookie", &cookie)];
+        let response = call(&app, "/maybe", &bad).await;
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+        let response = call(&app, "/maybe", &[("authorization", "Token nope")]).await;
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+
+        // A stale cookie is just anonymous.
+        sqlx::query("DELETE FROM session")
+            .execute(&db)
+            .await
+            .unwrap();
+        let response = call(&app, "/maybe", &[("cookie", &cookie)]).await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert!(response.body.is_null());
+        let response = call(&app, "/api/user", &[("cookie", &cookie)]).await;
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn access_tokens_lose_roles_taken_away() {
+        let db = test_database().await;
+        insert_user(&db, 1, "root", Role::Admin).await;
+        let state = Arc::new(AppState::new(db.clone()));
+        let bearer = format!(
+            "Bearer {}",
+            access_token(&state.jwt, 1, vec![Role::Admin]).0
+        );
+        let app = crate::app::api_router(state);
+        let headers = [("authorization", bearer.as_str())];
+        let list_users = || call(&app, "/api/admin/users", &headers);
+
+        assert_eq!(list_users().await.status, StatusCode::OK);
+
+        sqlx::query("UPDATE user SET role = 'user' WHERE user_id = 1")
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(list_users().await.status, StatusCode::FORBIDDEN);
+
+        sqlx::query("UPDATE user SET suspended_at = '2024-01-01T00:00:00Z' WHERE user_id = 1")
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(list_users().await.status, StatusCode::UNAUTHORIZED);
+    }
+}
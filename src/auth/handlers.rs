@@ -0,0 +1,332 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::header::SET_COOKIE;
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::Router;
+use chrono::Utc;
+
+use super::email::send_verification;
+use super::extractor::{AuthUser, CurrentSession};
+use super::model::User;
+use super::password::{hash_password, verify_password, DUMMY_HASH};
+use super::session::{create_session, delete_session, rotate_session, user_agent, SessionToken};
+use super::two_factor::start_challenge;
+use crate::app::AppState;
+use crate::common::errors::{Error, Result};
+use crate::common::extract::Json;
+use crate::store::{Registration, Store, StoreError};
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/auth/signup", post(signup))
+        .route("/api/auth/login", post(login))
+        .route("/api/auth/logout", post(logout))
+        .route("/api/auth/password", put(change_password))
+        .route("/api/user", get(current_user))
+}
+
+/// A wrapper type for all requests/responses from these routes.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(super) struct UserBody<T> {
+    pub user: T,
+}
+
+#[derive(serde::Deserialize)]
+struct NewUser {
+    username: String,
+    email: String,
+    password: String,
+}
+
+#[derive(serde::Deserialize)]
+pub(super) struct LoginUser {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChangePassword {
+    current_password: String,
+    new_password: String,
+}
+
+pub(super) const PASSWORD_RULE: &str = "must be 8-128 characters";
+
+pub(super) fn valid_password(password: &str) -> bool {
+    (8..=128).contains(&password.chars().count())
+}
+
+/// Responses logging the user in carry the session cookie.
+pub(super) type LoggedIn = ([(HeaderName, HeaderValue); 1], Json<UserBody<User>>);
+
+async fn signup(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<UserBody<NewUser>>,
+) -> Result<(StatusCode, LoggedIn)> {
+    let new = req.user;
+    let username = new.username.trim();
+    let email = new.email.trim();
+    validate_signup(username, email, &new.password)?;
+
+    let password_hash = hash_password(new.password).await?;
+
+    let registration = Registration {
+        username: username.to_string(),
+        email: email.to_string(),
+        password_hash,
+    };
+    let user = state
+        .store
+        .users
+        .create_user(&registration, Utc::now())
+        .await
+        .map_err(|e| match e {
+            StoreError::Conflict(field) => {
+                Error::unprocessable_entity([(field, format!("{field} taken"))])
+            }
+            e => e.into(),
+        })?;
+
+    // The account is usable right away, an address that can't be verified only shows as such.
+    if let Err(error) = send_verification(&state, user.user_id, &user.email).await {
+        tracing::warn!("sending verification email failed: {error:#}");
+    }
+
+    let token = create_session(&state.store, user.user_id, user_agent(&headers)).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        ([(SET_COOKIE, token.cookie())], Json(UserBody { user })),
+    ))
+}
+
+/// The checks of a new account's username, email and password, as given without surrounding
+/// whitespace.
+pub(crate) fn validate_signup(username: &str, email: &str, password: &str) -> Result<()> {
+    let mut errors = Vec::new();
+    if !(1..=32).contains(&username.len())
+        || !username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+    {
+        errors.push(("username", "must be 1-32 letters, digits, `_` or `-`"));
+    }
+    if email.len() > 254
+        || !email
+            .split_once('@')
+            .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'))
+    {
+        errors.push(("email", "must be an email address"));
+    }
+    if !valid_password(password) {
+        errors.push(("password", PASSWORD_RULE));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::unprocessable_entity(errors))
+    }
+}
+
+// Unknown emails and wrong passwords are both a bare 401, not telling which accounts exist.
+//
+// Users with two-factor authentication get a challenge to answer at `/api/auth/login/2fa`
+// instead of a session.
+async fn login(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<UserBody<LoginUser>>,
+) -> Result<Response> {
+    let user_id = check_credentials(&state.store, req.user).await?;
+
+    if let Some(challenge) = start_challenge(&state, user_id).await? {
+        return Ok(challenge.into_response());
+    }
+    Ok(log_in(&state, user_id, &headers).await?.into_response())
+}
+
+/// Start a session of `user_id`, whose credentials were checked.
+pub(super) async fn log_in(
+    state: &AppState,
+    user_id: i64,
+    headers: &HeaderMap,
+) -> Result<LoggedIn> {
+    let user = state
+        .store
+        .users
+        .user(user_id)
+        .await?
+        .ok_or(Error::Unauthorized)?;
+    let token = create_session(&state.store, user_id, user_agent(headers)).await?;
+
+    Ok(([(SET_COOKIE, token.cookie())], Json(UserBody { user })))
+}
+
+/// The id of the user `credentials` are valid for, `Error::Unauthorized` otherwise.
+///
+/// Suspended users get `Error::Forbidden`, but only with the right password.
+pub(super) async fn check_credentials(store: &Store, credentials: LoginUser) -> Result<i64> {
+    let Some(found) = store.users.credentials(credentials.email.trim()).await? else {
+        // As slow as a wrong password, not to tell which emails are registered.
+        let _ = verify_password(credentials.password, DUMMY_HASH.to_string()).await;
+        return Err(Error::Unauthorized);
+    };
+
+    verify_password(credentials.password, found.password_hash).await?;
+    if found.suspended {
+        return Err(Error::Forbidden);
+    }
+    Ok(found.user_id)
+}
+
+async fn current_user(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<UserBody<User>>> {
+    let user = state
+        .store
+        .users
+        .user(auth_user.user_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    Ok(Json(UserBody { user }))
+}
+
+// Always succeeds, clearing the cookie even if the session was already gone.
+async fn logout(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 1])> {
+    if let Some(token) = SessionToken::from_headers(&headers) {
+        delete_session(&state.store, &token).await?;
+    }
+
+    Ok((
+        StatusCode::NO_CONTENT,
+        [(SET_COOKIE, SessionToken::removal_cookie())],
+    ))
+}
+
+// Other sessions and every refresh token are logged out, and the current session gets a new
+// token: whoever may have known the old password or token doesn't keep access.
+async fn change_password(
+    CurrentSession(session): CurrentSession,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<ChangePassword>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 1])> {
+    if !valid_password(&req.new_password) {
+        return Err(Error::unprocessable_entity([(
+            "newPassword",
+            PASSWORD_RULE,
+        )]));
+    }
+
+    let password_hash = state
+        .store
+        .users
+        .password_hash(session.user_id)
+        .await?
+        .ok_or(Error::Unauthorized)?;
+    verify_password(req.current_password, password_hash)
+        .await
+        .map_err(|_| Error::unprocessable_entity([("currentPassword", "is wrong")]))?;
+
+    let password_hash = hash_password(req.new_password).await?;
+    state
+        .store
+        .users
+        .set_password(
+            session.user_id,
+            &password_hash,
+            Some(session.session_id),
+            state.clock.now(),
+        )
+        .await?;
+    let token = rotate_session(&state.store, session.session_id).await?;
+
+    Ok((StatusCode::NO_CONTENT, [(SET_COOKIE, token.cookie())]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conn::test_database;
+    use crate::testing::send_with;
+
+    #[tokio::test]
+    async fn signup_login_logout() {
+        let db = test_database().await;
+        let app = router()
+            .route(
+                "/whoami",
+                get(|user: AuthUser| async move { user.user_id.to_string() }),
+            )
+            .with_state(Arc::new(AppState::new(db)));
+        let whoami = |cookie: String| {
+            let app = app.clone();
+            async move {
+                let headers = [("cookie", cookie.as_str())];
+                send_with(&app, "GET", "/whoami", &headers, None)
+                    .await
+                    .status
+            }
+        };
+
+        let alice = serde_json::json!({"user": {"username": "alice", "email": "alice@example.com", "password": "correct horse"}});
+        let response = send_with(&app, "POST", "/api/auth/signup", &[], Some(alice)).await;
+        assert_eq!(response.status, StatusCode::CREATED);
+        assert_eq!(response.body["user"]["username"], "alice");
+        assert!(response.body["user"].get("passwordHash").is_none());
+        assert_eq!(whoami(response.cookie.unwrap()).await, StatusCode::OK);
+
+        // SQLite reports one violated constraint at a time.
+        let taken = serde_json::json!({"user": {"username": "Alice", "email": "alice@example.org", "password": "correct horse"}});
+        let response = send_with(&app, "POST", "/api/auth/signup", &[], Some(taken)).await;
+        assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.body["errors"]["username"][0], "username taken");
+        let shouting = serde_json::json!({"user": {"username": "bob", "email": "ALICE@example.com", "password": "correct horse"}});
+        let response = send_with(&app, "POST", "/api/auth/signup", &[], Some(shouting)).await;
+        assert_eq!(response.body["errors"]["email"][0], "email taken");
+        let invalid = serde_json::json!({"user": {"username": "bob smith", "email": "bob", "password": "short"}});
+        let response = send_with(&app, "POST", "/api/auth/signup", &[], Some(invalid)).await;
+        assert_eq!(response.body["errors"].as_object().unwrap().len(), 3);
+
+        let wrong = serde_json::json!({"user": {"email": "alice@example.com", "password": "battery staple"}});
+        let response = send_with(&app, "POST", "/api/auth/login", &[], Some(wrong)).await;
+        assert_eq!(
+            (response.status, response.cookie),
+            (StatusCode::UNAUTHORIZED, None)
+        );
+
+        let unknown = serde_json::json!({"user": {"email": "mallory@example.com", "password": "correct horse"}});
+        let response = send_with(&app, "POST", "/api/auth/login", &[], Some(unknown)).await;
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+        // A dummy that didn't parse would refuse unknown emails quicker than wrong passwords.
+        assert!(argon2::PasswordHash::new(DUMMY_HASH).is_ok());
+
+        let right = serde_json::json!({"user": {"email": "alice@example.com", "password": "correct horse"}});
+        let response = send_with(&app, "POST", "/api/auth/login", &[], Some(right)).await;
+        assert_eq!(response.status, StatusCode::OK);
+        let cookie = response.cookie.unwrap();
+        assert_eq!(whoami(cookie.clone()).await, StatusCode::OK);
+
+        let response = send_with(
+            &app,
+            "POST",
+            "/api/auth/logout",
+            &[("cookie", &cookie)],
+            Some(serde_json::json!({})),
+        )
+        .await;
+        assert_eq!(response.status, StatusCode::NO_CONTENT);
+        assert_eq!(response.cookie.as_deref(), Some("session="));
+        assert_eq!(whoami(cookie).await, StatusCode::UNAUTHORIZED);
+    }
+}
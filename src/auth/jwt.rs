@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+use chrono::{Duration, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use super::model::Role;
+use crate::app::AppState;
+use crate::common::errors::{Error, Result};
+
+/// Access tokens are only checked by signature, this is how long a revoked one stays usable.
+pub(crate) const ACCESS_TOKEN_MINUTES: i64 = 15;
+
+/// HMAC keys signing and verifying access tokens, and the tokens mailed to users.
+pub struct JwtKeys {
+    pub(super) encoding: EncodingKey,
+    pub(super) decoding: DecodingKey,
+}
+
+impl JwtKeys {
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+        }
+    }
+
+    /// Keys from a random secret, for when none is configured.
+    ///
+    /// Access tokens then don't survive a restart, clients have to use their refresh token.
+    pub fn random() -> Self {
+        let mut secret = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut secret);
+        Self::new(&secret)
+    }
+}
+
+/// What an access token says about its bearer, and nothing else: no personal data, as JWTs are
+/// only signed, not encrypted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claims {
+    /// The user id, as a string per RFC 7519.
+    pub sub: String,
+    pub roles: Vec<Role>,
+    /// Expiry, in seconds since the epoch.
+    pub exp: i64,
+}
+
+impl Claims {
+    pub fn user_id(&self) -> Option<i64> {
+        self.sub.parse().ok()
+    }
+
+    /// The highest role of the bearer, as of when the token was issued.
+    pub fn role(&self) -> Role {
+        self.roles.iter().copied().max().unwrap_or_default()
+    }
+}
+
+/// Issue an access token of `user_id`, returning it with its lifetime in seconds.
+pub(crate) fn access_token(keys: &JwtKeys, user_id: i64, roles: Vec<Role>) -> (String, i64) {
+    let lifetime = Duration::minutes(ACCESS_TOKEN_MINUTES);
+    let claims = Claims {
+        sub: user_id.to_string(),
+        roles,
+        exp: (Utc::now() + lifetime).timestamp(),
+    };
+
+    let token = jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims, &keys.encoding)
+        .expect("HMAC signing can't fail");
+    (token, lifetime.num_seconds())
+}
+
+/// The claims of a valid, unexpired access token.
+pub(crate) fn verify(keys: &JwtKeys, token: &str) -> Option<Claims> {
+    // Tokens with an audience, such as the ones mailed out, are refused as access tokens as
+    // none is set here.
+    let mut validation = Validation::new(Algorithm::HS256);
+    // Expired is expired, no grace period.
+    validation.leeway = 0;
+
+    jsonwebtoken::decode::<Claims>(token, &keys.decoding, &validation)
+        .ok()
+        .map(|data| data.claims)
+}
+
+/// The claims of the `Authorization: Bearer` access token of a request.
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for Claims {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self> {
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(Error::Unauthorized)?;
+
+        verify(&state.jwt, token.trim()).ok_or(Error::Unauthorized)
+    }
+}
@@ -0,0 +1,41 @@
+mod api_key;
+mod api_keys;
+mod email;
+mod email_token;
+mod extractor;
+mod handlers;
+pub mod jwt;
+pub mod model;
+mod password;
+mod session;
+mod sessions;
+mod tokens;
+mod totp;
+mod two_factor;
+
+pub use extractor::{
+    Admin, AuthUser, CurrentSession, MaybeAuthUser, Premium, RequireRole, RequiredRole,
+};
+pub use jwt::{Claims, JwtKeys};
+pub use model::{Role, Scope};
+
+use std::sync::Arc;
+
+use axum::Router;
+
+use crate::app::AppState;
+
+pub fn router() -> Router<Arc<AppState>> {
+    handlers::router()
+        .merge(sessions::router())
+        .merge(api_keys::router())
+        .merge(tokens::router())
+        .merge(two_factor::router())
+        .merge(email::router())
+}
+
+pub(crate) use extractor::require_api_key_scope;
+pub(crate) use handlers::validate_signup;
+pub(crate) use password::hash_password;
+#[cfg(test)]
+pub(crate) use session::test_session_cookie;
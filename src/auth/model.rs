@@ -0,0 +1,126 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// What a user may do. Roles are ordered, each one allowing what the ones before it do.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, sqlx::Type, Serialize, Deserialize,
+)]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    User,
+    Premium,
+    Admin,
+}
+
+/// A user account, without its credentials.
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    #[serde(rename = "id")]
+    pub user_id: i64,
+    pub username: String,
+    pub email: String,
+    pub role: Role,
+    /// Unknown for users created before signup was in place.
+    pub created_at: Option<DateTime<Utc>>,
+    /// When the user proved they own `email`, by following the link mailed to it.
+    pub email_verified_at: Option<DateTime<Utc>>,
+}
+
+/// A login of a user on some device.
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    #[serde(rename = "id")]
+    pub session_id: i64,
+    #[serde(skip)]
+    pub user_id: i64,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// What an API key may be used for. Sessions and access tokens may do anything their user may.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scope {
+    #[serde(rename = "read:portfolio")]
+    ReadPortfolio,
+    #[serde(rename = "write:transactions")]
+    WriteTransactions,
+    #[serde(rename = "read:market")]
+    ReadMarket,
+}
+
+impl Scope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ReadPortfolio => "read:portfolio",
+            Self::WriteTransactions => "write:transactions",
+            Self::ReadMarket => "read:market",
+        }
+    }
+}
+
+impl std::str::FromStr for Scope {
+    type Err = String;
+
+    fn from_str(scope: &str) -> Result<Self, Self::Err> {
+        [
+            Self::ReadPortfolio,
+            Self::WriteTransactions,
+            Self::ReadMarket,
+        ]
+        .into_iter()
+        .find(|known| known.as_str() == scope)
+        .ok_or_else(|| format!("unknown scope `{scope}`"))
+    }
+}
+
+/// The scopes of an API key, stored space separated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct Scopes(pub Vec<Scope>);
+
+impl Scopes {
+    pub fn contains(&self, scope: Scope) -> bool {
+        self.0.contains(&scope)
+    }
+}
+
+impl TryFrom<String> for Scopes {
+    type Error = String;
+
+    fn try_from(scopes: String) -> Result<Self, Self::Error> {
+        scopes
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl std::fmt::Display for Scopes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let scopes: Vec<_> = self.0.iter().map(|scope| scope.as_str()).collect();
+        f.write_str(&scopes.join(" "))
+    }
+}
+
+/// A key of a user's scripts, without the key itself.
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKey {
+    #[serde(rename = "id")]
+    pub api_key_id: i64,
+    pub name: String,
+    pub prefix: String,
+    #[sqlx(try_from = "String")]
+    pub scopes: Scopes,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub request_count: i64,
+}
@@ -0,0 +1,40 @@
+use anyhow::Context;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+
+use crate::common::errors::{Error, Result};
+
+/// Hash `password` into a PHC string (`$argon2id$v=19$...`) with a random salt.
+///
+/// Hashing is deliberately slow, so it runs on the blocking thread pool rather than stalling the
+/// async runtime.
+pub(crate) async fn hash_password(password: String) -> Result<String> {
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut rand::rngs::OsRng);
+        Ok(Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| anyhow::anyhow!("failed to generate password hash: {e}"))?
+            .to_string())
+    })
+    .await
+    .context("panic in generating password hash")?
+}
+
+/// A hash with the parameters of `hash_password` matching no password anyone would use, to
+/// check a password against when there is no user, so unknown emails take as long to refuse.
+pub(crate) const DUMMY_HASH: &str =
+    "$argon2id$v=19$m=19456,t=2,p=1$SNgcFi8LPnIF5cdfXboqKg$GRazMkX3leMTRYbFQ0gu3on5iniS5wDSSM3JBFsgKIU";
+
+/// Check `password` against a PHC string, `Error::Unauthorized` if it doesn't match.
+///
+/// Hashes that can't be parsed, e.g. of users created before passwords were hashed, never match.
+pub(crate) async fn verify_password(password: String, password_hash: String) -> Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let hash = PasswordHash::new(&password_hash).map_err(|_| Error::Unauthorized)?;
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .map_err(|_| Error::Unauthorized)
+    })
+    .await
+    .context("panic in verifying password hash")?
+}
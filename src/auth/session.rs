@@ -0,0 +1,145 @@
+use axum::http::{HeaderMap, HeaderValue};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{Duration, Utc};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use super::model::Session;
+use crate::common::cookie::get_cookie;
+use crate::common::errors::Result;
+use crate::store::Store;
+
+/// Name of the cookie holding the session token.
+pub(crate) const SESSION_COOKIE: &str = "session";
+
+/// Sessions not used for this many days expire.
+const SESSION_IDLE_DAYS: i64 = 14;
+
+/// However active, sessions have to log in again after this many days.
+const SESSION_MAX_DAYS: i64 = 90;
+
+/// `last_seen_at` is only written this often rather than on every request.
+const LAST_SEEN_RESOLUTION_SECS: i64 = 60;
+
+/// A random token identifying a login, handed to the client in the session cookie.
+///
+/// Only its SHA-256 hash is stored: the token has 256 bits of entropy, so unlike passwords it
+/// doesn't need a slow hash.
+pub(crate) struct SessionToken(String);
+
+impl SessionToken {
+    fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut bytes);
+        Self(URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    /// The token of the request's session cookie, if any.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        get_cookie(headers, SESSION_COOKIE)
+            .filter(|token| !token.is_empty())
+            .map(Self)
+    }
+
+    pub fn hash(&self) -> Vec<u8> {
+        Sha256::digest(self.0.as_bytes()).to_vec()
+    }
+
+    /// `Set-Cookie` value handing the token to the client.
+    ///
+    /// The cookie lives as long as a session can, idle sessions are expired server side.
+    pub fn cookie(&self) -> HeaderValue {
+        let cookie = format!(
+            "{SESSION_COOKIE}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}",
+            self.0,
+            Duration::days(SESSION_MAX_DAYS).num_seconds()
+        );
+        HeaderValue::from_str(&cookie).expect("tokens are valid header values")
+    }
+
+    /// `Set-Cookie` value removing the session cookie from the client.
+    pub fn removal_cookie() -> HeaderValue {
+        HeaderValue::from_static("session=; Path=/; HttpOnly; SameSite=Lax; Max-Age=0")
+    }
+}
+
+/// The `User-Agent` of a request, to tell sessions apart when listing them.
+pub(crate) fn user_agent(headers: &HeaderMap) -> Option<String> {
+    let user_agent = headers.get(axum::http::header::USER_AGENT)?.to_str().ok()?;
+    Some(user_agent.chars().take(256).collect())
+}
+
+/// Log `user_id` in, returning the token of the new session.
+pub(crate) async fn create_session(
+    store: &Store,
+    user_id: i64,
+    user_agent: Option<String>,
+) -> Result<SessionToken> {
+    let token = SessionToken::generate();
+    let now = Utc::now();
+
+    store
+        .sessions
+        .create_session(
+            &token.hash(),
+            user_id,
+            user_agent.as_deref(),
+            now,
+            now + Duration::days(SESSION_IDLE_DAYS),
+        )
+        .await?;
+
+    Ok(token)
+}
+
+/// The unexpired session of `token`, pushing back its expiry as it is being used.
+pub(crate) async fn authenticate(store: &Store, token: &SessionToken) -> Result<Option<Session>> {
+    let now = Utc::now();
+    let Some(mut session) = store.sessions.session(&token.hash(), now).await? else {
+        return Ok(None);
+    };
+    if now - session.last_seen_at < Duration::seconds(LAST_SEEN_RESOLUTION_SECS) {
+        return Ok(Some(session));
+    }
+
+    session.last_seen_at = now;
+    session.expires_at = (now + Duration::days(SESSION_IDLE_DAYS))
+        .min(session.created_at + Duration::days(SESSION_MAX_DAYS));
+    store
+        .sessions
+        .touch_session(session.session_id, session.last_seen_at, session.expires_at)
+        .await?;
+
+    Ok(Some(session))
+}
+
+/// Replace the token of a session, e.g. when its user's privileges change, so a token captured
+/// before can't be used with the new privileges.
+pub(crate) async fn rotate_session(store: &Store, session_id: i64) -> Result<SessionToken> {
+    let token = SessionToken::generate();
+    store
+        .sessions
+        .set_session_token(session_id, &token.hash())
+        .await?;
+
+    Ok(token)
+}
+
+pub(crate) async fn delete_session(store: &Store, token: &SessionToken) -> Result<()> {
+    store
+        .sessions
+        .delete_session_by_token(&token.hash())
+        .await?;
+
+    Ok(())
+}
+
+/// `Cookie` header value of a new session of `user_id`, for handler tests.
+#[cfg(test)]
+pub(crate) async fn test_session_cookie(db: &sqlx::SqlitePool, user_id: i64) -> String {
+    let token = create_session(&Store::sqlite(db.clone()), user_id, None)
+        .await
+        .unwrap();
+    format!("{SESSION_COOKIE}={}", token.0)
+}
@@ -0,0 +1,255 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::header::SET_COOKIE;
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use axum::routing::{delete, get};
+use axum::Router;
+use chrono::Utc;
+
+use super::extractor::CurrentSession;
+use super::model::Session;
+use super::session::SessionToken;
+use crate::app::AppState;
+use crate::auth::AuthUser;
+use crate::common::errors::{Error, Result};
+use crate::common::extract::{Json, Path};
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/sessions", get(list_sessions).delete(delete_sessions))
+        .route("/api/sessions/:id", delete(delete_session))
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionView {
+    #[serde(flatten)]
+    session: Session,
+    /// Whether this is the session making the request.
+    current: bool,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MultipleSessionsBody {
+    sessions: Vec<SessionView>,
+    sessions_count: usize,
+}
+
+// The devices the user is logged in on, most recently used first.
+async fn list_sessions(
+    CurrentSession(current): CurrentSession,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<MultipleSessionsBody>> {
+    let sessions = state
+        .store
+        .sessions
+        .list_sessions(current.user_id, Utc::now())
+        .await?;
+
+    let sessions: Vec<SessionView> = sessions
+        .into_iter()
+        .map(|session| SessionView {
+            current: session.session_id == current.session_id,
+            session,
+        })
+        .collect();
+
+    Ok(Json(MultipleSessionsBody {
+        sessions_count: sessions.len(),
+        sessions,
+    }))
+}
+
+// Log out everywhere, this device and API clients' refresh tokens included.
+async fn delete_sessions(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 1])> {
+    state
+        .store
+        .sessions
+        .log_out_everywhere(auth_user.user_id, None, state.clock.now())
+        .await?;
+
+    Ok((
+        StatusCode::NO_CONTENT,
+        [(SET_COOKIE, SessionToken::removal_cookie())],
+    ))
+}
+
+async fn delete_session(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<i64>,
+) -> Result<StatusCode> {
+    let deleted = state
+        .store
+        .sessions
+        .delete_session(auth_user.user_id, session_id)
+        .await?;
+
+    if !deleted {
+        return Err(Error::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{test_session_cookie, Role};
+    use crate::conn::test_database;
+    use crate::testing::{insert_user, insert_user_with_password, send, send_with};
+    use chrono::{DateTime, Duration};
+
+    async fn expires_at(db: &sqlx::SqlitePool, session_id: i64) -> DateTime<Utc> {
+        sqlx::query_scalar("SELECT expires_at FROM session WHERE session_id = $1")
+            .bind(session_id)
+            .fetch_one(db)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn list_expire_and_revoke_sessions() {
+        let db = test_database().await;
+        insert_user(&db, 1, "alice", Role::User).await;
+        insert_user(&db, 2, "bob", Role::User).await;
+        let laptop = test_session_cookie(&db, 1).await;
+        let phone = test_session_cookie(&db, 1).await;
+        let bob = test_session_cookie(&db, 2).await;
+        let app = router().with_state(Arc::new(AppState::new(db.clone())));
+
+        let (status, body) = send(&app, "GET", "/api/sessions", &laptop, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["sessionsCount"], 2);
+        let current: Vec<_> = body["sessions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|session| session["current"] == true)
+            .collect();
+        assert_eq!(current.len(), 1);
+        let laptop_id = current[0]["id"].as_i64().unwrap();
+
+        let uri = format!("/api/sessions/{laptop_id}");
+        let (status, _) = send(&app, "DELETE", &uri, &bob, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // Used a while ago and about to expire, using it pushes back its expiry.
+        sqlx::query("UPDATE session SET last_seen_at = $1, expires_at = $2")
+            .bind(Utc::now() - Duration::days(13))
+            .bind(Utc::now() + Duration::hours(1))
+            .execute(&db)
+            .await
+            .unwrap();
+        send(&app, "GET", "/api/sessions", &laptop, None).await;
+        assert!(expires_at(&db, laptop_id).await > Utc::now() + Duration::days(13));
+
+        // Expired sessions are neither accepted nor listed.
+        sqlx::query("UPDATE session SET expires_at = $1 WHERE user_id = 1 AND session_id != $2")
+            .bind(Utc::now())
+            .bind(laptop_id)
+            .execute(&db)
+            .await
+            .unwrap();
+        let (status, _) = send(&app, "GET", "/api/sessions", &phone, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (_, body) = send(&app, "GET", "/api/sessions", &laptop, None).await;
+        assert_eq!(body["sessionsCount"], 1);
+
+        let (status, _) = send(&app, "DELETE", "/api/sessions", &laptop, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(&app, "GET", "/api/sessions", &laptop, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(&app, "GET", "/api/sessions", &bob, None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn password_change_rotates_and_revokes() {
+        let db = test_database().await;
+        insert_user_with_password(&db, 1, "alice", "correct horse").await;
+        let laptop = test_session_cookie(&db, 1).await;
+        let phone = test_session_cookie(&db, 1).await;
+        let app = crate::auth::router().with_state(Arc::new(AppState::new(db)));
+        let refresh_token = api_client_login(&app).await;
+
+        let change = |current: &str| serde_json::json!({"currentPassword": current, "newPassword": "battery staple"});
+        let (status, body) = send(
+            &app,
+            "PUT",
+            "/api/auth/password",
+            &laptop,
+            Some(change("wrong")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body["errors"]["currentPassword"].is_array());
+
+        let response = send_with(
+            &app,
+            "PUT",
+            "/api/auth/password",
+            &[("cookie", &laptop)],
+            Some(change("correct horse")),
+        )
+        .await;
+        assert_eq!(response.status, StatusCode::NO_CONTENT);
+
+        for stale in [laptop, phone] {
+            let (status, _) = send(&app, "GET", "/api/sessions", &stale, None).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+        }
+        let (_, body) = send(
+            &app,
+            "GET",
+            "/api/sessions",
+            &response.cookie.unwrap(),
+            None,
+        )
+        .await;
+        assert_eq!(body["sessionsCount"], 1);
+        let (status, _) = send(
+            &app,
+            "POST",
+            "/api/auth/token/refresh",
+            "",
+            Some(refresh_token),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn logging_out_everywhere_revokes_refresh_tokens() {
+        let db = test_database().await;
+        insert_user_with_password(&db, 1, "alice", "correct horse").await;
+        let laptop = test_session_cookie(&db, 1).await;
+        let app = crate::auth::router().with_state(Arc::new(AppState::new(db)));
+        let refresh_token = api_client_login(&app).await;
+
+        let (status, _) = send(&app, "DELETE", "/api/sessions", &laptop, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(
+            &app,
+            "POST",
+            "/api/auth/token/refresh",
+            "",
+            Some(refresh_token),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    /// Log alice in as an API client, returning the body to refresh her tokens with.
+    async fn api_client_login(app: &Router) -> serde_json::Value {
+        let login = serde_json::json!({"user": {"email": "alice@example.com", "password": "correct horse"}});
+        let (status, body) = send(app, "POST", "/api/auth/token", "", Some(login)).await;
+        assert_eq!(status, StatusCode::OK);
+        serde_json::json!({"refreshToken": body["refreshToken"]})
+    }
+}
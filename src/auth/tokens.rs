@@ -0,0 +1,388 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::Router;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{Duration, Utc};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use super::handlers::{check_credentials, LoginUser, UserBody};
+use super::jwt::access_token;
+use super::model::Role;
+use super::two_factor::start_challenge;
+use crate::app::AppState;
+use crate::common::errors::{Error, Result};
+use crate::common::extract::Json;
+use crate::store::Rotation;
+
+/// Refresh tokens not used for this many days expire, each refresh starts the period over.
+const REFRESH_TOKEN_DAYS: i64 = 30;
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/auth/token", post(issue_tokens))
+        .route("/api/auth/token/refresh", post(refresh_tokens))
+        .route("/api/auth/token/revoke", post(revoke_tokens))
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct TokenBody {
+    access_token: String,
+    token_type: &'static str,
+    /// Lifetime of the access token, in seconds.
+    expires_in: i64,
+    refresh_token: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RefreshRequest {
+    refresh_token: String,
+}
+
+fn hash(token: &str) -> Vec<u8> {
+    Sha256::digest(token.as_bytes()).to_vec()
+}
+
+/// A new refresh token and the hash to store it by.
+///
+/// Like session tokens these are random enough that a fast hash will do.
+fn new_refresh_token() -> (String, Vec<u8>) {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    let token = URL_SAFE_NO_PAD.encode(bytes);
+    let token_hash = hash(&token);
+    (token, token_hash)
+}
+
+fn tokens(state: &AppState, user_id: i64, role: Role, refresh_token: String) -> Json<TokenBody> {
+    let (access_token, expires_in) = access_token(&state.jwt, user_id, vec![role]);
+
+    Json(TokenBody {
+        access_token,
+        token_type: "Bearer",
+        expires_in,
+        refresh_token,
+    })
+}
+
+// Logging in for API clients: each call starts a new token family, much like a session.
+//
+// As with `/api/auth/login`, users with two-factor authentication get a challenge instead, to
+// answer at `/api/auth/token/2fa`.
+async fn issue_tokens(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<UserBody<LoginUser>>,
+) -> Result<Response> {
+    let user_id = check_credentials(&state.store, req.user).await?;
+
+    if let Some(challenge) = start_challenge(&state, user_id).await? {
+        return Ok(challenge.into_response());
+    }
+    Ok(start_token_family(&state, user_id).await?.into_response())
+}
+
+/// Tokens of a new family of `user_id`, whose credentials were checked.
+pub(super) async fn start_token_family(state: &AppState, user_id: i64) -> Result<Json<TokenBody>> {
+    let role = state
+        .store
+        .users
+        .active_role(user_id)
+        .await?
+        .ok_or(Error::Unauthorized)?;
+    let mut family = [0u8; 16];
+    rand::rngs::OsRng.fill_bytes(&mut family);
+    let (refresh_token, token_hash) = new_refresh_token();
+    let now = Utc::now();
+    state
+        .store
+        .sessions
+        .create_refresh_token(
+            &token_hash,
+            &URL_SAFE_NO_PAD.encode(family),
+            user_id,
+            now,
+            now + Duration::days(REFRESH_TOKEN_DAYS),
+        )
+        .await?;
+
+    Ok(tokens(state, user_id, role, refresh_token))
+}
+
+// A refresh token is good for one refresh. When one comes back a second time, either the client
+// or an attacker holds a stolen copy; there's no telling which, so the family is revoked and
+// both have to log in again.
+async fn refresh_tokens(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<RefreshRequest>,
+) -> Result<Json<TokenBody>> {
+    let token = state
+        .store
+        .sessions
+        .refresh_token(&hash(&req.refresh_token))
+        .await?
+        .ok_or(Error::Unauthorized)?;
+    let user_id = token.user_id;
+
+    let now = Utc::now();
+    if token.revoked_at.is_some() || token.expires_at <= now {
+        return Err(Error::Unauthorized);
+    }
+    // Two refreshes racing with the same token: only one gets to mark it used, the other one
+    // is a reuse. Roles are looked up again on each refresh, so role changes and suspensions
+    // catch up with access tokens within their lifetime.
+    let (refresh_token, token_hash) = new_refresh_token();
+    let rotation = if token.used_at.is_some() {
+        Rotation::Reused
+    } else {
+        state
+            .store
+            .sessions
+            .rotate_refresh_token(
+                &token,
+                &token_hash,
+                now,
+                now + Duration::days(REFRESH_TOKEN_DAYS),
+            )
+            .await?
+    };
+    match rotation {
+        Rotation::Rotated(role) => Ok(tokens(&state, user_id, role, refresh_token)),
+        Rotation::Reused => {
+            tracing::warn!("refresh token reused, revoking token family of user {user_id}");
+            state
+                .store
+                .sessions
+                .revoke_token_family(&token.family, now)
+                .await?;
+            Err(Error::Unauthorized)
+        }
+        Rotation::Inactive => Err(Error::Unauthorized),
+    }
+}
+
+// Logging out for API clients. Always succeeds, as unknown tokens have nothing left to revoke.
+// Access tokens already handed out stay valid until they expire.
+async fn revoke_tokens(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<RefreshRequest>,
+) -> Result<StatusCode> {
+    let token = state
+        .store
+        .sessions
+        .refresh_token(&hash(&req.refresh_token))
+        .await?;
+    if let Some(token) = token {
+        state
+            .store
+            .sessions
+            .revoke_token_family(&token.family, Utc::now())
+            .await?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::jwt::{verify, JwtKeys};
+    use crate::auth::{Claims, Role};
+    use crate::conn::test_database;
+    use crate::testing::{insert_user_with_password, send_with, TestResponse};
+    use axum::routing::get;
+    use sqlx::SqlitePool;
+
+    async fn setup() -> (Router, Arc<AppState>, SqlitePool) {
+        let db = test_database().await;
+        insert_user_with_password(&db, 1, "alice", "correct horse").await;
+        let state = Arc::new(AppState::new(db.clone()));
+        let app = router()
+            .route("/claims", get(|claims: Claims| async move { Json(claims) }))
+            .with_state(state.clone());
+        (app, state, db)
+    }
+
+    fn refresh(token: &serde_json::Value) -> Option<serde_json::Value> {
+        Some(serde_json::json!({ "refreshToken": token }))
+    }
+
+    #[tokio::test]
+    async fn issue_and_use_access_token() {
+        let (app, state, _) = setup().await;
+
+        let wrong =
+            serde_json::json!({"user": {"email": "alice@example.com", "password": "wrong"}});
+        let TestResponse { status, .. } =
+            send_with(&app, "POST", "/api/auth/token", &[], Some(wrong)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let login = serde_json::json!({"user": {"email": "alice@example.com", "password": "correct horse"}});
+        let TestResponse { status, body, .. } =
+            send_with(&app, "POST", "/api/auth/token", &[], Some(login)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["tokenType"], "Bearer");
+        assert_eq!(body["expiresIn"], 15 * 60);
+
+        let bearer = format!("Bearer {}", body["accessToken"].as_str().unwrap());
+        let TestResponse {
+            status,
+            body: claims,
+            ..
+        } = send_with(&app, "GET", "/claims", &[("authorization", &bearer)], None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(claims["sub"], "1");
+        assert_eq!(claims["roles"], serde_json::json!(["user"]));
+        // Only the id, roles and expiry.
+        assert_eq!(claims.as_object().unwrap().len(), 3);
+
+        let TestResponse { status, .. } = send_with(&app, "GET", "/claims", &[], None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // Signed with another key, or expired.
+        let (forged, _) = access_token(&JwtKeys::random(), 1, vec![Role::Admin]);
+        let TestResponse { status, .. } = send_with(
+            &app,
+            "GET",
+            "/claims",
+            &[("authorization", &format!("Bearer {forged}"))],
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let expired = Claims {
+            sub: "1".to_string(),
+            roles: vec![],
+            exp: Utc::now().timestamp() - 1,
+        };
+        let expired = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &expired,
+            &jsonwebtoken::EncodingKey::from_secret(b"unused"),
+        )
+        .unwrap();
+        assert!(verify(&state.jwt, &expired).is_none());
+    }
+
+    #[tokio::test]
+    async fn refresh_rotates_and_reuse_revokes_family() {
+        let (app, state, db) = setup().await;
+        let login = serde_json::json!({"user": {"email": "alice@example.com", "password": "correct horse"}});
+        let TestResponse { body: first, .. } =
+            send_with(&app, "POST", "/api/auth/token", &[], Some(login.clone())).await;
+        let TestResponse { body: other, .. } =
+            send_with(&app, "POST", "/api/auth/token", &[], Some(login)).await;
+
+        let TestResponse {
+            status,
+            body: second,
+            ..
+        } = send_with(
+            &app,
+            "POST",
+            "/api/auth/token/refresh",
+            &[],
+            refresh(&first["refreshToken"]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_ne!(second["refreshToken"], first["refreshToken"]);
+        assert!(verify(&state.jwt, second["accessToken"].as_str().unwrap()).is_some());
+
+        // The first token coming back takes the whole family down, the rotated one included.
+        let TestResponse { status, .. } = send_with(
+            &app,
+            "POST",
+            "/api/auth/token/refresh",
+            &[],
+            refresh(&first["refreshToken"]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let TestResponse { status, .. } = send_with(
+            &app,
+            "POST",
+            "/api/auth/token/refresh",
+            &[],
+            refresh(&second["refreshToken"]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // Other families are left alone, until revoked.
+        let TestResponse {
+            status,
+            body: third,
+            ..
+        } = send_with(
+            &app,
+            "POST",
+            "/api/auth/token/refresh",
+            &[],
+            refresh(&other["refreshToken"]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let TestResponse { status, .. } = send_with(
+            &app,
+            "POST",
+            "/api/auth/token/revoke",
+            &[],
+            refresh(&third["refreshToken"]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let TestResponse { status, .. } = send_with(
+            &app,
+            "POST",
+            "/api/auth/token/refresh",
+            &[],
+            refresh(&third["refreshToken"]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let stored: Vec<Vec<u8>> = sqlx::query_scalar("SELECT token_hash FROM refresh_token")
+            .fetch_all(&db)
+            .await
+            .unwrap();
+        assert!(stored
+            .iter()
+            .all(|hash| hash.len() == 32
+                && *hash != third["refreshToken"].as_str().unwrap().as_bytes()));
+    }
+
+    #[tokio::test]
+    async fn suspended_users_cannot_refresh() {
+        let (app, _, db) = setup().await;
+        let login = serde_json::json!({"user": {"email": "alice@example.com", "password": "correct horse"}});
+        let TestResponse { body, .. } =
+            send_with(&app, "POST", "/api/auth/token", &[], Some(login)).await;
+        sqlx::query("UPDATE user SET suspended_at = CURRENT_TIMESTAMP")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let TestResponse { status, .. } = send_with(
+            &app,
+            "POST",
+            "/api/auth/token/refresh",
+            &[],
+            refresh(&body["refreshToken"]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        // No replacement is stored.
+        let stored: i64 = sqlx::query_scalar("SELECT count(*) FROM refresh_token")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(stored, 1);
+    }
+}
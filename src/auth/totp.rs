@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::Sha1;
+
+/// How long each code is valid, the RFC 6238 default every authenticator app uses.
+const STEP_SECS: i64 = 30;
+
+const DIGITS: u32 = 6;
+
+/// Codes of the steps just before and after the current one are accepted too, for clocks
+/// being a bit off and codes typed in just as they change.
+const SKEW_STEPS: i64 = 1;
+
+/// A new random secret, 160 bits as RFC 4226 recommends for HMAC-SHA-1.
+pub(crate) fn generate_secret() -> Vec<u8> {
+    let mut secret = vec![0u8; 20];
+    rand::rngs::OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+/// The secret as users type it into their authenticator app.
+pub(crate) fn encode_secret(secret: &[u8]) -> String {
+    BASE32_NOPAD.encode(secret)
+}
+
+/// The `otpauth://` URI authenticator apps read from a QR code.
+pub(crate) fn otpauth_uri(secret: &[u8], account: &str) -> String {
+    format!(
+        "otpauth://totp/stockrs:{}?secret={}&issuer=stockrs&algorithm=SHA1&digits={DIGITS}&period={STEP_SECS}",
+        percent_encode(account),
+        encode_secret(secret)
+    )
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'@' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// The RFC 4226 HOTP value of `counter`, with `digits` digits.
+fn hotp(secret: &[u8], counter: u64, digits: u32) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    // Dynamic truncation: the low nibble of the last byte picks where to read 31 bits from.
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes(hash[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+    value % 10u32.pow(digits)
+}
+
+fn step(time: DateTime<Utc>) -> i64 {
+    time.timestamp().div_euclid(STEP_SECS)
+}
+
+/// The step `code` is valid for at `now`, if any, so the caller can refuse it next time.
+///
+/// Steps up to `last_used_step` are refused: a code seen once, e.g. over a shoulder, can't be
+/// used again.
+pub(crate) fn verify(
+    secret: &[u8],
+    code: &str,
+    now: DateTime<Utc>,
+    last_used_step: Option<i64>,
+) -> Option<i64> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let code: u32 = code.parse().ok()?;
+
+    let current = step(now);
+    (current - SKEW_STEPS..=current + SKEW_STEPS)
+        .filter(|step| last_used_step.is_none_or(|last| *step > last))
+        .find(|step| *step >= 0 && hotp(secret, *step as u64, DIGITS) == code)
+}
+
+/// The code an authenticator app shows at `time`, for tests.
+#[cfg(test)]
+pub(crate) fn code_at(secret: &[u8], time: DateTime<Utc>) -> String {
+    format!("{:06}", hotp(secret, step(time) as u64, DIGITS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"12345678901234567890";
+
+    fn at(timestamp: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(timestamp, 0).unwrap()
+    }
+
+    #[test]
+    fn rfc_6238_test_vectors() {
+        for (time, expected) in [
+            (59, 94287082),
+            (1111111109, 7081804),
+            (1111111111, 14050471),
+            (1234567890, 89005924),
+            (2000000000, 69279037),
+        ] {
+            assert_eq!(hotp(SECRET, step(at(time)) as u64, 8), expected, "{time}");
+        }
+    }
+
+    #[test]
+    fn verify_window_and_replay() {
+        let now = at(1234567890);
+        let code = code_at(SECRET, now);
+
+        let used = verify(SECRET, &code, now, None).unwrap();
+        assert_eq!(used, step(now));
+        // Still good half a minute later, not a minute and a half later.
+        assert!(verify(SECRET, &code, at(1234567890 + 30), None).is_some());
+        assert!(verify(SECRET, &code, at(1234567890 + 90), None).is_none());
+
+        assert!(verify(SECRET, &code, now, Some(used)).is_none());
+        assert!(verify(SECRET, "12345", now, None).is_none());
+        assert!(verify(SECRET, "abcdef", now, None).is_none());
+    }
+
+    #[test]
+    fn otpauth_uri_escapes_account() {
+        let uri = otpauth_uri(SECRET, "alice smith@example.com");
+        assert!(uri.starts_with("otpauth://totp/stockrs:alice%20smith@example.com?secret=GEZDG"));
+    }
+}
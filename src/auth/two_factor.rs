@@ -0,0 +1,589 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::Router;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Duration;
+use data_encoding::BASE32_NOPAD;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use super::handlers::{log_in, LoggedIn};
+use super::tokens::{start_token_family, TokenBody};
+use super::totp;
+use crate::app::AppState;
+use crate::auth::AuthUser;
+use crate::common::errors::{Error, Result};
+use crate::common::extract::Json;
+
+/// How long a user has to come up with their code after entering their password.
+const CHALLENGE_MINUTES: i64 = 5;
+
+/// Wrong codes allowed per challenge, after which the password has to be entered again. Codes
+/// entered from a session are capped alike, see `check_session_code`.
+const CHALLENGE_ATTEMPTS: i64 = 5;
+
+const RECOVERY_CODES: usize = 10;
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/auth/2fa", post(enroll).delete(disable))
+        .route("/api/auth/2fa/confirm", post(confirm))
+        .route(
+            "/api/auth/2fa/recovery-codes",
+            post(regenerate_recovery_codes),
+        )
+        .route("/api/auth/login/2fa", post(complete_login))
+        .route("/api/auth/token/2fa", post(complete_token))
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Challenge {
+    challenge: String,
+    /// Seconds left to answer the challenge.
+    expires_in: i64,
+}
+
+/// The "2FA required" answer to a login with the right password, `202 Accepted` with the
+/// challenge to answer with a code.
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct TwoFactorRequired {
+    two_factor: Challenge,
+}
+
+impl IntoResponse for TwoFactorRequired {
+    fn into_response(self) -> Response {
+        (StatusCode::ACCEPTED, Json(self)).into_response()
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct ChallengeAnswer {
+    challenge: String,
+    /// A code from the authenticator app, or a recovery code.
+    code: String,
+}
+
+#[derive(serde::Deserialize)]
+struct CodeBody {
+    code: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Enrollment {
+    /// Base32, for typing into an authenticator app.
+    secret: String,
+    otpauth_uri: String,
+}
+
+#[derive(serde::Serialize)]
+struct TotpBody {
+    totp: Enrollment,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RecoveryCodesBody {
+    recovery_codes: Vec<String>,
+}
+
+fn hash(token: &str) -> Vec<u8> {
+    Sha256::digest(token.as_bytes()).to_vec()
+}
+
+/// Recovery codes are shown as `XXXX-XXXX` but accepted in any case, with or without dashes.
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// A challenge for `user_id` if they have two-factor authentication, `None` if their password
+/// is all it takes.
+pub(super) async fn start_challenge(
+    state: &AppState,
+    user_id: i64,
+) -> Result<Option<TwoFactorRequired>> {
+    if !enabled(state, user_id).await? {
+        return Ok(None);
+    }
+
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    let challenge = URL_SAFE_NO_PAD.encode(bytes);
+    let lifetime = Duration::minutes(CHALLENGE_MINUTES);
+
+    state
+        .store
+        .two_factor
+        .create_challenge(&hash(&challenge), user_id, state.clock.now() + lifetime)
+        .await?;
+
+    Ok(Some(TwoFactorRequired {
+        two_factor: Challenge {
+            challenge,
+            expires_in: lifetime.num_seconds(),
+        },
+    }))
+}
+
+/// Whether `code` is the current code of `user_id`'s authenticator, or one of their unused
+/// recovery codes. Either way it's used up.
+async fn check_second_factor(state: &AppState, user_id: i64, code: &str) -> Result<bool> {
+    let now = state.clock.now();
+    let Some(totp) = state.store.two_factor.totp(user_id).await? else {
+        return Ok(false);
+    };
+    if totp.enabled_at.is_none() {
+        return Ok(false);
+    }
+
+    if let Some(step) = totp::verify(&totp.secret, code, now, totp.last_used_step) {
+        // Two requests with the same code: only one gets to move `last_used_step` past it.
+        return Ok(state.store.two_factor.use_totp_step(user_id, step).await?);
+    }
+
+    let used = state
+        .store
+        .two_factor
+        .use_recovery_code(user_id, &hash(&normalize_recovery_code(code)), now)
+        .await?;
+    if used {
+        tracing::info!("user {user_id} logged in with a recovery code");
+    }
+    Ok(used)
+}
+
+/// The user of an answered challenge. A wrong code can be corrected until the attempts run
+/// out, after which the challenge is gone.
+async fn answer_challenge(state: &AppState, answer: ChallengeAnswer) -> Result<i64> {
+    let (challenge_id, user_id) = state
+        .store
+        .two_factor
+        .attempt_challenge(
+            &hash(&answer.challenge),
+            state.clock.now(),
+            CHALLENGE_ATTEMPTS,
+        )
+        .await?
+        .ok_or(Error::Unauthorized)?;
+
+    if !check_second_factor(state, user_id, &answer.code).await? {
+        return Err(Error::unprocessable_entity([("code", "is wrong")]));
+    }
+
+    state
+        .store
+        .two_factor
+        .delete_challenge(challenge_id)
+        .await?;
+    // Logging in with both factors lifts a lockout from wrong codes entered in a session.
+    state.store.two_factor.reset_failed_codes(user_id).await?;
+    Ok(user_id)
+}
+
+/// Check a code entered from a logged in session, e.g. to turn two-factor authentication off.
+///
+/// Unlike challenges these can't be started over, so wrong codes count against the user: after
+/// `CHALLENGE_ATTEMPTS` in a row they're logged out everywhere, and codes are refused until they
+/// log in again with both factors.
+async fn check_session_code(state: &AppState, user_id: i64, code: &str) -> Result<()> {
+    let attempt = state
+        .store
+        .two_factor
+        .count_failed_code(user_id, CHALLENGE_ATTEMPTS)
+        .await?
+        .ok_or(Error::Unauthorized)?;
+
+    if check_second_factor(state, user_id, code).await? {
+        state.store.two_factor.reset_failed_codes(user_id).await?;
+        return Ok(());
+    }
+
+    if attempt >= CHALLENGE_ATTEMPTS {
+        tracing::warn!("user {user_id} entered {attempt} wrong codes in a row, logging them out");
+        state
+            .store
+            .sessions
+            .log_out_everywhere(user_id, None, state.clock.now())
+            .await?;
+        return Err(Error::Unauthorized);
+    }
+    Err(Error::unprocessable_entity([("code", "is wrong")]))
+}
+
+async fn complete_login(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(answer): Json<ChallengeAnswer>,
+) -> Result<LoggedIn> {
+    let user_id = answer_challenge(&state, answer).await?;
+    log_in(&state, user_id, &headers).await
+}
+
+async fn complete_token(
+    State(state): State<Arc<AppState>>,
+    Json(answer): Json<ChallengeAnswer>,
+) -> Result<Json<TokenBody>> {
+    let user_id = answer_challenge(&state, answer).await?;
+    start_token_family(&state, user_id).await
+}
+
+/// New recovery codes, shown to the user, with the hashes to store.
+fn generate_recovery_codes() -> (Vec<String>, Vec<Vec<u8>>) {
+    (0..RECOVERY_CODES)
+        .map(|_| {
+            let mut bytes = [0u8; 5];
+            rand::rngs::OsRng.fill_bytes(&mut bytes);
+            let code = BASE32_NOPAD.encode(&bytes);
+            (format!("{}-{}", &code[..4], &code[4..]), hash(&code))
+        })
+        .unzip()
+}
+
+async fn enabled(state: &AppState, user_id: i64) -> Result<bool> {
+    let totp = state.store.two_factor.totp(user_id).await?;
+    Ok(totp.is_some_and(|totp| totp.enabled_at.is_some()))
+}
+
+// Starts enrolling a new authenticator. Logins only ask for its codes once it's confirmed, so an
+// abandoned enrollment doesn't lock anyone out; starting over replaces the secret.
+async fn enroll(auth_user: AuthUser, State(state): State<Arc<AppState>>) -> Result<Json<TotpBody>> {
+    if enabled(&state, auth_user.user_id).await? {
+        return Err(Error::unprocessable_entity([("totp", "already enabled")]));
+    }
+
+    let user = state
+        .store
+        .users
+        .user(auth_user.user_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let secret = totp::generate_secret();
+
+    state
+        .store
+        .two_factor
+        .enroll_totp(auth_user.user_id, &secret, state.clock.now())
+        .await?;
+
+    Ok(Json(TotpBody {
+        totp: Enrollment {
+            secret: totp::encode_secret(&secret),
+            otpauth_uri: totp::otpauth_uri(&secret, &user.email),
+        },
+    }))
+}
+
+// A first code proves the authenticator was set up right; from then on logins need a code.
+async fn confirm(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CodeBody>,
+) -> Result<Json<RecoveryCodesBody>> {
+    let secret = state
+        .store
+        .two_factor
+        .totp(auth_user.user_id)
+        .await?
+        .filter(|totp| totp.enabled_at.is_none())
+        .map(|totp| totp.secret)
+        .ok_or_else(|| Error::unprocessable_entity([("totp", "no enrollment started")]))?;
+
+    let now = state.clock.now();
+    let step = totp::verify(&secret, &req.code, now, None)
+        .ok_or_else(|| Error::unprocessable_entity([("code", "is wrong")]))?;
+
+    let (recovery_codes, hashes) = generate_recovery_codes();
+    state
+        .store
+        .two_factor
+        .enable_totp(auth_user.user_id, step, &hashes, now)
+        .await?;
+
+    Ok(Json(RecoveryCodesBody { recovery_codes }))
+}
+
+async fn regenerate_recovery_codes(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CodeBody>,
+) -> Result<Json<RecoveryCodesBody>> {
+    if !enabled(&state, auth_user.user_id).await? {
+        return Err(Error::NotFound);
+    }
+    check_session_code(&state, auth_user.user_id, &req.code).await?;
+
+    let (recovery_codes, hashes) = generate_recovery_codes();
+    state
+        .store
+        .two_factor
+        .replace_recovery_codes(auth_user.user_id, &hashes)
+        .await?;
+
+    Ok(Json(RecoveryCodesBody { recovery_codes }))
+}
+
+// Turning two-factor authentication off takes a code too, a stolen session alone can't: it only
+// gets a few guesses before it's logged out.
+async fn disable(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CodeBody>,
+) -> Result<StatusCode> {
+    if !enabled(&state, auth_user.user_id).await? {
+        return Err(Error::NotFound);
+    }
+    check_session_code(&state, auth_user.user_id, &req.code).await?;
+
+    state
+        .store
+        .two_factor
+        .disable_totp(auth_user.user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::test_session_cookie;
+    use crate::auth::totp::code_at;
+    use crate::common::clock::{Clock, FixedClock};
+    use crate::conn::test_database;
+    use crate::testing::{insert_user_with_password, send, send_with};
+    use chrono::{TimeZone, Utc};
+
+    #[tokio::test]
+    async fn enroll_and_log_in_with_second_factor() {
+        let db = test_database().await;
+        insert_user_with_password(&db, 1, "alice", "correct horse").await;
+        let cookie = test_session_cookie(&db, 1).await;
+        let clock = Arc::new(FixedClock::new(
+            Utc.with_ymd_and_hms(2024, 1, 2, 9, 30, 0).unwrap(),
+        ));
+        let mut state = AppState::new(db.clone());
+        state.clock = clock.clone();
+        let app = crate::auth::router().with_state(Arc::new(state));
+        let secret = || async {
+            sqlx::query_scalar::<_, Vec<u8>>("SELECT secret FROM totp WHERE user_id = 1")
+                .fetch_one(&db)
+                .await
+                .unwrap()
+        };
+        let code = |secret: &[u8]| serde_json::json!({ "code": code_at(secret, clock.now()) });
+        let login = serde_json::json!({"user": {"email": "alice@example.com", "password": "correct horse"}});
+
+        let (status, body) = send(
+            &app,
+            "POST",
+            "/api/auth/2fa",
+            &cookie,
+            Some(serde_json::json!({})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["totp"]["otpauthUri"]
+            .as_str()
+            .unwrap()
+            .starts_with("otpauth://totp/stockrs:alice@example.com?secret="));
+
+        // Not asked for until confirmed.
+        let response = send_with(&app, "POST", "/api/auth/login", &[], Some(login.clone())).await;
+        assert_eq!(
+            (response.status, response.cookie.is_some()),
+            (StatusCode::OK, true)
+        );
+
+        let secret = secret().await;
+        let (status, _) = send(
+            &app,
+            "POST",
+            "/api/auth/2fa/confirm",
+            &cookie,
+            Some(serde_json::json!({"code": "000000"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let (status, body) = send(
+            &app,
+            "POST",
+            "/api/auth/2fa/confirm",
+            &cookie,
+            Some(code(&secret)),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let recovery_codes: Vec<String> =
+            serde_json::from_value(body["recoveryCodes"].clone()).unwrap();
+        assert_eq!(recovery_codes.len(), RECOVERY_CODES);
+
+        // The password alone gets a challenge now, not a session.
+        let response = send_with(&app, "POST", "/api/auth/login", &[], Some(login.clone())).await;
+        assert_eq!(
+            (response.status, response.cookie.is_some()),
+            (StatusCode::ACCEPTED, false)
+        );
+        let challenge = response.body["twoFactor"]["challenge"].clone();
+        let answer = |code: serde_json::Value| serde_json::json!({"challenge": challenge, "code": code["code"]});
+
+        // The code used to confirm can't be used again.
+        let (status, _) = send(
+            &app,
+            "POST",
+            "/api/auth/login/2fa",
+            "",
+            Some(answer(code(&secret))),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        clock.advance(Duration::seconds(30));
+        let response = send_with(
+            &app,
+            "POST",
+            "/api/auth/login/2fa",
+            &[],
+            Some(answer(code(&secret))),
+        )
+        .await;
+        assert_eq!(
+            (response.status, response.cookie.is_some()),
+            (StatusCode::OK, true)
+        );
+        assert_eq!(response.body["user"]["username"], "alice");
+        // Challenges are single use.
+        let (status, _) = send(
+            &app,
+            "POST",
+            "/api/auth/login/2fa",
+            "",
+            Some(answer(code(&secret))),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // API clients go through the same, here with a recovery code, which is then used up.
+        let (status, body) = send(&app, "POST", "/api/auth/token", "", Some(login.clone())).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let recovery = serde_json::json!({"challenge": body["twoFactor"]["challenge"], "code": recovery_codes[0].to_lowercase()});
+        let (status, body) = send(
+            &app,
+            "POST",
+            "/api/auth/token/2fa",
+            "",
+            Some(recovery.clone()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["accessToken"].is_string());
+        let (_, body) = send(&app, "POST", "/api/auth/token", "", Some(login.clone())).await;
+        let reused = serde_json::json!({"challenge": body["twoFactor"]["challenge"], "code": recovery_codes[0]});
+        let (status, _) = send(&app, "POST", "/api/auth/token/2fa", "", Some(reused)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        // Challenges expire, and run out of attempts.
+        let (_, body) = send(&app, "POST", "/api/auth/login", "", Some(login.clone())).await;
+        let stale = serde_json::json!({"challenge": body["twoFactor"]["challenge"], "code": recovery_codes[1]});
+        clock.advance(Duration::minutes(CHALLENGE_MINUTES));
+        let (status, _) = send(&app, "POST", "/api/auth/login/2fa", "", Some(stale)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (_, body) = send(&app, "POST", "/api/auth/login", "", Some(login.clone())).await;
+        let guess =
+            serde_json::json!({"challenge": body["twoFactor"]["challenge"], "code": "nope"});
+        for _ in 0..CHALLENGE_ATTEMPTS {
+            let (status, _) =
+                send(&app, "POST", "/api/auth/login/2fa", "", Some(guess.clone())).await;
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        }
+        let right = serde_json::json!({"challenge": body["twoFactor"]["challenge"], "code": recovery_codes[1]});
+        let (status, _) = send(&app, "POST", "/api/auth/login/2fa", "", Some(right)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        clock.advance(Duration::seconds(30));
+        let (status, _) = send(
+            &app,
+            "DELETE",
+            "/api/auth/2fa",
+            &cookie,
+            Some(code(&secret)),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let response = send_with(&app, "POST", "/api/auth/login", &[], Some(login)).await;
+        assert_eq!(
+            (response.status, response.cookie.is_some()),
+            (StatusCode::OK, true)
+        );
+    }
+
+    #[tokio::test]
+    async fn a_session_runs_out_of_codes_to_guess() {
+        let db = test_database().await;
+        let secret = totp::generate_secret();
+        insert_user_with_password(&db, 1, "alice", "correct horse").await;
+        sqlx::query(
+            "INSERT INTO totp (user_id, secret, created_at, enabled_at)
+             VALUES (1, $1, '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+        )
+        .bind(&secret)
+        .execute(&db)
+        .await
+        .unwrap();
+        let stolen = test_session_cookie(&db, 1).await;
+        let other = test_session_cookie(&db, 1).await;
+        let clock = Arc::new(FixedClock::new(
+            Utc.with_ymd_and_hms(2024, 1, 2, 9, 30, 0).unwrap(),
+        ));
+        let mut state = AppState::new(db.clone());
+        state.clock = clock.clone();
+        let app = crate::auth::router().with_state(Arc::new(state));
+        let code = || serde_json::json!({ "code": code_at(&secret, clock.now()) });
+        let guess = serde_json::json!({"code": "000000"});
+
+        for _ in 1..CHALLENGE_ATTEMPTS {
+            let (status, _) = send(
+                &app,
+                "DELETE",
+                "/api/auth/2fa",
+                &stolen,
+                Some(guess.clone()),
+            )
+            .await;
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        }
+        let (status, _) = send(
+            &app,
+            "POST",
+            "/api/auth/2fa/recovery-codes",
+            &stolen,
+            Some(guess),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // Every session is logged out, and even the right code is refused until the next login.
+        let (status, _) = send(&app, "DELETE", "/api/auth/2fa", &other, Some(code())).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let extra = test_session_cookie(&db, 1).await;
+        let (status, _) = send(&app, "DELETE", "/api/auth/2fa", &extra, Some(code())).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let login = serde_json::json!({"user": {"email": "alice@example.com", "password": "correct horse"}});
+        let (_, body) = send(&app, "POST", "/api/auth/login", "", Some(login)).await;
+        let answer = serde_json::json!({"challenge": body["twoFactor"]["challenge"], "code": code()["code"]});
+        let (status, _) = send(&app, "POST", "/api/auth/login/2fa", "", Some(answer)).await;
+        assert_eq!(status, StatusCode::OK);
+        clock.advance(Duration::seconds(30));
+        let (status, _) = send(&app, "DELETE", "/api/auth/2fa", &extra, Some(code())).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+}
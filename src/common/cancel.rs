@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+/// A cheap to clone signal asking background tasks to stop, as in `guide/cancel_task.rs`.
+///
+/// Built on a `watch` channel holding whether cancellation was requested, so unlike a
+/// `broadcast` a task that starts waiting after `cancel` still sees it.
+#[derive(Clone)]
+pub struct CancellationToken {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self {
+            sender: Arc::new(watch::channel(false).0),
+        }
+    }
+
+    /// Request every holder of the token to stop.
+    pub fn cancel(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Resolve once `cancel` has been called, immediately if it already was.
+    pub async fn cancelled(&self) {
+        let mut receiver = self.sender.subscribe();
+        // The sender lives as long as `self`, so this can't fail.
+        let _ = receiver.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -2,36 +2,22 @@ use axum::http::HeaderMap;
 
 const COOKIE_NAME: &str = "MERGE_TOKEN";
 
+/// The value of the cookie `cookie_name` sent with a request, if any.
 pub fn get_cookie(headers: &HeaderMap, cookie_name: &str) -> Option<String> {
-    let cookie = headers
-        .get(axum::http::header::COOKIE)
-        .and_then(|value| value.to_str().ok())
-        .map(|value| value.to_string());
-    match cookie {
-        Some(cookie) => {
-            let cookie = cookie.as_str();
-            let cs: Vec<&str> = cookie.split(';').collect();
-            for item in cs {
-                let item: Vec<&str> = item.split('=').collect();
-                if item.len() != 2 {
-                    continue;
-                }
-                let key = item[0];
-                let val = item[1];
-                let key = key.trim();
-                let val = val.trim();
-                if key == cookie_name {
-                    return Some(val.to_string());
-                }
-            }
-            None
-        }
-        None => None,
-    }
+    headers
+        .get_all(axum::http::header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        // Values may contain `=` themselves, only the first one separates the name.
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| name.trim() == cookie_name)
+        .map(|(_, value)| value.trim().trim_matches('"').to_string())
 }
+
 pub fn set_cookie(value: &str) -> HeaderMap {
     let c = format!("{}={}", COOKIE_NAME, value);
     let mut hm = HeaderMap::new();
-    hm.insert(axum::http::header::SET_COOKIE, (&c).parse().unwrap());
+    hm.insert(axum::http::header::SET_COOKIE, c.parse().unwrap());
     hm
 }
@@ -0,0 +1,433 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use axum::extract::multipart::MultipartRejection;
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::extract::ws::rejection::WebSocketUpgradeRejection;
+use axum::http::header::{CONTENT_TYPE, WWW_AUTHENTICATE};
+use axum::http::{HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::BoxError;
+use sqlx::error::DatabaseError;
+
+/// Handler result type, defaulting the error to the API [`Error`].
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The error type returned by API handlers, extractors and middleware.
+///
+/// Every error is rendered as an RFC 7807 `application/problem+json` body with a stable
+/// machine-readable `code`, see [`Error::code`]. Internal errors are logged along with a
+/// correlation id, which the response carries instead of the cause.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Return `401 Unauthorized`
+    #[error("authentication required")]
+    Unauthorized,
+
+    /// Return `403 Forbidden`
+    #[error("user may not perform that action")]
+    Forbidden,
+
+    /// Return `404 Not Found`
+    #[error("request path not found")]
+    NotFound,
+
+    /// Return `405 Method Not Allowed`, for a path that exists with other methods.
+    #[error("method not allowed on this path")]
+    MethodNotAllowed,
+
+    /// Return `408 Request Timeout`
+    #[error("the request took too long")]
+    Timeout,
+
+    /// Return `422 Unprocessable Entity` with a map of field name to error messages.
+    #[error("error in the request body")]
+    UnprocessableEntity {
+        errors: HashMap<Cow<'static, str>, Vec<Cow<'static, str>>>,
+    },
+
+    /// A request an extractor couldn't make sense of, e.g. a malformed JSON body, returned
+    /// with the status of the rejection.
+    #[error("{detail}")]
+    Rejected {
+        status: StatusCode,
+        code: &'static str,
+        detail: String,
+    },
+
+    /// Return `500 Internal Server Error` on a `sqlx::Error`.
+    ///
+    /// The actual error message isn't returned to the client for security reasons, it is
+    /// logged instead.
+    #[error("an error occurred with the database")]
+    Sqlx(#[from] sqlx::Error),
+
+    /// Return `500 Internal Server Error` on a `anyhow::Error`.
+    #[error("an internal server error occurred")]
+    Anyhow(#[from] anyhow::Error),
+}
+
+impl Error {
+    /// Convenient constructor for `Error::UnprocessableEntity`.
+    ///
+    /// Multiple for the same key are collected into a list for that key.
+    pub fn unprocessable_entity<K, V>(errors: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<Cow<'static, str>>,
+        V: Into<Cow<'static, str>>,
+    {
+        let mut error_map = HashMap::new();
+
+        for (key, val) in errors {
+            error_map
+                .entry(key.into())
+                .or_insert_with(Vec::new)
+                .push(val.into());
+        }
+
+        Self::UnprocessableEntity { errors: error_map }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            Self::Timeout => StatusCode::REQUEST_TIMEOUT,
+            Self::UnprocessableEntity { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Rejected { status, .. } => *status,
+            Self::Sqlx(_) | Self::Anyhow(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// The machine-readable code of the error, which clients can rely on:
+    ///
+    /// * `unauthorized`, `forbidden`, `not_found`, `method_not_allowed`, `request_timeout`
+    /// * `validation_failed`: with the offending fields in `errors`
+    /// * `malformed_body`, `invalid_body`, `unsupported_media_type`, `payload_too_large`,
+    ///   `unreadable_body`, `invalid_multipart`, `invalid_path`, `invalid_query`,
+    ///   `invalid_upgrade`: rejected by an extractor
+    /// * `internal_error`: with a `correlationId` to find the cause in the logs
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Unauthorized => "unauthorized",
+            Self::Forbidden => "forbidden",
+            Self::NotFound => "not_found",
+            Self::MethodNotAllowed => "method_not_allowed",
+            Self::Timeout => "request_timeout",
+            Self::UnprocessableEntity { .. } => "validation_failed",
+            Self::Rejected { code, .. } => code,
+            Self::Sqlx(_) | Self::Anyhow(_) => "internal_error",
+        }
+    }
+
+    /// The error of a failed middleware, e.g. `Timeout` when `tower::timeout` gave up.
+    pub fn from_middleware(error: BoxError) -> Self {
+        if error.is::<tower::timeout::error::Elapsed>() {
+            Self::Timeout
+        } else {
+            Self::Anyhow(anyhow::anyhow!(error).context("middleware failed"))
+        }
+    }
+
+    fn rejected(status: StatusCode, code: &'static str, detail: String) -> Self {
+        Self::Rejected {
+            status,
+            code,
+            detail,
+        }
+    }
+}
+
+/// The `type` of problems is this followed by their code.
+pub const PROBLEM_TYPE_PREFIX: &str = "urn:stockrs:problem:";
+
+/// An RFC 7807 problem details body, extended with `code`, and `errors` or `correlationId`
+/// where they apply.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub title: String,
+    pub status: u16,
+    pub code: String,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<HashMap<Cow<'static, str>, Vec<Cow<'static, str>>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        let mut problem = Problem {
+            kind: format!("{PROBLEM_TYPE_PREFIX}{}", self.code()),
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            code: self.code().to_string(),
+            detail: self.to_string(),
+            errors: None,
+            correlation_id: None,
+        };
+
+        match self {
+            Self::UnprocessableEntity { errors } => problem.errors = Some(errors),
+
+            // Logged with `tracing` so that it gets linked to the HTTP request by `TraceLayer`,
+            // the client only gets the id to report.
+            Self::Sqlx(ref e) => {
+                let id = correlation_id();
+                tracing::error!(correlation_id = %id, "SQLx error: {:?}", e);
+                problem.correlation_id = Some(id);
+            }
+            Self::Anyhow(ref e) => {
+                let id = correlation_id();
+                tracing::error!(correlation_id = %id, "Generic error: {:?}", e);
+                problem.correlation_id = Some(id);
+            }
+
+            _ => (),
+        }
+
+        let mut response = (status, axum::Json(problem)).into_response();
+        let headers = response.headers_mut();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/problem+json"),
+        );
+        if status == StatusCode::UNAUTHORIZED {
+            // Include the `WWW-Authenticate` challenge required in the specification
+            // for the `401 Unauthorized` response code:
+            // https://developer.mozilla.org/en-US/docs/Web/HTTP/Status/401
+            headers.insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        }
+        response
+    }
+}
+
+fn correlation_id() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+/// The code of a body that couldn't be read, by the status of the rejection.
+fn unreadable_body(status: StatusCode) -> &'static str {
+    if status == StatusCode::PAYLOAD_TOO_LARGE {
+        "payload_too_large"
+    } else {
+        "unreadable_body"
+    }
+}
+
+impl From<JsonRejection> for Error {
+    fn from(rejection: JsonRejection) -> Self {
+        let code = match &rejection {
+            JsonRejection::JsonDataError(_) => "invalid_body",
+            JsonRejection::JsonSyntaxError(_) => "malformed_body",
+            JsonRejection::MissingJsonContentType(_) => "unsupported_media_type",
+            _ => unreadable_body(rejection.status()),
+        };
+        Self::rejected(rejection.status(), code, rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for Error {
+    fn from(rejection: PathRejection) -> Self {
+        Self::rejected(rejection.status(), "invalid_path", rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for Error {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::rejected(rejection.status(), "invalid_query", rejection.body_text())
+    }
+}
+
+impl From<MultipartRejection> for Error {
+    fn from(rejection: MultipartRejection) -> Self {
+        let code = match rejection.status() {
+            StatusCode::BAD_REQUEST => "invalid_multipart",
+            status => unreadable_body(status),
+        };
+        Self::rejected(rejection.status(), code, rejection.body_text())
+    }
+}
+
+impl From<WebSocketUpgradeRejection> for Error {
+    fn from(rejection: WebSocketUpgradeRejection) -> Self {
+        Self::rejected(rejection.status(), "invalid_upgrade", rejection.body_text())
+    }
+}
+
+/// A little helper trait for more easily converting database constraint errors into API errors.
+///
+/// ```rust,ignore
+/// sqlx::query("INSERT INTO stock (symbol, ...) VALUES ($1, ...)")
+///     .bind(&symbol)
+///     .execute(&state.db)
+///     .await
+///     .on_constraint("stock.symbol", |_| {
+///         Error::unprocessable_entity([("symbol", "already listed")])
+///     })?;
+/// ```
+pub trait ResultExt<T> {
+    /// If `self` contains a SQLx database constraint error with the given name,
+    /// transform the error.
+    ///
+    /// SQLite doesn't report constraint names, only the offending columns, e.g.
+    /// `UNIQUE constraint failed: stock.symbol`, so `name` may be given as `table.column` too.
+    ///
+    /// Otherwise, the result is passed through unchanged.
+    fn on_constraint(
+        self,
+        name: &str,
+        f: impl FnOnce(Box<dyn DatabaseError>) -> Error,
+    ) -> Result<T, Error>;
+}
+
+impl<T, E> ResultExt<T> for Result<T, E>
+where
+    E: Into<Error>,
+{
+    fn on_constraint(
+        self,
+        name: &str,
+        map_err: impl FnOnce(Box<dyn DatabaseError>) -> Error,
+    ) -> Result<T, Error> {
+        self.map_err(|e| match e.into() {
+            Error::Sqlx(sqlx::Error::Database(dbe)) if violates_constraint(&*dbe, name) => {
+                map_err(dbe)
+            }
+            e => e,
+        })
+    }
+}
+
+pub(crate) fn violates_constraint(dbe: &dyn DatabaseError, name: &str) -> bool {
+    match dbe.constraint() {
+        Some(constraint) => constraint == name,
+        None => dbe
+            .message()
+            .rsplit_once("constraint failed: ")
+            .is_some_and(|(_, columns)| columns == name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::extract::{Json, Path};
+    use axum::body::Body;
+    use axum::http::header::ALLOW;
+    use axum::http::Request;
+    use axum::routing::{get, post};
+    use axum::Router;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    async fn problem(response: Response) -> (StatusCode, Problem) {
+        let status = response.status();
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/problem+json");
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn rendered_as_problem_details() {
+        let (status, body) = problem(Error::NotFound.into_response()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body.kind, "urn:stockrs:problem:not_found");
+        assert_eq!(body.title, "Not Found");
+        assert_eq!(body.status, 404);
+        assert_eq!(body.code, "not_found");
+        assert!(body.errors.is_none() && body.correlation_id.is_none());
+
+        let response = Error::Unauthorized.into_response();
+        assert_eq!(response.headers()[WWW_AUTHENTICATE], "Bearer");
+
+        let error = Error::unprocessable_entity([("name", "can't be blank")]);
+        let (status, body) = problem(error.into_response()).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body.code, "validation_failed");
+        assert_eq!(body.errors.unwrap()["name"], ["can't be blank"]);
+
+        // Internal causes stay in the logs, the client gets an id to find them by.
+        let error = Error::Anyhow(anyhow::anyhow!("secret connection string"));
+        let (status, body) = problem(error.into_response()).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body.code, "internal_error");
+        assert!(!body.detail.contains("secret"));
+        assert_eq!(body.correlation_id.unwrap().len(), 16);
+
+        let elapsed: BoxError = Box::new(tower::timeout::error::Elapsed::new());
+        assert_eq!(Error::from_middleware(elapsed).code(), "request_timeout");
+        assert_eq!(
+            Error::from_middleware("boom".into()).code(),
+            "internal_error"
+        );
+    }
+
+    #[tokio::test]
+    async fn wrong_method() {
+        let state = crate::app::AppState::new(crate::conn::test_database().await);
+        let app = crate::app::api_router(std::sync::Arc::new(state));
+        let request = Request::delete("/api/stocks").body(Body::empty()).unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.headers()[ALLOW], "GET,HEAD,POST");
+        let (status, body) = problem(response).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(body.code, "method_not_allowed");
+    }
+
+    #[tokio::test]
+    async fn extractor_rejections() {
+        #[derive(serde::Deserialize)]
+        struct NewItem {
+            #[allow(dead_code)]
+            name: String,
+        }
+
+        let app = Router::new()
+            .route("/items", post(|Json(_): Json<NewItem>| async {}))
+            .route(
+                "/items/:id",
+                get(|Path(id): Path<i64>| async move { id.to_string() }),
+            );
+        let send = |request: Request<Body>| async {
+            problem(app.clone().oneshot(request).await.unwrap()).await
+        };
+        let post_json = |content_type: &str, body: &'static str| {
+            Request::post("/items")
+                .header(CONTENT_TYPE, content_type)
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let (status, body) = send(post_json("application/json", "{")).await;
+        assert_eq!(
+            (status, body.code.as_str()),
+            (StatusCode::BAD_REQUEST, "malformed_body")
+        );
+        let (status, body) = send(post_json("application/json", "{}")).await;
+        assert_eq!(
+            (status, body.code.as_str()),
+            (StatusCode::UNPROCESSABLE_ENTITY, "invalid_body")
+        );
+        assert!(body.detail.contains("name"), "{}", body.detail);
+        let (status, body) = send(post_json("text/plain", "{}")).await;
+        assert_eq!(
+            (status, body.code.as_str()),
+            (StatusCode::UNSUPPORTED_MEDIA_TYPE, "unsupported_media_type")
+        );
+
+        let request = Request::get("/items/one").body(Body::empty()).unwrap();
+        let (status, body) = send(request).await;
+        assert_eq!(
+            (status, body.code.as_str()),
+            (StatusCode::BAD_REQUEST, "invalid_path")
+        );
+    }
+}
@@ -0,0 +1,26 @@
+use axum::extract::{FromRequest, FromRequestParts};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+
+use super::errors::Error;
+
+/// `axum::Json`, rejecting bodies with the API [`Error`]. Responds like `axum::Json` too.
+#[derive(FromRequest, Debug, Clone, Copy, Default)]
+#[from_request(via(axum::Json), rejection(Error))]
+pub struct Json<T>(pub T);
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+/// `axum::extract::Path`, rejecting with the API [`Error`].
+#[derive(FromRequestParts, Debug)]
+#[from_request(via(axum::extract::Path), rejection(Error))]
+pub struct Path<T>(pub T);
+
+/// `axum::extract::Query`, rejecting with the API [`Error`].
+#[derive(FromRequestParts, Debug, Default)]
+#[from_request(via(axum::extract::Query), rejection(Error))]
+pub struct Query<T>(pub T);
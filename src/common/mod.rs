@@ -0,0 +1,15 @@
+/// Cooperative cancellation of background tasks, e.g. scheduled jobs on shutdown.
+pub mod cancel;
+/// The time as seen by time-sensitive code such as two-factor authentication, fixed in tests.
+pub mod clock;
+/// Minimal `Cookie` header parsing, for the session cookie.
+pub mod cookie;
+pub mod errors;
+/// `Json`, `Path` and `Query` extractors rejecting with `errors::Error`, to use in place of
+/// axum's.
+pub mod extract;
+/// The async observer pattern explored in `guide/asyncer.rs`, for subsystems reacting to a
+/// shared subject, e.g. alert rules evaluated on each incoming quote.
+pub mod observer;
+/// `?page=&limit=` of list endpoints.
+pub mod paging;
@@ -0,0 +1,21 @@
+use std::future::Future;
+use std::pin::Pin;
+
+/// A boxed future borrowing from its observer and subject, as async trait methods can't be
+/// named in an associated type.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+pub trait Observer: Send + Sync {
+    type Subject;
+
+    fn observe<'a>(&'a self, subject: &'a Self::Subject) -> BoxFuture<'a, ()>;
+}
+
+pub trait Observable {
+    type Observer;
+
+    /// Notify every attached observer of the current state, concurrently.
+    fn update(&self) -> BoxFuture<'_, ()>;
+    fn attach(&mut self, observer: Self::Observer);
+    fn detach(&mut self, observer: Self::Observer);
+}
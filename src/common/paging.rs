@@ -0,0 +1,43 @@
+/// The `page` and `limit` query parameters of list endpoints, pages counted from 1.
+#[derive(serde::Deserialize, Debug, Default, Clone, Copy)]
+#[serde(default)]
+pub struct Paging {
+    pub page: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+impl Paging {
+    /// The `LIMIT` and `OFFSET` of the page, with `default` rows per page and at most `max`.
+    ///
+    /// Pages far past the end are empty like any page past the end, rather than overflowing.
+    pub fn limit_offset(self, default: usize, max: usize) -> (i64, i64) {
+        let limit = self.limit.unwrap_or(default).min(max);
+        let offset = (self.page.unwrap_or(1).max(1) - 1).saturating_mul(limit);
+
+        (
+            i64::try_from(limit).unwrap_or(i64::MAX),
+            i64::try_from(offset).unwrap_or(i64::MAX),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_and_offset() {
+        let paging = |page, limit| Paging { page, limit };
+
+        assert_eq!(paging(None, None).limit_offset(10, 100), (10, 0));
+        assert_eq!(paging(Some(0), Some(20)).limit_offset(10, 100), (20, 0));
+        assert_eq!(
+            paging(Some(3), Some(1000)).limit_offset(10, 100),
+            (100, 200)
+        );
+        assert_eq!(
+            paging(Some(usize::MAX), None).limit_offset(10, 100),
+            (10, i64::MAX)
+        );
+    }
+}
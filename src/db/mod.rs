@@ -0,0 +1,240 @@
+mod seed;
+
+pub use seed::{Fixture, SeedReport, FIXTURE_VERSION};
+
+use std::collections::HashMap;
+
+use sqlx::migrate::{AppliedMigration, Migrate, MigrateError, Migrator};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{PgPool, SqlitePool};
+
+use crate::conn::get_database_pool;
+use crate::store::{Backend, Store};
+
+/// The SQLite schema, embedded at build time.
+pub static SQLITE_MIGRATIONS: Migrator = sqlx::migrate!("./migrations");
+
+/// The Postgres schema of the data in `store`, embedded at build time.
+pub static POSTGRES_MIGRATIONS: Migrator = sqlx::migrate!("./migrations_postgres");
+
+/// A database of either backend, for managing its schema and data.
+#[derive(Clone)]
+pub enum Database {
+    Sqlite(SqlitePool),
+    Postgres(PgPool),
+}
+
+/// A migration known to this build or applied to the database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but from another script than the one of this build.
+    Modified,
+    /// Applied, but unknown to this build, e.g. by a newer version.
+    Unknown,
+}
+
+impl std::fmt::Display for MigrationState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Applied => "applied",
+            Self::Pending => "pending",
+            Self::Modified => "modified",
+            Self::Unknown => "unknown",
+        })
+    }
+}
+
+impl Database {
+    /// Connect to the database of `url`, SQLite or Postgres depending on its scheme.
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        match Backend::of(url) {
+            Some(Backend::Sqlite) => Ok(Self::Sqlite(get_database_pool(url).await?)),
+            Some(Backend::Postgres) => Ok(Self::Postgres(PgPoolOptions::new().connect(url).await?)),
+            None => anyhow::bail!("unsupported database URL, expected sqlite: or postgres://"),
+        }
+    }
+
+    pub fn backend(&self) -> Backend {
+        match self {
+            Self::Sqlite(_) => Backend::Sqlite,
+            Self::Postgres(_) => Backend::Postgres,
+        }
+    }
+
+    pub fn migrator(&self) -> &'static Migrator {
+        match self {
+            Self::Sqlite(_) => &SQLITE_MIGRATIONS,
+            Self::Postgres(_) => &POSTGRES_MIGRATIONS,
+        }
+    }
+
+    pub fn store(&self) -> Store {
+        match self {
+            Self::Sqlite(pool) => Store::sqlite(pool.clone()),
+            Self::Postgres(pool) => Store::postgres(pool.clone()),
+        }
+    }
+
+    /// Every migration by version, whether applied or not.
+    pub async fn migrations(&self) -> Result<Vec<MigrationStatus>, MigrateError> {
+        let applied = match self {
+            Self::Sqlite(pool) => applied_migrations(&mut *pool.acquire().await?).await?,
+            Self::Postgres(pool) => applied_migrations(&mut *pool.acquire().await?).await?,
+        };
+        let mut applied: HashMap<_, _> = applied
+            .into_iter()
+            .map(|migration| (migration.version, migration.checksum))
+            .collect();
+
+        let mut migrations: Vec<_> = self
+            .migrator()
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .map(|migration| MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                state: match applied.remove(&migration.version) {
+                    None => MigrationState::Pending,
+                    Some(checksum) if checksum == migration.checksum => MigrationState::Applied,
+                    Some(_) => MigrationState::Modified,
+                },
+            })
+            .collect();
+        migrations.extend(applied.into_keys().map(|version| MigrationStatus {
+            version,
+            description: String::new(),
+            state: MigrationState::Unknown,
+        }));
+        migrations.sort_by_key(|migration| migration.version);
+
+        Ok(migrations)
+    }
+
+    /// The migrations keeping the schema from matching this build: pending, modified or unknown.
+    pub async fn out_of_date_migrations(&self) -> Result<Vec<MigrationStatus>, MigrateError> {
+        let mut migrations = self.migrations().await?;
+        migrations.retain(|migration| migration.state != MigrationState::Applied);
+        Ok(migrations)
+    }
+
+    /// The migrations still to apply.
+    pub async fn pending_migrations(&self) -> Result<Vec<MigrationStatus>, MigrateError> {
+        let mut migrations = self.migrations().await?;
+        migrations.retain(|migration| migration.state == MigrationState::Pending);
+        Ok(migrations)
+    }
+
+    /// Apply the pending migrations, returning them.
+    pub async fn migrate_up(&self) -> Result<Vec<MigrationStatus>, MigrateError> {
+        let pending = self.pending_migrations().await?;
+        match self {
+            Self::Sqlite(pool) => self.migrator().run(pool).await?,
+            Self::Postgres(pool) => self.migrator().run(pool).await?,
+        }
+        Ok(pending)
+    }
+
+    /// Revert the last `steps` applied migrations, returning them latest first.
+    pub async fn migrate_down(&self, steps: usize) -> Result<Vec<MigrationStatus>, MigrateError> {
+        let mut applied = self.migrations().await?;
+        applied.retain(|migration| migration.state != MigrationState::Pending);
+
+        let keep = applied.len().saturating_sub(steps);
+        let target = keep.checked_sub(1).map_or(0, |last| applied[last].version);
+        let mut reverted = applied.split_off(keep);
+        reverted.reverse();
+
+        match self {
+            Self::Sqlite(pool) => self.migrator().undo(pool, target).await?,
+            Self::Postgres(pool) => self.migrator().undo(pool, target).await?,
+        }
+        Ok(reverted)
+    }
+
+    /// Check that the database answers queries.
+    pub async fn ping(&self) -> Result<(), sqlx::Error> {
+        match self {
+            Self::Sqlite(pool) => sqlx::query("SELECT 1").execute(pool).await.map(drop),
+            Self::Postgres(pool) => sqlx::query("SELECT 1").execute(pool).await.map(drop),
+        }
+    }
+
+    pub async fn close(&self) {
+        match self {
+            Self::Sqlite(pool) => pool.close().await,
+            Self::Postgres(pool) => pool.close().await,
+        }
+    }
+}
+
+async fn applied_migrations<C: Migrate>(
+    conn: &mut C,
+) -> Result<Vec<AppliedMigration>, MigrateError> {
+    conn.ensure_migrations_table().await?;
+    if let Some(version) = conn.dirty_version().await? {
+        return Err(MigrateError::Dirty(version));
+    }
+    conn.list_applied_migrations().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn states(migrations: &[MigrationStatus]) -> Vec<MigrationState> {
+        migrations.iter().map(|migration| migration.state).collect()
+    }
+
+    #[tokio::test]
+    async fn migrate_up_and_down() {
+        let pool = get_database_pool("sqlite::memory:").await.unwrap();
+        let db = Database::Sqlite(pool.clone());
+        let count = SQLITE_MIGRATIONS
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .count();
+
+        let pending = db.pending_migrations().await.unwrap();
+        assert_eq!(pending.len(), count);
+        assert_eq!(db.migrate_up().await.unwrap(), pending);
+        assert!(db.pending_migrations().await.unwrap().is_empty());
+        assert!(db.migrate_up().await.unwrap().is_empty());
+
+        let reverted = db.migrate_down(2).await.unwrap();
+        assert_eq!(
+            reverted.iter().map(|m| m.version).collect::<Vec<_>>(),
+            [pending[count - 1].version, pending[count - 2].version]
+        );
+        let mut expected = vec![MigrationState::Applied; count - 2];
+        expected.extend([MigrationState::Pending; 2]);
+        assert_eq!(states(&db.migrations().await.unwrap()), expected);
+
+        // A migration applied by another build shows up, and blocks applying ours.
+        sqlx::query(
+            "INSERT INTO _sqlx_migrations
+             (version, description, success, checksum, execution_time)
+             VALUES (99990101000000, 'from the future', TRUE, x'00', 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let migrations = db.migrations().await.unwrap();
+        assert_eq!(migrations.last().unwrap().state, MigrationState::Unknown);
+        assert!(db.migrate_up().await.is_err());
+        let mut expected = vec![MigrationState::Pending; 2];
+        expected.push(MigrationState::Unknown);
+        assert_eq!(
+            states(&db.out_of_date_migrations().await.unwrap()),
+            expected
+        );
+    }
+}
@@ -0,0 +1,355 @@
+use std::path::Path;
+
+use anyhow::Context;
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::auth::{hash_password, validate_signup};
+use crate::common::errors::Error;
+use crate::portfolios::model::{NewPortfolio, NewTransaction};
+use crate::portfolios::{check_new_portfolio, check_new_transaction};
+use crate::stocks::check_new_stock;
+use crate::stocks::model::NewStock;
+use crate::store::{Registration, Store, StoreError};
+
+/// The fixture format this build reads, bumped on incompatible changes.
+pub const FIXTURE_VERSION: u32 = 1;
+
+/// Data to seed a database with, e.g. the demo data of `fixtures/demo.toml`.
+///
+/// Entries take the shape of the API request bodies.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fixture {
+    pub version: u32,
+    #[serde(default)]
+    pub stocks: Vec<NewStock>,
+    #[serde(default)]
+    pub users: Vec<FixtureUser>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixtureUser {
+    pub username: String,
+    pub email: String,
+    /// In plain text, fixtures are for development and demos.
+    pub password: String,
+    #[serde(default)]
+    pub portfolios: Vec<FixturePortfolio>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct FixturePortfolio {
+    pub name: String,
+    pub base_currency: String,
+    #[serde(default)]
+    pub transactions: Vec<NewTransaction>,
+}
+
+/// How many entries seeding created, and skipped as already there.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SeedReport {
+    pub stocks: usize,
+    pub users: usize,
+    pub portfolios: usize,
+    pub transactions: usize,
+    pub skipped: usize,
+}
+
+impl std::fmt::Display for SeedReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "created {} stocks, {} users, {} portfolios and {} transactions, skipped {} already there",
+            self.stocks, self.users, self.portfolios, self.transactions, self.skipped
+        )
+    }
+}
+
+impl Fixture {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read fixture {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("invalid fixture {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let fixture: Self = toml::from_str(text)?;
+        if fixture.version != FIXTURE_VERSION {
+            anyhow::bail!(
+                "fixture version {} isn't supported, this build reads version {FIXTURE_VERSION}",
+                fixture.version
+            );
+        }
+        Ok(fixture)
+    }
+
+    /// Add the fixture's data to `store`, checked as the API would check it.
+    ///
+    /// Seeding again is harmless: stocks, users (by email) and portfolios already there are
+    /// skipped, along with the transactions of skipped portfolios. Each portfolio is created
+    /// along with its ledger, or not at all.
+    pub async fn seed(&self, store: &Store) -> anyhow::Result<SeedReport> {
+        let fixture = self.checked()?;
+        let now = Utc::now();
+        let mut report = SeedReport::default();
+
+        for stock in &fixture.stocks {
+            match store.stocks.create_stock(stock, now).await {
+                Ok(_) => report.stocks += 1,
+                Err(StoreError::Conflict(_)) => report.skipped += 1,
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        for user in &fixture.users {
+            let user_id = match store.users.credentials(&user.email).await? {
+                Some(existing) => {
+                    report.skipped += 1;
+                    existing.user_id
+                }
+                None => {
+                    let registration = Registration {
+                        username: user.username.clone(),
+                        email: user.email.clone(),
+                        password_hash: hash_password(user.password.clone()).await?,
+                    };
+                    let created = store
+                        .users
+                        .create_user(&registration, now)
+                        .await
+                        .with_context(|| format!("failed to create user {}", user.username))?;
+                    report.users += 1;
+                    created.user_id
+                }
+            };
+
+            for portfolio in &user.portfolios {
+                for symbol in portfolio.transactions.iter().flat_map(|t| &t.symbol) {
+                    if store.stocks.stock(symbol).await?.is_none() {
+                        anyhow::bail!(
+                            "portfolio {} trades unknown symbol {symbol}",
+                            portfolio.name
+                        );
+                    }
+                }
+
+                let new = NewPortfolio {
+                    name: portfolio.name.clone(),
+                    base_currency: portfolio.base_currency.clone(),
+                };
+                let created = store
+                    .portfolios
+                    .create_portfolio_with_ledger(user_id, &new, &portfolio.transactions, now)
+                    .await;
+                match created {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(oversold)) => anyhow::bail!(
+                        "portfolio {} sells more {} than held on {}",
+                        portfolio.name,
+                        oversold.symbol,
+                        oversold.trade_date
+                    ),
+                    Err(StoreError::Conflict(_)) => {
+                        report.skipped += 1;
+                        continue;
+                    }
+                    Err(error) => return Err(error.into()),
+                }
+                report.portfolios += 1;
+                report.transactions += portfolio.transactions.len();
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// The fixture normalized and validated like the API requests it mirrors, so that a bad
+    /// entry fails seeding before anything is written.
+    fn checked(&self) -> anyhow::Result<Self> {
+        let stocks = self
+            .stocks
+            .iter()
+            .map(|stock| {
+                check_new_stock(stock.clone())
+                    .map_err(|e| invalid(format!("stock {}", stock.symbol), e))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let mut users = Vec::with_capacity(self.users.len());
+        for user in &self.users {
+            let username = user.username.trim();
+            let email = user.email.trim();
+            validate_signup(username, email, &user.password)
+                .map_err(|e| invalid(format!("user {username}"), e))?;
+
+            let mut portfolios = Vec::with_capacity(user.portfolios.len());
+            for portfolio in &user.portfolios {
+                let what = format!("portfolio {} of user {username}", portfolio.name);
+                let new = check_new_portfolio(NewPortfolio {
+                    name: portfolio.name.clone(),
+                    base_currency: portfolio.base_currency.clone(),
+                })
+                .map_err(|e| invalid(&what, e))?;
+                let transactions = portfolio
+                    .transactions
+                    .iter()
+                    .enumerate()
+                    .map(|(n, transaction)| {
+                        check_new_transaction(transaction.clone())
+                            .map_err(|e| invalid(format!("transaction {} of {what}", n + 1), e))
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+
+                portfolios.push(FixturePortfolio {
+                    name: new.name,
+                    base_currency: new.base_currency,
+                    transactions,
+                });
+            }
+
+            users.push(FixtureUser {
+                username: username.to_string(),
+                email: email.to_string(),
+                password: user.password.clone(),
+                portfolios,
+            });
+        }
+
+        Ok(Self {
+            version: self.version,
+            stocks,
+            users,
+        })
+    }
+}
+
+/// A validation `error` of `what`, listing the offending fields like the API would.
+fn invalid(what: impl std::fmt::Display, error: Error) -> anyhow::Error {
+    let Error::UnprocessableEntity { errors } = error else {
+        return anyhow::Error::new(error).context(format!("failed to check {what}"));
+    };
+    let mut fields: Vec<_> = errors
+        .iter()
+        .map(|(field, messages)| format!("{field} {}", messages.join(", ")))
+        .collect();
+    fields.sort();
+    anyhow::anyhow!("invalid {what}: {}", fields.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conn::test_database;
+
+    const DEMO: &str = include_str!("../../fixtures/demo.toml");
+
+    #[tokio::test]
+    async fn seeding_the_demo_twice() {
+        let store = Store::sqlite(test_database().await);
+        let fixture = Fixture::parse(DEMO).unwrap();
+
+        let report = fixture.seed(&store).await.unwrap();
+        assert_eq!(report.stocks, fixture.stocks.len());
+        assert_eq!(report.users, fixture.users.len());
+        assert!(report.transactions > 0);
+        assert_eq!(report.skipped, 0);
+
+        let again = fixture.seed(&store).await.unwrap();
+        assert_eq!(
+            again,
+            SeedReport {
+                skipped: report.stocks + report.users + report.portfolios,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn bad_fixtures_write_nothing() {
+        let store = Store::sqlite(test_database().await);
+        let stocks = "version = 1
+
+[[stocks]]
+symbol = \"acme\"
+exchange = \"xnys\"
+name = \"Acme\"
+currency = \"usd\"
+
+[[users]]
+username = \"demo\"
+email = \"demo@example.com\"
+password = \"demo-password\"
+
+[[users.portfolios]]
+name = \"Demo\"
+baseCurrency = \"USD\"
+";
+
+        // Checked like API requests, before anything is written.
+        let zero_quantity = format!(
+            "{stocks}
+[[users.portfolios.transactions]]
+type = \"buy\"
+symbol = \"ACME\"
+tradeDate = \"2024-01-03\"
+quantity = 0.0
+price = 10.0
+"
+        );
+        let error = Fixture::parse(&zero_quantity)
+            .unwrap()
+            .seed(&store)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid transaction 1 of portfolio Demo of user demo: quantity must be a positive number"
+        );
+        assert!(store.stocks.stock("ACME").await.unwrap().is_none());
+
+        // A portfolio whose ledger can't be booked isn't created, so seeding it again once fixed
+        // doesn't skip it.
+        let oversold = format!(
+            "{stocks}
+[[users.portfolios.transactions]]
+type = \"sell\"
+symbol = \"acme\"
+tradeDate = \"2024-01-03\"
+quantity = 1.0
+price = 10.0
+"
+        );
+        let error = Fixture::parse(&oversold)
+            .unwrap()
+            .seed(&store)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("sells more ACME"));
+        let user_id = store
+            .users
+            .credentials("demo@example.com")
+            .await
+            .unwrap()
+            .unwrap()
+            .user_id;
+        assert!(store
+            .portfolios
+            .list_portfolios(user_id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let report = Fixture::parse(stocks).unwrap().seed(&store).await.unwrap();
+        assert_eq!(report.portfolios, 1);
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let error = Fixture::parse("version = 2").unwrap_err();
+        assert!(error.to_string().contains("version 2"));
+        assert!(Fixture::parse("version = 1\n[[stocks]]\nsymbol = \"X\"").is_err());
+    }
+}
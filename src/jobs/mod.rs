@@ -0,0 +1,27 @@
+mod model;
+mod schedule;
+mod scheduler;
+mod tasks;
+
+pub use model::{JobRun, JobStatus};
+pub use schedule::{ParseScheduleError, Schedule};
+pub use scheduler::{Retry, Scheduler};
+pub use tasks::{AlertSweep, EodIngest, PruneJobRuns, PruneSessions};
+
+use axum::async_trait;
+
+use crate::app::AppState;
+use crate::common::cancel::CancellationToken;
+
+/// A unit of background work run by the [`Scheduler`].
+#[async_trait]
+pub trait Job: Send + Sync {
+    /// Identifies the job in `job_run` and logs.
+    fn name(&self) -> &'static str;
+
+    /// Do the work once, erroring to have it retried.
+    ///
+    /// Jobs are never aborted: long running ones should check `cancel` between units of work
+    /// and return an error when it is set, the run is then recorded as cancelled.
+    async fn run(&self, state: &AppState, cancel: &CancellationToken) -> anyhow::Result<()>;
+}
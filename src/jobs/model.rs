@@ -0,0 +1,31 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Serialize)]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+    /// Stopped early on shutdown.
+    Cancelled,
+}
+
+/// One attempt at running a scheduled job.
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobRun {
+    #[serde(rename = "id")]
+    pub job_run_id: i64,
+    pub job_name: String,
+    /// When the schedule fired, shared by every attempt of a run.
+    pub scheduled_for: DateTime<Utc>,
+    /// Starting at 1.
+    pub attempt: i64,
+    pub status: JobStatus,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
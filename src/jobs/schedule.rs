@@ -0,0 +1,212 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Utc};
+
+/// A cron-like schedule in UTC: `minute hour day-of-month month day-of-week`.
+///
+/// Each field is `*`, a number, a range `a-b`, any of them stepped with `/n`, or a comma
+/// separated list of those. Days of week run from 0 (Sunday) to 6, 7 also meaning Sunday. As in
+/// cron, a day matches either of day-of-month or day-of-week when both are restricted.
+///
+/// `@hourly`, `@daily`, `@weekly` and `@monthly` are accepted too.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day fields were restricted, for the either-or rule on days.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("invalid schedule `{schedule}`: {reason}")]
+pub struct ParseScheduleError {
+    schedule: String,
+    reason: String,
+}
+
+impl FromStr for Schedule {
+    type Err = ParseScheduleError;
+
+    fn from_str(schedule: &str) -> Result<Self, Self::Err> {
+        let error = |reason: String| ParseScheduleError {
+            schedule: schedule.to_string(),
+            reason,
+        };
+
+        let expanded = match schedule.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(error(format!("expected 5 fields, got {}", fields.len())));
+        };
+
+        let field = |text: &str, name: &str, min: u32, max: u32| {
+            parse_field(text, min, max).map_err(|reason| error(format!("{name}: {reason}")))
+        };
+        let mut weekdays_set = field(weekdays, "day of week", 0, 7)?;
+        // Fold 7 onto Sunday.
+        if weekdays_set & 1 << 7 != 0 {
+            weekdays_set |= 1;
+        }
+
+        Ok(Self {
+            minutes: field(minutes, "minute", 0, 59)?,
+            hours: field(hours, "hour", 0, 23)?,
+            days: field(days, "day of month", 1, 31)?,
+            months: field(months, "month", 1, 12)?,
+            weekdays: weekdays_set,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+}
+
+/// Parse one field into a bit set of the values it matches.
+fn parse_field(text: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut set = 0;
+
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("bad step `{step}`"))?;
+                if step == 0 {
+                    return Err("step must not be 0".to_string());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let number = |text: &str| -> Result<u32, String> {
+            match text.parse() {
+                Ok(value) if (min..=max).contains(&value) => Ok(value),
+                _ => Err(format!("`{text}` is not in {min}-{max}")),
+            }
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (number(start)?, number(end)?),
+                // `5/15` means from 5 to the end, every 15.
+                None if step > 1 => (number(range)?, max),
+                None => (number(range)?, number(range)?),
+            },
+        };
+        if start > end {
+            return Err(format!("empty range `{range}`"));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+
+    Ok(set)
+}
+
+impl Schedule {
+    /// The first time strictly after `after` the schedule fires, at the start of a minute.
+    ///
+    /// `None` if it never does, e.g. on February 30th.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut time = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        // Every combination of day-of-month and day-of-week repeats within 28 years.
+        let limit = time + Duration::days(366 * 28);
+
+        while time < limit {
+            if !self.matches_day(time.date_naive()) {
+                time = (time.date_naive() + Duration::days(1))
+                    .and_time(chrono::NaiveTime::MIN)
+                    .and_utc();
+            } else if !contains(self.hours, time.hour()) {
+                time = time.with_minute(0)? + Duration::hours(1);
+            } else if !contains(self.minutes, time.minute()) {
+                time += Duration::minutes(1);
+            } else {
+                return Some(time);
+            }
+        }
+
+        None
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if !contains(self.months, date.month()) {
+            return false;
+        }
+
+        let day = contains(self.days, date.day());
+        let weekday = contains(self.weekdays, date.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (false, true) => day,
+            (true, false) => weekday,
+            (false, false) => day || weekday,
+        }
+    }
+}
+
+fn contains(set: u64, value: u32) -> bool {
+    set & 1 << value != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn next(schedule: &str, after: &str) -> Option<String> {
+        let schedule: Schedule = schedule.parse().unwrap();
+        schedule
+            .next_after(after.parse().unwrap())
+            .map(|time| time.to_rfc3339())
+    }
+
+    #[test]
+    fn next_firing() {
+        let after = "2024-01-05T22:31:10Z"; // A Friday.
+
+        assert_eq!(
+            next("*/5 * * * *", after).as_deref(),
+            Some("2024-01-05T22:35:00+00:00")
+        );
+        assert_eq!(
+            next("30 22 * * 1-5", after).as_deref(),
+            Some("2024-01-08T22:30:00+00:00")
+        );
+        assert_eq!(
+            next("@daily", after).as_deref(),
+            Some("2024-01-06T00:00:00+00:00")
+        );
+        assert_eq!(
+            next("0 12 1 * 7", after).as_deref(),
+            Some("2024-01-07T12:00:00+00:00")
+        );
+        assert_eq!(
+            next("0 0 29 2 *", after).as_deref(),
+            Some("2024-02-29T00:00:00+00:00")
+        );
+        assert_eq!(next("0 0 30 2 *", after), None);
+    }
+
+    #[test]
+    fn rejects_invalid_fields() {
+        for schedule in [
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "a * * * *",
+        ] {
+            assert!(schedule.parse::<Schedule>().is_err(), "{schedule}");
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::task::JoinHandle;
+
+use super::model::JobStatus;
+use super::schedule::Schedule;
+use super::Job;
+use crate::app::AppState;
+use crate::common::cancel::CancellationToken;
+use crate::common::errors::Result;
+
+/// How often a failed run is attempted, doubling the delay between attempts.
+#[derive(Debug, Clone, Copy)]
+pub struct Retry {
+    /// Attempts in total, at least 1.
+    pub attempts: u32,
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+impl Retry {
+    /// Delay before the attempt following `attempt`.
+    fn delay(&self, attempt: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_backoff)
+    }
+}
+
+struct Entry {
+    schedule: Schedule,
+    job: Arc<dyn Job>,
+    retry: Retry,
+}
+
+/// Runs jobs on their schedules until cancelled, recording every attempt in `job_run`.
+///
+/// Each job has its own task, so a slow job only delays itself. Firings missed while a job is
+/// still running are skipped rather than caught up on.
+pub struct Scheduler {
+    state: Arc<AppState>,
+    cancel: CancellationToken,
+    entries: Vec<Entry>,
+}
+
+impl Scheduler {
+    pub fn new(state: Arc<AppState>, cancel: CancellationToken) -> Self {
+        Self {
+            state,
+            cancel,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, schedule: Schedule, job: impl Job + 'static, retry: Retry) {
+        self.entries.push(Entry {
+            schedule,
+            job: Arc::new(job),
+            retry,
+        });
+    }
+
+    /// Start every job, the handle resolving once they all stopped after cancellation.
+    pub fn spawn(self) -> JoinHandle<()> {
+        let tasks: Vec<_> = self
+            .entries
+            .into_iter()
+            .map(|entry| tokio::spawn(run_schedule(self.state.clone(), self.cancel.clone(), entry)))
+            .collect();
+
+        tokio::spawn(async move {
+            futures::future::join_all(tasks).await;
+        })
+    }
+}
+
+async fn run_schedule(state: Arc<AppState>, cancel: CancellationToken, entry: Entry) {
+    let name = entry.job.name();
+    let mut after = Utc::now();
+
+    loop {
+        let Some(due) = entry.schedule.next_after(after) else {
+            tracing::warn!(job = name, "schedule never fires again");
+            return;
+        };
+        let wait = (due - Utc::now()).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = tokio::time::sleep(wait) => {}
+        }
+
+        if let Err(error) = run_job(&state, &cancel, &*entry.job, entry.retry, due).await {
+            tracing::error!(job = name, "recording job run failed: {error}");
+        }
+        if cancel.is_cancelled() {
+            return;
+        }
+        after = Utc::now().max(due);
+    }
+}
+
+/// Run `job` for the firing at `scheduled_for`, retrying failed attempts.
+pub(super) async fn run_job(
+    state: &AppState,
+    cancel: &CancellationToken,
+    job: &dyn Job,
+    retry: Retry,
+    scheduled_for: DateTime<Utc>,
+) -> Result<JobStatus> {
+    let attempts = retry.attempts.max(1);
+    for attempt in 1..=attempts {
+        let job_run_id = state
+            .store
+            .jobs
+            .start_job_run(job.name(), scheduled_for, i64::from(attempt), Utc::now())
+            .await?;
+
+        let result = job.run(state, cancel).await;
+        let status = match &result {
+            Ok(()) => JobStatus::Succeeded,
+            Err(_) if cancel.is_cancelled() => JobStatus::Cancelled,
+            Err(_) => JobStatus::Failed,
+        };
+        let error = result.err().map(|error| format!("{error:#}"));
+
+        state
+            .store
+            .jobs
+            .finish_job_run(job_run_id, status, error.as_deref(), Utc::now())
+            .await?;
+
+        match status {
+            JobStatus::Failed if attempt < attempts => {
+                let delay = retry.delay(attempt);
+                tracing::warn!(
+                    job = job.name(),
+                    attempt,
+                    "job failed, retrying in {delay:?}: {}",
+                    error.unwrap_or_default()
+                );
+                tokio::select! {
+                    _ = cancel.cancelled() => return Ok(JobStatus::Cancelled),
+                    _ = tokio::time::sleep(delay) => {}
+                }
+            }
+            JobStatus::Failed => {
+                tracing::error!(
+                    job = job.name(),
+                    attempt,
+                    "job failed: {}",
+                    error.unwrap_or_default()
+                );
+                return Ok(status);
+            }
+            status => {
+                tracing::debug!(job = job.name(), attempt, "job {status:?}");
+                return Ok(status);
+            }
+        }
+    }
+
+    unreachable!("the last attempt always returns")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conn::test_database;
+    use crate::jobs::JobRun;
+    use axum::async_trait;
+    use sqlx::SqlitePool;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Fails until its `successes_after`-th run.
+    struct Flaky {
+        runs: Arc<AtomicU32>,
+        successes_after: u32,
+    }
+
+    #[async_trait]
+    impl Job for Flaky {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        async fn run(&self, _: &AppState, cancel: &CancellationToken) -> anyhow::Result<()> {
+            let runs = self.runs.fetch_add(1, Ordering::SeqCst) + 1;
+            if cancel.is_cancelled() {
+                anyhow::bail!("cancelled");
+            }
+            if runs < self.successes_after {
+                anyhow::bail!("run {runs} failed");
+            }
+            Ok(())
+        }
+    }
+
+    fn retry(attempts: u32) -> Retry {
+        Retry {
+            attempts,
+            backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        }
+    }
+
+    async fn job_runs(db: &SqlitePool) -> Vec<JobRun> {
+        sqlx::query_as("SELECT * FROM job_run ORDER BY job_run_id")
+            .fetch_all(db)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn retries_until_success_or_attempts_run_out() {
+        let db = test_database().await;
+        let state = AppState::new(db.clone());
+        let cancel = CancellationToken::new();
+        let now = Utc::now();
+        let flaky = |successes_after| Flaky {
+            runs: Arc::default(),
+            successes_after,
+        };
+
+        let status = run_job(&state, &cancel, &flaky(3), retry(3), now).await;
+        assert_eq!(status.unwrap(), JobStatus::Succeeded);
+        let status = run_job(&state, &cancel, &flaky(3), retry(2), now).await;
+        assert_eq!(status.unwrap(), JobStatus::Failed);
+
+        let runs = job_runs(&db).await;
+        let attempts: Vec<(i64, JobStatus)> =
+            runs.iter().map(|run| (run.attempt, run.status)).collect();
+        assert_eq!(
+            attempts,
+            [
+                (1, JobStatus::Failed),
+                (2, JobStatus::Failed),
+                (3, JobStatus::Succeeded),
+                (1, JobStatus::Failed),
+                (2, JobStatus::Failed),
+            ]
+        );
+        assert_eq!(runs[0].error.as_deref(), Some("run 1 failed"));
+        assert!(runs.iter().all(|run| run.finished_at.is_some()));
+
+        cancel.cancel();
+        let status = run_job(&state, &cancel, &flaky(1), retry(3), now).await;
+        assert_eq!(status.unwrap(), JobStatus::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn stops_on_cancellation() {
+        let db = test_database().await;
+        let state = Arc::new(AppState::new(db.clone()));
+        let cancel = CancellationToken::new();
+        let runs = Arc::new(AtomicU32::new(0));
+
+        let mut scheduler = Scheduler::new(state.clone(), cancel.clone());
+        let job = Flaky {
+            runs: runs.clone(),
+            successes_after: 0,
+        };
+        scheduler.add("* * * * *".parse().unwrap(), job, Retry::default());
+        let running = scheduler.spawn();
+
+        // Waiting for the next minute, so nothing ran yet.
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        cancel.cancel();
+        tokio::time::timeout(Duration::from_secs(1), running)
+            .await
+            .expect("scheduler stops on cancellation")
+            .unwrap();
+
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+        assert!(job_runs(&db).await.is_empty());
+    }
+}
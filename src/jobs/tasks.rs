@@ -0,0 +1,236 @@
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use axum::async_trait;
+use chrono::{Duration, NaiveDate, Utc};
+
+use super::Job;
+use crate::alerts::AlertEngine;
+use crate::app::AppState;
+use crate::common::cancel::CancellationToken;
+use crate::market::MarketDataProvider;
+use crate::prices::feed::publish_latest_bar;
+use crate::prices::{validate_bar, NewBar};
+
+/// How far back a symbol without any stored bar is backfilled.
+const EOD_BACKFILL_DAYS: i64 = 30;
+
+/// Pulls the daily bars each listed stock is missing from a market data provider.
+///
+/// Only dates after the latest stored bar are requested, so a retried or repeated run picks up
+/// where the last one stopped.
+pub struct EodIngest {
+    provider: Arc<dyn MarketDataProvider>,
+}
+
+impl EodIngest {
+    pub fn new(provider: Arc<dyn MarketDataProvider>) -> Self {
+        Self { provider }
+    }
+
+    async fn ingest(
+        &self,
+        state: &AppState,
+        symbol: &str,
+        today: NaiveDate,
+    ) -> anyhow::Result<usize> {
+        let latest = state.store.prices.latest_bars(symbol, 1).await?;
+        let from = match latest.first() {
+            Some(latest) => latest.date + Duration::days(1),
+            None => today - Duration::days(EOD_BACKFILL_DAYS),
+        };
+        if from > today {
+            return Ok(0);
+        }
+
+        let bars = self.provider.bars(symbol, from, today).await?;
+        let mut writer = state.store.prices.write_bars(symbol).await?;
+        for bar in &bars {
+            let bar = NewBar {
+                date: bar.date,
+                open: bar.open,
+                high: bar.high,
+                low: bar.low,
+                close: bar.close,
+                adj_close: Some(bar.adj_close),
+                volume: bar.volume,
+            };
+            if let Err(reason) = validate_bar(&bar) {
+                bail!("bar of {}: {reason}", bar.date);
+            }
+            writer.upsert(&bar).await?;
+        }
+        writer.commit().await?;
+
+        if !bars.is_empty() {
+            publish_latest_bar(state, symbol).await?;
+        }
+        Ok(bars.len())
+    }
+}
+
+#[async_trait]
+impl Job for EodIngest {
+    fn name(&self) -> &'static str {
+        "eod_ingest"
+    }
+
+    async fn run(&self, state: &AppState, cancel: &CancellationToken) -> anyhow::Result<()> {
+        let symbols = state.store.stocks.listed_symbols().await?;
+        let today = Utc::now().date_naive();
+
+        // One symbol failing doesn't hold back the others, the run fails at the end instead.
+        let mut failed = Vec::new();
+        let mut ingested = 0;
+        for symbol in &symbols {
+            if cancel.is_cancelled() {
+                bail!("cancelled with {ingested} bars ingested");
+            }
+            match self.ingest(state, symbol, today).await {
+                Ok(count) => ingested += count,
+                Err(error) => {
+                    tracing::warn!("end of day ingestion of {symbol} failed: {error:#}");
+                    failed.push(symbol.as_str());
+                }
+            }
+        }
+
+        if !failed.is_empty() {
+            bail!("ingestion failed for {}", failed.join(", "));
+        }
+        tracing::debug!("ingested {ingested} bars for {} symbols", symbols.len());
+        Ok(())
+    }
+}
+
+/// Evaluates alert rules against the latest quote of their symbol.
+///
+/// The `QuoteFeed` drops quotes when it falls behind, the sweep makes sure those still trigger
+/// alerts. Rules that already fired this session are left alone by `AlertEngine::evaluate`.
+pub struct AlertSweep {
+    engine: Arc<AlertEngine>,
+}
+
+impl AlertSweep {
+    pub fn new(engine: Arc<AlertEngine>) -> Self {
+        Self { engine }
+    }
+}
+
+#[async_trait]
+impl Job for AlertSweep {
+    fn name(&self) -> &'static str {
+        "alert_sweep"
+    }
+
+    async fn run(&self, state: &AppState, cancel: &CancellationToken) -> anyhow::Result<()> {
+        let symbols = state.store.alerts.alerted_symbols().await?;
+
+        let mut fired = 0;
+        for symbol in symbols {
+            if cancel.is_cancelled() {
+                bail!("cancelled with {fired} alerts triggered");
+            }
+            if let Some(quote) = state.quotes.latest(&symbol) {
+                fired += self
+                    .engine
+                    .evaluate(&quote)
+                    .await
+                    .with_context(|| format!("evaluating alerts of {symbol}"))?;
+            }
+        }
+
+        tracing::debug!("alert sweep triggered {fired} alerts");
+        Ok(())
+    }
+}
+
+/// Deletes finished `job_run` rows older than `keep`.
+pub struct PruneJobRuns {
+    keep: Duration,
+}
+
+impl PruneJobRuns {
+    pub fn new(keep: Duration) -> Self {
+        Self { keep }
+    }
+}
+
+#[async_trait]
+impl Job for PruneJobRuns {
+    fn name(&self) -> &'static str {
+        "prune_job_runs"
+    }
+
+    async fn run(&self, state: &AppState, _cancel: &CancellationToken) -> anyhow::Result<()> {
+        let pruned = state
+            .store
+            .jobs
+            .prune_job_runs(Utc::now() - self.keep)
+            .await?;
+
+        tracing::debug!("pruned {pruned} job runs");
+        Ok(())
+    }
+}
+
+/// Deletes expired sessions, refresh tokens, login challenges and used email tokens, which are
+/// already refused but would otherwise pile up.
+pub struct PruneSessions;
+
+#[async_trait]
+impl Job for PruneSessions {
+    fn name(&self) -> &'static str {
+        "prune_sessions"
+    }
+
+    async fn run(&self, state: &AppState, _cancel: &CancellationToken) -> anyhow::Result<()> {
+        let now = Utc::now();
+        let pruned = state.store.sessions.prune_sessions(now).await?;
+        let pruned_tokens = state.store.sessions.prune_refresh_tokens(now).await?;
+        let pruned_challenges = state.store.two_factor.prune_challenges(now).await?;
+        let pruned_email_tokens = state.store.sessions.prune_email_tokens(now).await?;
+
+        tracing::debug!(
+            "pruned {pruned} expired sessions, {pruned_tokens} refresh tokens, \
+             {pruned_challenges} login challenges and {pruned_email_tokens} used email tokens"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conn::test_database;
+    use crate::market::Simulator;
+    use crate::testing::insert_stock;
+
+    #[tokio::test]
+    async fn eod_ingest_fills_missing_bars() {
+        let db = test_database().await;
+        insert_stock(&db, "AAPL", "XNAS", "Apple").await;
+        let state = AppState::new(db.clone());
+        let job = EodIngest::new(Arc::new(Simulator::new(1, std::time::Duration::ZERO)));
+        let cancel = CancellationToken::new();
+
+        let count = || async {
+            sqlx::query_scalar::<_, i64>("SELECT count(*) FROM price_bar WHERE symbol = 'AAPL'")
+                .fetch_one(&db)
+                .await
+                .unwrap()
+        };
+
+        job.run(&state, &cancel).await.unwrap();
+        let backfilled = count().await;
+        // 30 days hold at least 20 weekdays.
+        assert!(backfilled >= 20, "{backfilled}");
+        assert!(state.quotes.latest("AAPL").is_some());
+
+        job.run(&state, &cancel).await.unwrap();
+        assert_eq!(count().await, backfilled);
+
+        cancel.cancel();
+        assert!(job.run(&state, &cancel).await.is_err());
+    }
+}
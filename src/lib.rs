@@ -4,7 +4,26 @@
 // However, this style better facilitates a guided exploration of the code, so it's the one
 // we'll be using in this project.
 
+pub mod admin;
+pub mod alerts;
+pub mod app;
+pub mod auth;
+pub mod common;
+pub mod config;
 pub mod conn;
+pub mod db;
+// Exploratory snippets kept for reference, not held to the lint set of the app code.
+#[allow(dead_code, mismatched_lifetime_syntaxes, clippy::all)]
 mod guide;
 pub mod helpers;
-mod web;
+pub mod jobs;
+pub mod mail;
+pub mod market;
+pub mod portfolios;
+pub mod prices;
+pub mod stocks;
+pub mod store;
+/// Helpers shared by handler tests.
+#[cfg(test)]
+mod testing;
+pub mod watchlists;
@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use axum::async_trait;
+use chrono::Utc;
+
+use super::{Email, Mailer};
+
+/// Writes emails out instead of sending them, for development and tests.
+pub struct FileMailer {
+    /// `None` for stdout.
+    dir: Option<PathBuf>,
+    sent: AtomicU64,
+}
+
+impl FileMailer {
+    /// Prints emails to stdout.
+    pub fn stdout() -> Self {
+        Self {
+            dir: None,
+            sent: AtomicU64::new(0),
+        }
+    }
+
+    /// Writes each email to its own `.eml` file in `dir`, created if missing.
+    pub fn dir(dir: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+
+        Ok(Self {
+            dir: Some(dir),
+            sent: AtomicU64::new(0),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> anyhow::Result<()> {
+        let message = format!(
+            "To: {}\nSubject: {}\n\n{}\n",
+            email.to, email.subject, email.body
+        );
+
+        match &self.dir {
+            Some(dir) => {
+                // Named so that listing the directory shows them in the order they were sent.
+                let name = format!(
+                    "{}-{:04}.eml",
+                    Utc::now().format("%Y%m%dT%H%M%S%.6f"),
+                    self.sent.fetch_add(1, Ordering::Relaxed)
+                );
+                tokio::fs::write(dir.join(name), message).await?;
+            }
+            None => println!("{message}"),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+mod file;
+mod smtp;
+
+pub use file::FileMailer;
+pub use smtp::SmtpMailer;
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use axum::async_trait;
+
+use crate::config::MailConfig;
+
+/// A way of sending email, e.g. an SMTP relay, or files for development and tests.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> anyhow::Result<()>;
+}
+
+/// A plain text email.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Pick a mailer from the config: its SMTP relay, else files in its directory, else stdout.
+pub fn mailer_from_config(config: &MailConfig) -> anyhow::Result<Arc<dyn Mailer>> {
+    let mailer: Arc<dyn Mailer> = match (&config.smtp_url, &config.dir) {
+        (Some(url), _) => {
+            let from = config
+                .from
+                .as_deref()
+                .context("mail.from must be set to send over SMTP")?;
+            Arc::new(SmtpMailer::new(url.expose(), from)?)
+        }
+        (None, Some(dir)) => Arc::new(FileMailer::dir(dir)?),
+        (None, None) => Arc::new(FileMailer::stdout()),
+    };
+
+    Ok(mailer)
+}
@@ -1,66 +1,265 @@
+use anyhow::Context;
 use axum::{
-    // async_trait,
     body::{Body, Bytes},
     error_handling::HandleErrorLayer,
-    extract::{Extension, FromRequest, MatchedPath, Path, Query, Request},
+    extract::{MatchedPath, Request},
     http::{HeaderMap, StatusCode},
-    middleware::{self, Next},
-    response::{Html, IntoResponse, Response},
-    routing::{delete, get, post},
-    BoxError,
-    Form,
-    Json,
-    RequestExt,
-    Router,
+    response::{IntoResponse, Response},
+    routing::get,
+    BoxError, Router,
 };
+use clap::{Parser, Subcommand};
+use std::future::IntoFuture;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
-use stockrs::conn::get_database_pool;
+use stockrs::alerts::AlertEngine;
+use stockrs::app::{api_router, AppState};
+use stockrs::auth::JwtKeys;
+use stockrs::common::errors::Error;
+use stockrs::common::observer::Observable;
+use stockrs::config::Config;
+use stockrs::db::{Database, Fixture, MigrationState};
+use stockrs::jobs::{AlertSweep, EodIngest, PruneJobRuns, PruneSessions, Retry, Scheduler};
+use stockrs::prices::feed::QuoteFeed;
+use stockrs::{mail, market};
 use tower::ServiceBuilder;
 use tower_http::{classify::ServerErrorsFailureClass, trace::TraceLayer};
 use tracing::{info_span, Span};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+/// Stock and portfolio tracking API.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Serve the API, refusing to while migrations are pending.
+    Serve {
+        /// Apply pending migrations first.
+        #[arg(long)]
+        auto_migrate: bool,
+    },
+    /// Manage the database schema.
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+    /// Add the data of a fixture, skipping what's already there.
+    Seed {
+        /// e.g. `fixtures/demo.toml`.
+        #[arg(long)]
+        fixture: PathBuf,
+    },
+    /// Inspect the database.
+    #[command(subcommand)]
+    Db(DbCommand),
+}
+
+#[derive(Subcommand)]
+enum MigrateCommand {
+    /// Apply the pending migrations.
+    Up,
+    /// Revert the last applied migrations.
+    Down {
+        #[arg(long, default_value_t = 1)]
+        steps: usize,
+    },
+    /// List the migrations and whether they're applied.
+    Status,
+}
+
+#[derive(Subcommand)]
+enum DbCommand {
+    /// Check that the database is reachable and its schema up to date.
+    Check,
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
     dotenvy::dotenv().ok();
+    let config = Config::load()?;
 
     // tracing-subscriber is logger crate to define logger impl `Subscriber` from `tracing`.
     tracing_subscriber::registry()
         .with(tracing_subscriber::fmt::layer())
-        .with(
-            // RUST_LOG=stockrs=debug,tower_http=debug
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                // axum logs rejections from built-in extractors with `axum::rejection` target
-                // at `TRACE` level. `axum::rejection=trace` enables showing all events.
-                .unwrap_or_else(|_| "stockrs=debug,tower_http=debug,axum::rejection=trace".into()),
-        )
+        .with(tracing_subscriber::EnvFilter::try_new(&config.log.filter)?)
         .init();
 
-    if !std::path::Path::new("db").exists() {
-        std::fs::create_dir("db")?;
+    if let Some(dir) = config.database.sqlite_file().and_then(Path::parent) {
+        if !dir.as_os_str().is_empty() && !dir.exists() {
+            std::fs::create_dir_all(dir)?;
+        }
     }
-    let url = std::env::var("DATABASE_URL")?;
-    let pool = get_database_pool(&url).await?;
+    let db = Database::connect(&config.database.url).await?;
+
+    let result = match cli.command {
+        Command::Serve { auto_migrate } => serve(config, &db, auto_migrate).await,
+        Command::Migrate(MigrateCommand::Up) => migrate_up(&db).await,
+        Command::Migrate(MigrateCommand::Down { steps }) => migrate_down(&db, steps).await,
+        Command::Migrate(MigrateCommand::Status) => migrate_status(&db).await,
+        Command::Seed { fixture } => seed(&db, &fixture).await,
+        Command::Db(DbCommand::Check) => db_check(&db).await,
+    };
+    db.close().await;
+    result
+}
+
+async fn migrate_up(db: &Database) -> anyhow::Result<()> {
+    let applied = db.migrate_up().await?;
+    if applied.is_empty() {
+        println!("no pending migrations");
+    }
+    for migration in applied {
+        println!("applied {} {}", migration.version, migration.description);
+    }
+    Ok(())
+}
+
+async fn migrate_down(db: &Database, steps: usize) -> anyhow::Result<()> {
+    let reverted = db.migrate_down(steps).await?;
+    if reverted.is_empty() {
+        println!("no applied migrations");
+    }
+    for migration in reverted {
+        println!("reverted {} {}", migration.version, migration.description);
+    }
+    Ok(())
+}
+
+async fn migrate_status(db: &Database) -> anyhow::Result<()> {
+    for migration in db.migrations().await? {
+        println!(
+            "{:<14} {:<8} {}",
+            migration.version, migration.state, migration.description
+        );
+    }
+    Ok(())
+}
+
+async fn seed(db: &Database, fixture: &Path) -> anyhow::Result<()> {
+    let fixture = Fixture::load(fixture)?;
+    if !db.pending_migrations().await?.is_empty() {
+        anyhow::bail!("migrations are pending, run `migrate up` first");
+    }
+    println!("{}", fixture.seed(&db.store()).await?);
+    Ok(())
+}
+
+async fn db_check(db: &Database) -> anyhow::Result<()> {
+    db.ping().await.context("database unreachable")?;
+    let out_of_date = db.out_of_date_migrations().await?;
+    for migration in &out_of_date {
+        println!(
+            "{:<14} {:<8} {}",
+            migration.version, migration.state, migration.description
+        );
+    }
+    if !out_of_date.is_empty() {
+        anyhow::bail!("the schema isn't up to date with this build");
+    }
+    println!(
+        "ok: {:?} database reachable, all {} migrations applied",
+        db.backend(),
+        db.migrations().await?.len()
+    );
+    Ok(())
+}
+
+async fn serve(config: Config, db: &Database, auto_migrate: bool) -> anyhow::Result<()> {
+    tracing::info!("effective config:\n{}", config.redacted());
+
+    // Pending migrations can be applied, edited ones or ones of a newer build need a look first.
+    let out_of_date = db.out_of_date_migrations().await?;
+    if let Some(migration) = out_of_date
+        .iter()
+        .find(|migration| migration.state != MigrationState::Pending)
+    {
+        anyhow::bail!(
+            "migration {} is {}, the schema doesn't match this build, see `db check`",
+            migration.version,
+            migration.state
+        );
+    }
+    if !out_of_date.is_empty() {
+        if !auto_migrate {
+            anyhow::bail!(
+                "{} migrations are pending, run `migrate up` or serve with --auto-migrate",
+                out_of_date.len()
+            );
+        }
+        for migration in db.migrate_up().await? {
+            tracing::info!(
+                "applied migration {} {}",
+                migration.version,
+                migration.description
+            );
+        }
+    }
+
+    let mut state = AppState::new(db.store());
+    match &config.auth.jwt_secret {
+        Some(secret) => state.jwt = JwtKeys::new(secret.expose().as_bytes()),
+        None => tracing::warn!(
+            "auth.jwt_secret not set, access tokens and emailed links won't survive a restart"
+        ),
+    }
+    state.mailer = mail::mailer_from_config(&config.mail)?;
+    state.public_url = config.server.public_url.clone();
+    let state = Arc::new(state);
+
+    // The feed only holds weak references, `alerts` keeps the engine alive while serving.
+    let alerts = AlertEngine::new(state.store.alerts.clone());
+    let mut feed = QuoteFeed::default();
+    feed.attach(alerts.clone());
+    let feed = tokio::spawn(feed.run(state.quotes.subscribe(), state.shutdown.clone()));
+
+    let provider = market::provider_from_config(&config.market_data)?;
+    let quotes = provider
+        .as_ref()
+        .map(|provider| market::spawn_quotes(provider.clone(), state.clone()));
+
+    let shutdown = state.shutdown.clone();
+    let mut scheduler = Scheduler::new(state.clone(), shutdown.clone());
+    if let Some(provider) = provider {
+        // Weekdays after the US close.
+        scheduler.add(
+            "30 22 * * 1-5".parse()?,
+            EodIngest::new(provider),
+            Retry::default(),
+        );
+    }
+    scheduler.add(
+        "*/5 * * * *".parse()?,
+        AlertSweep::new(alerts.clone()),
+        Retry::default(),
+    );
+    scheduler.add(
+        "@daily".parse()?,
+        PruneJobRuns::new(chrono::Duration::days(30)),
+        Retry::default(),
+    );
+    scheduler.add("@hourly".parse()?, PruneSessions, Retry::default());
+    let jobs = scheduler.spawn();
 
     let app = Router::new()
         .route("/", get(root_handler))
         .route("/health", get(healthcheck_handler))
+        .merge(api_router(state))
+        .fallback(|| async { Error::NotFound })
+        .method_not_allowed_fallback(|| async { Error::MethodNotAllowed })
         // NOTE: Extension (layer) is not type safe, while used by handlers, missing to add
         // .layer() still compiles!
         // .layer(Extension(AppState { state: 42 }))
         .layer(
             ServiceBuilder::new()
                 .layer(HandleErrorLayer::new(|error: BoxError| async move {
-                    if error.is::<tower::timeout::error::Elapsed>() {
-                        Ok(StatusCode::REQUEST_TIMEOUT)
-                    } else {
-                        Err((
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            format!("Unhandled internal error: {}", error),
-                        ))
-                    }
+                    Error::from_middleware(error)
                 }))
-                .timeout(Duration::from_secs(10))
+                .timeout(config.server.request_timeout())
                 .layer(TraceLayer::new_for_http())
                 .into_inner(),
         )
@@ -103,14 +302,73 @@ async fn main() -> anyhow::Result<()> {
                 ),
         );
 
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await?;
+    let listener = tokio::net::TcpListener::bind(config.server.bind).await?;
     tracing::debug!("server up and listening on {}", listener.local_addr()?);
 
-    axum::serve(listener, app).await?;
+    // On a signal the listener stops accepting and in-flight requests get until the deadline to
+    // finish. Cancelling `shutdown` right away ends quote streams, which would otherwise keep
+    // their connections open forever, and stops background tasks.
+    let server = axum::serve(listener, app).with_graceful_shutdown({
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown_signal().await;
+            tracing::info!("shutting down, draining connections");
+            shutdown.cancel();
+        }
+    });
+    let drain_timeout = config.server.shutdown_timeout();
+    tokio::select! {
+        served = server.into_future() => served?,
+        _ = async {
+            shutdown.cancelled().await;
+            tokio::time::sleep(drain_timeout).await;
+        } => tracing::warn!("connections still open after {drain_timeout:?}, dropping them"),
+    }
+
+    // Jobs stop between attempts, or mid-attempt for those watching the token.
+    if tokio::time::timeout(drain_timeout, jobs).await.is_err() {
+        tracing::warn!("background jobs still running after {drain_timeout:?}, abandoning them");
+    }
+    // The quote feed ends on the cancelled token, unless stuck waiting on the provider.
+    if let Some(mut quotes) = quotes {
+        if tokio::time::timeout(drain_timeout, &mut quotes)
+            .await
+            .is_err()
+        {
+            tracing::warn!("quote feed still running after {drain_timeout:?}, aborting it");
+            quotes.abort();
+        }
+    }
+    feed.abort();
+    tracing::info!("shut down");
 
     Ok(())
 }
 
+/// Resolve on Ctrl+C, or SIGTERM as sent by container runtimes and service managers.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
 async fn root_handler() -> String {
     "hello world".to_string()
 }
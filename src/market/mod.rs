@@ -0,0 +1,118 @@
+mod replay;
+mod simulator;
+
+pub use replay::ReplayProvider;
+pub use simulator::Simulator;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::async_trait;
+use chrono::NaiveDate;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use crate::app::AppState;
+use crate::config::{MarketDataConfig, MarketDataSource};
+use crate::prices::model::{PriceBar, Quote};
+
+/// A source of market data, e.g. a vendor API, recorded files or a simulation.
+#[async_trait]
+pub trait MarketDataProvider: Send + Sync {
+    /// Live quotes for `symbols`, ending when the source is exhausted.
+    fn quotes(&self, symbols: &[String]) -> BoxStream<'static, Quote>;
+
+    /// Daily bars of `symbol` between `from` and `to` inclusive, oldest first.
+    async fn bars(
+        &self,
+        symbol: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> anyhow::Result<Vec<PriceBar>>;
+
+    /// Splits and dividends of `symbol` between `from` and `to` inclusive, oldest first.
+    async fn corporate_actions(
+        &self,
+        symbol: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> anyhow::Result<Vec<CorporateAction>>;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorporateAction {
+    pub symbol: String,
+    /// The ex-date.
+    pub date: NaiveDate,
+    #[serde(flatten)]
+    pub kind: CorporateActionKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum CorporateActionKind {
+    /// `ratio` new shares per old share.
+    Split { ratio: f64 },
+    /// Cash `amount` paid per share.
+    Dividend { amount: f64 },
+}
+
+/// Pick the configured provider, `None` leaving the app without live quotes.
+pub fn provider_from_config(
+    config: &MarketDataConfig,
+) -> anyhow::Result<Option<Arc<dyn MarketDataProvider>>> {
+    let provider: Arc<dyn MarketDataProvider> = match config.provider {
+        MarketDataSource::None => return Ok(None),
+        MarketDataSource::Replay => {
+            let path = config
+                .file
+                .as_ref()
+                .context("market_data.file must be set to replay")?;
+            Arc::new(ReplayProvider::open(path, config.speed)?)
+        }
+        MarketDataSource::Simulate => Arc::new(Simulator::new(config.seed, Duration::from_secs(1))),
+    };
+
+    Ok(Some(provider))
+}
+
+/// Publish the provider's quotes for every listed stock on the app's `QuoteBus`, until shutdown.
+///
+/// The task logs why it stopped early, if it did, as nothing else is waiting on it while serving.
+pub fn spawn_quotes(provider: Arc<dyn MarketDataProvider>, state: Arc<AppState>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(error) = stream_quotes(provider, &state).await {
+            tracing::error!("quote feed stopped: {error:#}");
+        }
+    })
+}
+
+async fn stream_quotes(
+    provider: Arc<dyn MarketDataProvider>,
+    state: &AppState,
+) -> anyhow::Result<()> {
+    let symbols = state
+        .store
+        .stocks
+        .listed_symbols()
+        .await
+        .context("listing the symbols to stream")?;
+    tracing::debug!("streaming quotes for {} symbols", symbols.len());
+
+    let mut quotes = provider.quotes(&symbols);
+    loop {
+        let quote = tokio::select! {
+            _ = state.shutdown.cancelled() => return Ok(()),
+            quote = quotes.next() => quote,
+        };
+        let Some(quote) = quote else { break };
+        state.quotes.publish(quote);
+    }
+
+    tracing::debug!("market data provider exhausted");
+    Ok(())
+}
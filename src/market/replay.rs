@@ -0,0 +1,354 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use axum::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::stream::BoxStream;
+use futures::StreamExt;
+
+use super::{CorporateAction, CorporateActionKind, MarketDataProvider};
+use crate::prices::model::{PriceBar, Quote};
+use crate::stocks::normalize_code;
+
+/// Replays market data recorded to a file.
+///
+/// `.csv` files hold quotes only, with an `at,symbol,price` header and optional `open` and
+/// `previous_close` columns. Any other file is read as JSON lines tagged by `type`:
+///
+/// ```text
+/// {"type": "quote", "symbol": "AAPL", "price": 201.5, "at": "2024-01-02T15:00:00Z"}
+/// {"type": "bar", "symbol": "AAPL", "date": "2024-01-02", "open": 198, "high": 202, ...}
+/// {"type": "split", "symbol": "AAPL", "date": "2024-01-03", "ratio": 4}
+/// {"type": "dividend", "symbol": "AAPL", "date": "2024-01-04", "amount": 0.24}
+/// ```
+///
+/// A quote without `open` takes the first price of its symbol and session. Symbols without
+/// recorded bars get daily bars aggregated from their quotes.
+pub struct ReplayProvider {
+    quotes: Vec<Quote>,
+    bars: BTreeMap<String, Vec<PriceBar>>,
+    actions: Vec<CorporateAction>,
+    speed: f64,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Record {
+    Quote(RecordedQuote),
+    Bar(PriceBar),
+    Split {
+        symbol: String,
+        date: NaiveDate,
+        ratio: f64,
+    },
+    Dividend {
+        symbol: String,
+        date: NaiveDate,
+        amount: f64,
+    },
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordedQuote {
+    symbol: String,
+    price: f64,
+    open: Option<f64>,
+    previous_close: Option<f64>,
+    at: DateTime<Utc>,
+}
+
+impl ReplayProvider {
+    /// Load a recording, to be replayed `speed` times faster than it was recorded.
+    ///
+    /// An infinite speed replays without any delay.
+    pub fn open(path: impl AsRef<Path>, speed: f64) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        if speed.is_nan() || speed <= 0.0 {
+            bail!("replay speed must be positive, got {speed}");
+        }
+
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading market data from {}", path.display()))?;
+        let records = if path.extension().is_some_and(|ext| ext == "csv") {
+            parse_csv(&text)
+        } else {
+            parse_json_lines(&text)
+        }
+        .with_context(|| format!("parsing market data from {}", path.display()))?;
+
+        Ok(Self::from_records(records, speed))
+    }
+
+    fn from_records(records: Vec<Record>, speed: f64) -> Self {
+        let mut recorded = Vec::new();
+        let mut bars: BTreeMap<String, Vec<PriceBar>> = BTreeMap::new();
+        let mut actions = Vec::new();
+
+        for record in records {
+            match record {
+                Record::Quote(quote) => recorded.push(quote),
+                Record::Bar(bar) => bars
+                    .entry(normalize_code(&bar.symbol))
+                    .or_default()
+                    .push(bar),
+                Record::Split {
+                    symbol,
+                    date,
+                    ratio,
+                } => actions.push(CorporateAction {
+                    symbol: normalize_code(&symbol),
+                    date,
+                    kind: CorporateActionKind::Split { ratio },
+                }),
+                Record::Dividend {
+                    symbol,
+                    date,
+                    amount,
+                } => actions.push(CorporateAction {
+                    symbol: normalize_code(&symbol),
+                    date,
+                    kind: CorporateActionKind::Dividend { amount },
+                }),
+            }
+        }
+
+        recorded.sort_by_key(|quote| quote.at);
+        let mut opens: HashMap<(String, NaiveDate), f64> = HashMap::new();
+        let quotes = recorded
+            .into_iter()
+            .map(|quote| {
+                let symbol = normalize_code(&quote.symbol);
+                let open = *opens
+                    .entry((symbol.clone(), quote.at.date_naive()))
+                    .or_insert(quote.open.unwrap_or(quote.price));
+                Quote {
+                    symbol,
+                    price: quote.price,
+                    open: quote.open.unwrap_or(open),
+                    previous_close: quote.previous_close,
+                    at: quote.at,
+                }
+            })
+            .collect();
+
+        for bars in bars.values_mut() {
+            bars.sort_by_key(|bar| bar.date);
+        }
+        actions.sort_by_key(|action| action.date);
+
+        Self {
+            quotes,
+            bars,
+            actions,
+            speed,
+        }
+    }
+}
+
+#[async_trait]
+impl MarketDataProvider for ReplayProvider {
+    fn quotes(&self, symbols: &[String]) -> BoxStream<'static, Quote> {
+        let symbols: HashSet<&str> = symbols.iter().map(String::as_str).collect();
+        let quotes: Vec<Quote> = self
+            .quotes
+            .iter()
+            .filter(|quote| symbols.contains(quote.symbol.as_str()))
+            .cloned()
+            .collect();
+        let speed = self.speed;
+
+        futures::stream::unfold(
+            (quotes.into_iter(), None::<DateTime<Utc>>),
+            move |(mut quotes, last)| async move {
+                let quote = quotes.next()?;
+                let gap = last.and_then(|last| (quote.at - last).to_std().ok());
+                if let Some(gap) = gap.filter(|_| speed.is_finite()) {
+                    tokio::time::sleep(gap.div_f64(speed)).await;
+                }
+                let at = quote.at;
+                Some((quote, (quotes, Some(at))))
+            },
+        )
+        .boxed()
+    }
+
+    async fn bars(
+        &self,
+        symbol: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> anyhow::Result<Vec<PriceBar>> {
+        let in_range = |date: NaiveDate| from <= date && date <= to;
+
+        if let Some(bars) = self.bars.get(symbol) {
+            return Ok(bars
+                .iter()
+                .filter(|bar| in_range(bar.date))
+                .cloned()
+                .collect());
+        }
+
+        let mut bars: Vec<PriceBar> = Vec::new();
+        for quote in &self.quotes {
+            if quote.symbol != symbol || !in_range(quote.session()) {
+                continue;
+            }
+            match bars.last_mut() {
+                Some(bar) if bar.date == quote.session() => {
+                    bar.high = bar.high.max(quote.price);
+                    bar.low = bar.low.min(quote.price);
+                    bar.close = quote.price;
+                    bar.adj_close = quote.price;
+                }
+                _ => bars.push(PriceBar {
+                    symbol: symbol.to_string(),
+                    date: quote.session(),
+                    open: quote.open,
+                    high: quote.price.max(quote.open),
+                    low: quote.price.min(quote.open),
+                    close: quote.price,
+                    adj_close: quote.price,
+                    volume: 0,
+                }),
+            }
+        }
+
+        Ok(bars)
+    }
+
+    async fn corporate_actions(
+        &self,
+        symbol: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> anyhow::Result<Vec<CorporateAction>> {
+        Ok(self
+            .actions
+            .iter()
+            .filter(|action| action.symbol == symbol && from <= action.date && action.date <= to)
+            .cloned()
+            .collect())
+    }
+}
+
+fn parse_json_lines(text: &str) -> anyhow::Result<Vec<Record>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| serde_json::from_str(line).with_context(|| format!("line {}", idx + 1)))
+        .collect()
+}
+
+fn parse_csv(text: &str) -> anyhow::Result<Vec<Record>> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let Some((_, header)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+
+    let columns: Vec<String> = header
+        .split(',')
+        .map(|column| column.trim().to_lowercase())
+        .collect();
+    let position = |name: &str| columns.iter().position(|column| column == name);
+    let (Some(at), Some(symbol), Some(price)) =
+        (position("at"), position("symbol"), position("price"))
+    else {
+        bail!("header must have `at`, `symbol` and `price` columns");
+    };
+    let open = position("open");
+    let previous_close = position("previous_close");
+
+    lines
+        .map(|(idx, line)| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let field = |column: usize| fields.get(column).copied().unwrap_or_default();
+            let number = |column: Option<usize>| -> anyhow::Result<Option<f64>> {
+                match column.map(field).filter(|value| !value.is_empty()) {
+                    Some(value) => Ok(Some(value.parse()?)),
+                    None => Ok(None),
+                }
+            };
+
+            let quote = (|| -> anyhow::Result<RecordedQuote> {
+                Ok(RecordedQuote {
+                    symbol: field(symbol).to_string(),
+                    price: number(Some(price))?.context("missing price")?,
+                    open: number(open)?,
+                    previous_close: number(previous_close)?,
+                    at: field(at).parse()?,
+                })
+            })()
+            .with_context(|| format!("line {}", idx + 1))?;
+
+            Ok(Record::Quote(quote))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording(name: &str, contents: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("stockrs-{}-{name}", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn replays_csv_quotes() {
+        let path = recording(
+            "quotes.csv",
+            "at,symbol,price\n\
+             2024-01-02T15:00:01Z,msft,370\n\
+             2024-01-02T15:00:00Z,aapl,190\n\
+             2024-01-02T15:00:02Z,aapl,192\n\
+             2024-01-03T15:00:00Z,aapl,189\n",
+        );
+        let provider = ReplayProvider::open(&path, f64::INFINITY).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let quotes: Vec<Quote> = provider.quotes(&["AAPL".to_string()]).collect().await;
+        let prices: Vec<(f64, f64)> = quotes.iter().map(|q| (q.price, q.open)).collect();
+        assert_eq!(prices, [(190.0, 190.0), (192.0, 190.0), (189.0, 189.0)]);
+
+        let from = "2024-01-01".parse().unwrap();
+        let to = "2024-01-31".parse().unwrap();
+        let bars = provider.bars("AAPL", from, to).await.unwrap();
+        assert_eq!(bars.len(), 2);
+        assert_eq!(
+            (bars[0].open, bars[0].high, bars[0].close),
+            (190.0, 192.0, 192.0)
+        );
+    }
+
+    #[tokio::test]
+    async fn replays_json_lines() {
+        let path = recording(
+            "market.jsonl",
+            r#"{"type": "bar", "symbol": "AAPL", "date": "2024-01-02", "open": 1, "high": 2, "low": 1, "close": 2, "adjClose": 2, "volume": 10}
+               {"type": "split", "symbol": "AAPL", "date": "2024-01-03", "ratio": 4}
+
+               {"type": "quote", "symbol": "AAPL", "price": 0.5, "open": 0.4, "at": "2024-01-03T15:00:00Z"}"#,
+        );
+        let provider = ReplayProvider::open(&path, 1.0).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let from = "2024-01-01".parse().unwrap();
+        let to = "2024-01-31".parse().unwrap();
+        assert_eq!(provider.bars("AAPL", from, to).await.unwrap().len(), 1);
+        assert_eq!(
+            provider.corporate_actions("AAPL", from, to).await.unwrap()[0].kind,
+            CorporateActionKind::Split { ratio: 4.0 }
+        );
+        let quotes: Vec<Quote> = provider.quotes(&["AAPL".to_string()]).collect().await;
+        assert_eq!(quotes[0].open, 0.4);
+
+        assert!(ReplayProvider::open("/nonexistent.jsonl", 1.0).is_err());
+    }
+}
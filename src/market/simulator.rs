@@ -0,0 +1,226 @@
+use std::time::Duration;
+
+use axum::async_trait;
+use chrono::{Datelike, NaiveDate, Utc, Weekday};
+use futures::stream::BoxStream;
+use futures::StreamExt;
+
+use super::{CorporateAction, MarketDataProvider};
+use crate::prices::model::{PriceBar, Quote};
+
+/// Daily volatility of the simulated prices, roughly that of a large cap stock.
+const DAILY_VOLATILITY: f64 = 0.02;
+
+/// Ticks are much smaller moves than days.
+const TICK_VOLATILITY: f64 = 0.001;
+
+/// Bars are walked from this Monday, so a day's bar doesn't depend on the range asked for.
+const EPOCH: NaiveDate = match NaiveDate::from_ymd_opt(2000, 1, 3) {
+    Some(date) => date,
+    None => panic!("invalid epoch"),
+};
+
+/// Deterministic geometric random walk of every symbol, for running the app offline.
+///
+/// The same seed always produces the same bars, and the same sequence of quote prices from the
+/// simulated open of the current day; only quote timestamps follow the wall clock.
+pub struct Simulator {
+    seed: u64,
+    tick: Duration,
+}
+
+impl Simulator {
+    pub fn new(seed: u64, tick: Duration) -> Self {
+        Self { seed, tick }
+    }
+
+    /// Daily bars of `symbol` on weekdays from the epoch to `to` inclusive.
+    fn walk_days(&self, symbol: &str, to: NaiveDate) -> Vec<PriceBar> {
+        let mut rng = SplitMix64::new(self.seed ^ fnv1a(symbol));
+        // Start somewhere between 20 and 500.
+        let mut close = 20.0 + rng.next_f64() * 480.0;
+
+        EPOCH
+            .iter_days()
+            .take_while(|date| *date <= to)
+            .filter(|date| !matches!(date.weekday(), Weekday::Sat | Weekday::Sun))
+            .map(|date| {
+                let open = close;
+                close = open * (DAILY_VOLATILITY * rng.next_gaussian()).exp();
+                let wick = 1.0 + DAILY_VOLATILITY * rng.next_f64() / 2.0;
+                PriceBar {
+                    symbol: symbol.to_string(),
+                    date,
+                    open,
+                    high: open.max(close) * wick,
+                    low: open.min(close) / wick,
+                    close,
+                    adj_close: close,
+                    volume: (1e6 * (1.0 + rng.next_f64())) as i64,
+                }
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl MarketDataProvider for Simulator {
+    fn quotes(&self, symbols: &[String]) -> BoxStream<'static, Quote> {
+        let today = Utc::now().date_naive();
+        let walks: Vec<(Quote, SplitMix64)> = symbols
+            .iter()
+            .map(|symbol| {
+                let days = self.walk_days(symbol, today);
+                let mut last = days.iter().rev();
+                let (open, previous_close) = match (last.next(), last.next()) {
+                    // Quotes on a weekend carry on from Friday's open.
+                    (Some(bar), previous) => (bar.open, previous.map(|bar| bar.close)),
+                    (None, _) => (100.0, None),
+                };
+                let quote = Quote {
+                    symbol: symbol.clone(),
+                    price: open,
+                    open,
+                    previous_close,
+                    at: Utc::now(),
+                };
+                let rng =
+                    SplitMix64::new(self.seed ^ fnv1a(symbol) ^ today.num_days_from_ce() as u64);
+                (quote, rng)
+            })
+            .collect();
+        let tick = self.tick;
+
+        futures::stream::unfold(walks, move |mut walks| async move {
+            if walks.is_empty() {
+                return None;
+            }
+            tokio::time::sleep(tick).await;
+
+            let quotes: Vec<Quote> = walks
+                .iter_mut()
+                .map(|(quote, rng)| {
+                    quote.price *= (TICK_VOLATILITY * rng.next_gaussian()).exp();
+                    quote.at = Utc::now();
+                    quote.clone()
+                })
+                .collect();
+            Some((futures::stream::iter(quotes), walks))
+        })
+        .flatten()
+        .boxed()
+    }
+
+    async fn bars(
+        &self,
+        symbol: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> anyhow::Result<Vec<PriceBar>> {
+        let mut bars = self.walk_days(symbol, to);
+        bars.retain(|bar| bar.date >= from);
+        Ok(bars)
+    }
+
+    async fn corporate_actions(
+        &self,
+        _symbol: &str,
+        _from: NaiveDate,
+        _to: NaiveDate,
+    ) -> anyhow::Result<Vec<CorporateAction>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Small, fast and well distributed PRNG, so simulations don't need a `rand` dependency and
+/// stay reproducible across its versions.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Standard normal, by the Box-Muller transform.
+    fn next_gaussian(&mut self) -> f64 {
+        let u1 = 1.0 - self.next_f64();
+        let u2 = self.next_f64();
+        (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+    }
+}
+
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn bars_are_deterministic() {
+        let from = "2024-01-01".parse().unwrap();
+        let to = "2024-01-31".parse().unwrap();
+
+        let bars = Simulator::new(7, Duration::ZERO)
+            .bars("AAPL", from, to)
+            .await
+            .unwrap();
+        assert_eq!(bars.len(), 23);
+        assert!(bars
+            .iter()
+            .all(|bar| bar.low <= bar.open.min(bar.close) && bar.high >= bar.open.max(bar.close)));
+
+        let again = Simulator::new(7, Duration::ZERO);
+        assert_eq!(again.bars("AAPL", from, to).await.unwrap(), bars);
+        let week = again
+            .bars(
+                "AAPL",
+                "2024-01-08".parse().unwrap(),
+                "2024-01-12".parse().unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(week[..], bars[5..10]);
+
+        let other = Simulator::new(8, Duration::ZERO);
+        assert_ne!(other.bars("AAPL", from, to).await.unwrap(), bars);
+    }
+
+    #[tokio::test]
+    async fn quotes_walk_every_symbol() {
+        let symbols = ["AAPL".to_string(), "MSFT".to_string()];
+        let first: Vec<Quote> = Simulator::new(1, Duration::from_millis(1))
+            .quotes(&symbols)
+            .take(6)
+            .collect()
+            .await;
+        let second: Vec<Quote> = Simulator::new(1, Duration::from_millis(1))
+            .quotes(&symbols)
+            .take(6)
+            .collect()
+            .await;
+
+        let prices = |quotes: &[Quote]| -> Vec<(String, f64)> {
+            quotes.iter().map(|q| (q.symbol.clone(), q.price)).collect()
+        };
+        assert_eq!(prices(&first), prices(&second));
+        assert_eq!(first[4].symbol, "AAPL");
+        assert_ne!(first[0].price, first[2].price);
+    }
+}
@@ -0,0 +1,252 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::routing::get;
+use axum::Router;
+use chrono::{NaiveDate, Utc};
+
+use super::cost_basis::{cost_basis, Lot, LotMethod};
+use super::handlers::fetch_portfolio;
+use super::performance::{performance, Closes, DailyValue};
+use crate::app::AppState;
+use crate::auth::AuthUser;
+use crate::common::errors::{Error, Result};
+use crate::common::extract::{Json, Path, Query};
+
+/// The longest range a performance series may span. Every day of it is valued and returned, so
+/// a range of millennia would tie up the server and the response alike.
+const MAX_PERFORMANCE_YEARS: i64 = 50;
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/portfolios/:id/pnl", get(get_pnl))
+        .route("/api/portfolios/:id/performance", get(get_performance))
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct PnlQuery {
+    method: LotMethod,
+    /// Value the portfolio as it stood at the end of this day instead of today.
+    as_of: Option<NaiveDate>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PositionPnl {
+    symbol: String,
+    quantity: f64,
+    cost_basis: f64,
+    average_cost: Option<f64>,
+    /// `None` when no bar has been stored for the symbol yet, and so are the fields below.
+    market_price: Option<f64>,
+    market_value: Option<f64>,
+    unrealized_pnl: Option<f64>,
+    realized_pnl: f64,
+    dividends: f64,
+    lots: Vec<Lot>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PnlBody {
+    method: LotMethod,
+    positions: Vec<PositionPnl>,
+    realized_pnl: f64,
+    unrealized_pnl: f64,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(default)]
+struct PerformanceQuery {
+    /// Defaults to the first trade date of the portfolio.
+    from: Option<NaiveDate>,
+    /// Defaults to today.
+    to: Option<NaiveDate>,
+}
+
+#[derive(serde::Serialize)]
+struct PerformanceBody {
+    from: NaiveDate,
+    to: NaiveDate,
+    twr: Option<f64>,
+    xirr: Option<f64>,
+    series: Vec<DailyValue>,
+}
+
+// Closed positions are listed too so that their realized P&L shows up.
+async fn get_pnl(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(portfolio_id): Path<i64>,
+    Query(query): Query<PnlQuery>,
+) -> Result<Json<PnlBody>> {
+    let portfolio = fetch_portfolio(&state, auth_user, portfolio_id).await?;
+    let mut ledger = state
+        .store
+        .transactions
+        .ledger(portfolio.portfolio_id)
+        .await?;
+    if let Some(as_of) = query.as_of {
+        ledger.retain(|tx| tx.trade_date <= as_of);
+    }
+
+    // Booking replays the ledger with every new entry, so a stored one failing to is a bug.
+    let books = cost_basis(&ledger, query.method)
+        .map_err(|e| anyhow::anyhow!("ledger of portfolio {portfolio_id} is oversold: {e:?}"))?;
+
+    let mut positions = Vec::with_capacity(books.len());
+    for (symbol, book) in books {
+        let quantity = book.quantity();
+        let cost_basis = book.cost_basis();
+        let market_price = if book.lots.is_empty() {
+            None
+        } else {
+            state
+                .store
+                .prices
+                .latest_close(&symbol, query.as_of)
+                .await?
+        };
+        let market_value = market_price.map(|price| price * quantity);
+
+        positions.push(PositionPnl {
+            quantity,
+            cost_basis,
+            average_cost: book.average_cost(),
+            market_price,
+            market_value,
+            unrealized_pnl: market_value.map(|value| value - cost_basis),
+            realized_pnl: book.realized_pnl,
+            dividends: book.dividends,
+            lots: book.lots.into(),
+            symbol,
+        });
+    }
+
+    Ok(Json(PnlBody {
+        method: query.method,
+        realized_pnl: positions.iter().map(|p| p.realized_pnl).sum(),
+        unrealized_pnl: positions.iter().filter_map(|p| p.unrealized_pnl).sum(),
+        positions,
+    }))
+}
+
+async fn get_performance(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(portfolio_id): Path<i64>,
+    Query(query): Query<PerformanceQuery>,
+) -> Result<Json<PerformanceBody>> {
+    let portfolio = fetch_portfolio(&state, auth_user, portfolio_id).await?;
+    let ledger = state
+        .store
+        .transactions
+        .ledger(portfolio.portfolio_id)
+        .await?;
+
+    let to = query.to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = query
+        .from
+        .or_else(|| ledger.first().map(|tx| tx.trade_date))
+        .unwrap_or(to);
+    if from > to {
+        return Err(Error::unprocessable_entity([(
+            "from",
+            "must not be after `to`",
+        )]));
+    }
+    if (to - from).num_days() > MAX_PERFORMANCE_YEARS * 366 {
+        return Err(Error::unprocessable_entity([(
+            "from",
+            format!("must be within {MAX_PERFORMANCE_YEARS} years of `to`"),
+        )]));
+    }
+
+    let bars = state
+        .store
+        .prices
+        .portfolio_closes(portfolio.portfolio_id, to)
+        .await?;
+    let mut closes = Closes::new();
+    for (symbol, date, close) in bars {
+        closes.entry(symbol).or_default().insert(date, close);
+    }
+
+    let performance = performance(&ledger, &closes, from, to)
+        .map_err(|e| anyhow::anyhow!("ledger of portfolio {portfolio_id} is oversold: {e:?}"))?;
+
+    Ok(Json(PerformanceBody {
+        from,
+        to,
+        twr: performance.twr,
+        xirr: performance.xirr,
+        series: performance.series,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{test_session_cookie, Role};
+    use crate::conn::test_database;
+    use crate::testing::{insert_portfolio, insert_stock, insert_user, send};
+    use axum::http::StatusCode;
+
+    #[tokio::test]
+    async fn pnl_and_performance() {
+        let db = test_database().await;
+        insert_user(&db, 1, "alice", Role::User).await;
+        insert_stock(&db, "ACME", "XNYS", "Acme Corp").await;
+        insert_portfolio(&db, 1, 1, "ISA").await;
+        sqlx::query(
+            r#"INSERT INTO "transaction" (portfolio_id, kind, symbol, trade_date, quantity, price, fee, created_at)
+             VALUES (1, 'buy', 'ACME', '2024-01-02', 10, 10, 0, '2024-01-01T00:00:00Z'),
+                    (1, 'buy', 'ACME', '2024-01-03', 10, 20, 0, '2024-01-01T00:00:00Z'),
+                    (1, 'sell', 'ACME', '2024-01-04', 10, 25, 0, '2024-01-01T00:00:00Z');
+             INSERT INTO price_bar (symbol, date, open, high, low, close, adj_close, volume)
+             VALUES ('ACME', '2024-01-04', 25, 25, 25, 25, 25, 0),
+                    ('ACME', '2024-01-05', 30, 30, 30, 30, 30, 0);"#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        let alice = test_session_cookie(&db, 1).await;
+        let app = router().with_state(Arc::new(AppState::new(db)));
+
+        let get = |uri: &'static str| {
+            let app = app.clone();
+            let alice = alice.clone();
+            async move {
+                let (status, body) = send(&app, "GET", uri, &alice, None).await;
+                assert_eq!(status, StatusCode::OK);
+                body
+            }
+        };
+
+        let body = get("/api/portfolios/1/pnl").await;
+        assert_eq!(body["method"], "fifo");
+        assert_eq!(body["realizedPnl"], 150.0);
+        assert_eq!(body["positions"][0]["marketValue"], 300.0);
+        assert_eq!(body["unrealizedPnl"], 100.0);
+
+        let body = get("/api/portfolios/1/pnl?method=lifo&asOf=2024-01-04").await;
+        assert_eq!(body["realizedPnl"], 50.0);
+        assert_eq!(body["positions"][0]["averageCost"], 10.0);
+        assert_eq!(body["unrealizedPnl"], 150.0);
+
+        let body = get("/api/portfolios/1/pnl?method=average").await;
+        assert_eq!(body["realizedPnl"], 100.0);
+        assert_eq!(body["positions"][0]["lots"][0]["unitCost"], 15.0);
+
+        // No deposits, so the trades left cash at -50 against 10 shares at 30.
+        let body = get("/api/portfolios/1/performance?to=2024-01-05").await;
+        assert_eq!(body["from"], "2024-01-02");
+        assert_eq!(body["series"].as_array().unwrap().len(), 4);
+        assert_eq!(body["series"][3]["value"], 250.0);
+
+        let uri = "/api/portfolios/1/performance?from=0001-01-01&to=9999-12-31";
+        let (status, _) = send(&app, "GET", uri, &alice, None).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::ledger::{Oversold, EPSILON};
+use super::model::{Transaction, TransactionKind};
+
+/// How sells are matched against the open lots of a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LotMethod {
+    /// Oldest lots are sold first, as most tax authorities require.
+    #[default]
+    Fifo,
+    /// Newest lots are sold first.
+    Lifo,
+    /// All lots are pooled at their weighted average cost.
+    Average,
+}
+
+/// Shares bought together, with the per-share cost including the allocated buy fee.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Lot {
+    pub trade_date: NaiveDate,
+    pub quantity: f64,
+    pub unit_cost: f64,
+}
+
+/// Cost basis and P&L of one symbol after replaying the ledger.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PositionCost {
+    /// Open lots, oldest first. With `LotMethod::Average` there is at most one pooled lot.
+    ///
+    /// Sells take from either end, which is the same push/shift/pop shape as the guide's
+    /// doubly linked `List`; `VecDeque` does it without `Rc` so the books stay `Send`, and
+    /// lets splits rescale every lot in place.
+    pub lots: VecDeque<Lot>,
+    pub realized_pnl: f64,
+    pub dividends: f64,
+}
+
+impl PositionCost {
+    pub fn quantity(&self) -> f64 {
+        self.lots.iter().map(|lot| lot.quantity).sum()
+    }
+
+    pub fn cost_basis(&self) -> f64 {
+        self.lots
+            .iter()
+            .map(|lot| lot.quantity * lot.unit_cost)
+            .sum()
+    }
+
+    /// Average cost per share held, `None` when the position is closed.
+    pub fn average_cost(&self) -> Option<f64> {
+        let quantity = self.quantity();
+        (quantity > EPSILON).then(|| self.cost_basis() / quantity)
+    }
+
+    fn buy(&mut self, lot: Lot, method: LotMethod) {
+        match (method, self.lots.front_mut()) {
+            (LotMethod::Average, Some(pool)) => {
+                let quantity = pool.quantity + lot.quantity;
+                pool.unit_cost =
+                    (pool.quantity * pool.unit_cost + lot.quantity * lot.unit_cost) / quantity;
+                pool.quantity = quantity;
+            }
+            _ => self.lots.push_back(lot),
+        }
+    }
+
+    /// Match `quantity` shares against the open lots, returning their cost.
+    fn sell(&mut self, mut quantity: f64, method: LotMethod) -> f64 {
+        let mut cost = 0.0;
+
+        while quantity > EPSILON {
+            let lot = match method {
+                LotMethod::Fifo | LotMethod::Average => self.lots.front_mut(),
+                LotMethod::Lifo => self.lots.back_mut(),
+            };
+            let Some(lot) = lot else { break };
+
+            let matched = quantity.min(lot.quantity);
+            cost += matched * lot.unit_cost;
+            lot.quantity -= matched;
+            quantity -= matched;
+
+            if lot.quantity <= EPSILON {
+                match method {
+                    LotMethod::Fifo | LotMethod::Average => self.lots.pop_front(),
+                    LotMethod::Lifo => self.lots.pop_back(),
+                };
+            }
+        }
+
+        cost
+    }
+
+    fn split(&mut self, ratio: f64) {
+        for lot in &mut self.lots {
+            lot.quantity *= ratio;
+            lot.unit_cost /= ratio;
+        }
+    }
+}
+
+/// Replay a ledger, ordered by trade date then booking order, into per-symbol cost books.
+pub fn cost_basis<'a>(
+    ledger: impl IntoIterator<Item = &'a Transaction>,
+    method: LotMethod,
+) -> Result<BTreeMap<String, PositionCost>, Oversold> {
+    let mut books: BTreeMap<String, PositionCost> = BTreeMap::new();
+
+    for tx in ledger {
+        let Some(symbol) = &tx.symbol else { continue };
+        let quantity = tx.quantity.unwrap_or_default();
+        let price = tx.price.unwrap_or_default();
+
+        match tx.kind {
+            TransactionKind::Buy => {
+                let lot = Lot {
+                    trade_date: tx.trade_date,
+                    quantity,
+                    unit_cost: price + tx.fee / quantity,
+                };
+                books.entry(symbol.clone()).or_default().buy(lot, method);
+            }
+            TransactionKind::Sell => {
+                let book = books.entry(symbol.clone()).or_default();
+                let held = book.quantity();
+                if quantity > held + EPSILON {
+                    return Err(Oversold {
+                        symbol: symbol.clone(),
+                        trade_date: tx.trade_date,
+                        held,
+                    });
+                }
+
+                let cost = book.sell(quantity, method);
+                book.realized_pnl += quantity * price - tx.fee - cost;
+            }
+            TransactionKind::Split => {
+                if let Some(book) = books.get_mut(symbol) {
+                    book.split(tx.split_ratio.unwrap_or(1.0));
+                }
+            }
+            TransactionKind::Dividend => {
+                books.entry(symbol.clone()).or_default().dividends += tx.amount.unwrap_or_default();
+            }
+            TransactionKind::Fee | TransactionKind::Deposit | TransactionKind::Withdrawal => {}
+        }
+    }
+
+    Ok(books)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portfolios::ledger::tests::{trade, tx};
+
+    fn ledger() -> Vec<Transaction> {
+        use TransactionKind::*;
+
+        vec![
+            trade(Buy, "2024-01-01", "ACME", 10.0, 10.0),
+            Transaction {
+                fee: 20.0,
+                ..trade(Buy, "2024-02-01", "ACME", 10.0, 20.0)
+            },
+            // 2-for-1: 40 shares, lots now at 5.0 and 11.0 per share.
+            Transaction {
+                symbol: Some("ACME".to_string()),
+                split_ratio: Some(2.0),
+                ..tx(Split, "2024-03-01")
+            },
+            trade(Sell, "2024-04-01", "ACME", 30.0, 15.0),
+        ]
+    }
+
+    #[test]
+    fn fifo_sells_oldest_lots_first() {
+        let books = cost_basis(&ledger(), LotMethod::Fifo).unwrap();
+        let acme = &books["ACME"];
+
+        // 20 @ 5.0 then 10 @ 11.0 sold for 450.
+        assert_eq!(acme.realized_pnl, 450.0 - 100.0 - 110.0);
+        assert_eq!(acme.quantity(), 10.0);
+        assert_eq!(acme.average_cost(), Some(11.0));
+        assert_eq!(acme.lots[0].trade_date.to_string(), "2024-02-01");
+    }
+
+    #[test]
+    fn lifo_sells_newest_lots_first() {
+        let books = cost_basis(&ledger(), LotMethod::Lifo).unwrap();
+        let acme = &books["ACME"];
+
+        // 20 @ 11.0 then 10 @ 5.0 sold for 450.
+        assert_eq!(acme.realized_pnl, 450.0 - 220.0 - 50.0);
+        assert_eq!(acme.quantity(), 10.0);
+        assert_eq!(acme.average_cost(), Some(5.0));
+    }
+
+    #[test]
+    fn average_pools_lots() {
+        let books = cost_basis(&ledger(), LotMethod::Average).unwrap();
+        let acme = &books["ACME"];
+
+        // 40 shares pooled at 8.0 after the split.
+        assert_eq!(acme.lots.len(), 1);
+        assert_eq!(acme.realized_pnl, 450.0 - 240.0);
+        assert_eq!(acme.cost_basis(), 80.0);
+    }
+
+    #[test]
+    fn closed_positions_keep_realized_pnl() {
+        let mut ledger = ledger();
+        ledger.push(trade(
+            TransactionKind::Sell,
+            "2024-05-01",
+            "ACME",
+            10.0,
+            12.0,
+        ));
+
+        let books = cost_basis(&ledger, LotMethod::Fifo).unwrap();
+        assert!(books["ACME"].lots.is_empty());
+        assert_eq!(books["ACME"].average_cost(), None);
+        assert_eq!(books["ACME"].realized_pnl, 240.0 + 120.0 - 110.0);
+    }
+}
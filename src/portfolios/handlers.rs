@@ -0,0 +1,219 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::Router;
+use chrono::Utc;
+
+use super::model::{NewPortfolio, Portfolio, UpdatePortfolio};
+use crate::app::AppState;
+use crate::auth::AuthUser;
+use crate::common::errors::{Error, Result};
+use crate::common::extract::{Json, Path};
+use crate::stocks::normalize_code;
+use crate::store::StoreError;
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/api/portfolios",
+            get(list_portfolios).post(create_portfolio),
+        )
+        .route(
+            "/api/portfolios/:id",
+            get(get_portfolio)
+                .put(update_portfolio)
+                .delete(delete_portfolio),
+        )
+}
+
+/// A wrapper type for all requests/responses from these routes.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PortfolioBody<T> {
+    portfolio: T,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MultiplePortfoliosBody {
+    portfolios: Vec<Portfolio>,
+    portfolios_count: usize,
+}
+
+async fn list_portfolios(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<MultiplePortfoliosBody>> {
+    let portfolios = state
+        .store
+        .portfolios
+        .list_portfolios(auth_user.user_id)
+        .await?;
+
+    Ok(Json(MultiplePortfoliosBody {
+        portfolios_count: portfolios.len(),
+        portfolios,
+    }))
+}
+
+async fn get_portfolio(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(portfolio_id): Path<i64>,
+) -> Result<Json<PortfolioBody<Portfolio>>> {
+    let portfolio = fetch_portfolio(&state, auth_user, portfolio_id).await?;
+
+    Ok(Json(PortfolioBody { portfolio }))
+}
+
+async fn create_portfolio(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<PortfolioBody<NewPortfolio>>,
+) -> Result<(StatusCode, Json<PortfolioBody<Portfolio>>)> {
+    let new = check_new_portfolio(req.portfolio)?;
+
+    let portfolio = state
+        .store
+        .portfolios
+        .create_portfolio(auth_user.user_id, &new, Utc::now())
+        .await
+        .map_err(name_conflict)?;
+
+    Ok((StatusCode::CREATED, Json(PortfolioBody { portfolio })))
+}
+
+async fn update_portfolio(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(portfolio_id): Path<i64>,
+    Json(req): Json<PortfolioBody<UpdatePortfolio>>,
+) -> Result<Json<PortfolioBody<Portfolio>>> {
+    let update = UpdatePortfolio {
+        name: req.portfolio.name.map(|name| name.trim().to_string()),
+        base_currency: req.portfolio.base_currency.as_deref().map(normalize_code),
+    };
+    validate_portfolio(update.name.as_deref(), update.base_currency.as_deref())?;
+
+    let portfolio = state
+        .store
+        .portfolios
+        .update_portfolio(auth_user.user_id, portfolio_id, &update, Utc::now())
+        .await
+        .map_err(name_conflict)?
+        .ok_or(Error::NotFound)?;
+
+    Ok(Json(PortfolioBody { portfolio }))
+}
+
+async fn delete_portfolio(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(portfolio_id): Path<i64>,
+) -> Result<StatusCode> {
+    let deleted = state
+        .store
+        .portfolios
+        .delete_portfolio(auth_user.user_id, portfolio_id)
+        .await?;
+
+    if !deleted {
+        return Err(Error::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Load a portfolio owned by `auth_user`.
+///
+/// Other users' portfolios are reported as `404 Not Found` rather than `403 Forbidden` so
+/// that their ids can't be probed.
+pub(crate) async fn fetch_portfolio(
+    state: &AppState,
+    auth_user: AuthUser,
+    portfolio_id: i64,
+) -> Result<Portfolio> {
+    state
+        .store
+        .portfolios
+        .portfolio(auth_user.user_id, portfolio_id)
+        .await?
+        .ok_or(Error::NotFound)
+}
+
+fn name_conflict(error: StoreError) -> Error {
+    match error {
+        StoreError::Conflict(_) => Error::unprocessable_entity([("name", "name already used")]),
+        error => error.into(),
+    }
+}
+
+/// `new` with its name trimmed and its currency normalized, once it's valid.
+pub(crate) fn check_new_portfolio(new: NewPortfolio) -> Result<NewPortfolio> {
+    let new = NewPortfolio {
+        name: new.name.trim().to_string(),
+        base_currency: normalize_code(&new.base_currency),
+    };
+    validate_portfolio(Some(&new.name), Some(&new.base_currency))?;
+
+    Ok(new)
+}
+
+fn validate_portfolio(name: Option<&str>, base_currency: Option<&str>) -> Result<()> {
+    let mut errors = Vec::new();
+
+    if name.is_some_and(|name| name.is_empty() || name.len() > 64) {
+        errors.push(("name", "must be 1-64 characters"));
+    }
+    if let Some(currency) = base_currency {
+        if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+            errors.push(("baseCurrency", "must be a 3-letter ISO 4217 code"));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::unprocessable_entity(errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{test_session_cookie, Role};
+    use crate::conn::test_database;
+    use crate::testing::{insert_user, send};
+
+    #[tokio::test]
+    async fn portfolios_are_private_to_their_owner() {
+        let db = test_database().await;
+        insert_user(&db, 1, "alice", Role::User).await;
+        insert_user(&db, 2, "bob", Role::User).await;
+        let alice = test_session_cookie(&db, 1).await;
+        let bob = test_session_cookie(&db, 2).await;
+        let app = router().with_state(Arc::new(AppState::new(db)));
+
+        let new = serde_json::json!({"portfolio": {"name": "ISA", "baseCurrency": "gbp"}});
+        let (status, body) = send(&app, "POST", "/api/portfolios", &alice, Some(new.clone())).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["portfolio"]["baseCurrency"], "GBP");
+        let uri = format!("/api/portfolios/{}", body["portfolio"]["id"]);
+
+        let (status, _) = send(&app, "POST", "/api/portfolios", &alice, Some(new)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let (status, _) = send(&app, "GET", &uri, &bob, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = send(&app, "DELETE", &uri, &bob, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = send(&app, "GET", "/api/portfolios", "session=unknown", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = send(&app, "GET", &uri, &alice, None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+
+use super::model::{NewTransaction, Transaction, TransactionKind};
+
+/// Quantities closer to zero than this are treated as flat, absorbing float noise from splits.
+pub const EPSILON: f64 = 1e-9;
+
+/// Share quantities per symbol and the cash balance after replaying a ledger.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Holdings {
+    pub positions: BTreeMap<String, f64>,
+    pub cash: f64,
+}
+
+/// A sell of more shares than were held at the time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Oversold {
+    pub symbol: String,
+    pub trade_date: NaiveDate,
+    pub held: f64,
+}
+
+impl Holdings {
+    /// Apply one ledger entry. Missing optional fields count as zero, they are validated when
+    /// the entry is booked.
+    pub fn apply(&mut self, tx: &Transaction) -> Result<(), Oversold> {
+        let symbol = tx.symbol.clone().unwrap_or_default();
+        let quantity = tx.quantity.unwrap_or_default();
+        let price = tx.price.unwrap_or_default();
+        let amount = tx.amount.unwrap_or_default();
+
+        match tx.kind {
+            TransactionKind::Buy => {
+                *self.positions.entry(symbol).or_default() += quantity;
+                self.cash -= quantity * price + tx.fee;
+            }
+            TransactionKind::Sell => {
+                let held = self.positions.get(&symbol).copied().unwrap_or_default();
+                if quantity > held + EPSILON {
+                    return Err(Oversold {
+                        symbol,
+                        trade_date: tx.trade_date,
+                        held,
+                    });
+                }
+
+                if held - quantity <= EPSILON {
+                    self.positions.remove(&symbol);
+                } else {
+                    self.positions.insert(symbol, held - quantity);
+                }
+                self.cash += quantity * price - tx.fee;
+            }
+            TransactionKind::Split => {
+                if let Some(held) = self.positions.get_mut(&symbol) {
+                    *held *= tx.split_ratio.unwrap_or(1.0);
+                }
+            }
+            TransactionKind::Dividend | TransactionKind::Deposit => self.cash += amount,
+            TransactionKind::Fee | TransactionKind::Withdrawal => self.cash -= amount,
+        }
+
+        Ok(())
+    }
+}
+
+/// Replay ledger entries, which must be ordered by trade date then booking order.
+pub fn replay<'a>(ledger: impl IntoIterator<Item = &'a Transaction>) -> Result<Holdings, Oversold> {
+    let mut holdings = Holdings::default();
+    for tx in ledger {
+        holdings.apply(tx)?;
+    }
+    Ok(holdings)
+}
+
+/// Check that `new` can be appended to `ledger`, which is in replay order.
+///
+/// Entries may be backdated, so the whole ledger is replayed with the new one in place rather
+/// than checking today's holding: a reverse split before a later sell oversells it as surely as
+/// a sell does.
+pub fn check_booking(mut ledger: Vec<Transaction>, new: &NewTransaction) -> Result<(), Oversold> {
+    let candidate = Transaction {
+        transaction_id: i64::MAX,
+        portfolio_id: 0,
+        kind: new.kind,
+        symbol: new.symbol.clone(),
+        trade_date: new.trade_date,
+        quantity: new.quantity,
+        price: new.price,
+        amount: new.amount,
+        fee: new.fee,
+        split_ratio: new.split_ratio,
+        note: None,
+        created_at: chrono::DateTime::UNIX_EPOCH,
+    };
+    let at = ledger.partition_point(|tx| tx.trade_date <= new.trade_date);
+    ledger.insert(at, candidate);
+
+    replay(&ledger).map(drop)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    pub(crate) fn tx(kind: TransactionKind, date: &str) -> Transaction {
+        Transaction {
+            transaction_id: 0,
+            portfolio_id: 1,
+            kind,
+            symbol: None,
+            trade_date: date.parse().unwrap(),
+            quantity: None,
+            price: None,
+            amount: None,
+            fee: 0.0,
+            split_ratio: None,
+            note: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub(crate) fn trade(
+        kind: TransactionKind,
+        date: &str,
+        symbol: &str,
+        qty: f64,
+        price: f64,
+    ) -> Transaction {
+        Transaction {
+            symbol: Some(symbol.to_string()),
+            quantity: Some(qty),
+            price: Some(price),
+            ..tx(kind, date)
+        }
+    }
+
+    #[test]
+    fn positions_and_cash() {
+        let ledger = vec![
+            Transaction {
+                amount: Some(1000.0),
+                ..tx(TransactionKind::Deposit, "2024-01-01")
+            },
+            Transaction {
+                fee: 1.0,
+                ..trade(TransactionKind::Buy, "2024-01-02", "AAPL", 5.0, 100.0)
+            },
+            Transaction {
+                split_ratio: Some(4.0),
+                symbol: Some("AAPL".to_string()),
+                ..tx(TransactionKind::Split, "2024-02-01")
+            },
+            trade(TransactionKind::Sell, "2024-03-01", "AAPL", 20.0, 30.0),
+            Transaction {
+                amount: Some(2.5),
+                symbol: Some("MSFT".to_string()),
+                ..tx(TransactionKind::Dividend, "2024-03-02")
+            },
+        ];
+
+        let holdings = replay(&ledger).unwrap();
+        assert!(holdings.positions.is_empty());
+        assert_eq!(holdings.cash, 1000.0 - 501.0 + 600.0 + 2.5);
+    }
+
+    #[test]
+    fn rejects_backdated_reverse_split_overselling() {
+        let ledger = vec![
+            trade(TransactionKind::Buy, "2024-01-02", "AAPL", 10.0, 100.0),
+            trade(TransactionKind::Sell, "2024-03-01", "AAPL", 8.0, 100.0),
+        ];
+        let split = |ratio| NewTransaction {
+            kind: TransactionKind::Split,
+            symbol: Some("AAPL".to_string()),
+            trade_date: "2024-02-01".parse().unwrap(),
+            quantity: None,
+            price: None,
+            amount: None,
+            fee: 0.0,
+            split_ratio: Some(ratio),
+            note: None,
+        };
+
+        assert_eq!(check_booking(ledger.clone(), &split(2.0)), Ok(()));
+        assert_eq!(
+            check_booking(ledger, &split(0.5)),
+            Err(Oversold {
+                symbol: "AAPL".to_string(),
+                trade_date: "2024-03-01".parse().unwrap(),
+                held: 5.0,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_oversold() {
+        let ledger = vec![
+            trade(TransactionKind::Buy, "2024-01-02", "AAPL", 5.0, 100.0),
+            trade(TransactionKind::Sell, "2024-01-03", "AAPL", 6.0, 100.0),
+        ];
+
+        assert_eq!(
+            replay(&ledger),
+            Err(Oversold {
+                symbol: "AAPL".to_string(),
+                trade_date: "2024-01-03".parse().unwrap(),
+                held: 5.0,
+            })
+        );
+    }
+}
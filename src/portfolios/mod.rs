@@ -0,0 +1,22 @@
+mod analytics;
+pub mod cost_basis;
+mod handlers;
+pub mod ledger;
+pub mod model;
+pub mod performance;
+mod transactions;
+
+use std::sync::Arc;
+
+use axum::Router;
+
+use crate::app::AppState;
+
+pub(crate) use handlers::check_new_portfolio;
+pub(crate) use transactions::check_new_transaction;
+
+pub fn router() -> Router<Arc<AppState>> {
+    handlers::router()
+        .merge(transactions::router())
+        .merge(analytics::router())
+}
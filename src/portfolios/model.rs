@@ -0,0 +1,91 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// A named set of holdings owned by one user, e.g. "ISA" or "Taxable".
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Portfolio {
+    #[serde(rename = "id")]
+    pub portfolio_id: i64,
+    #[serde(skip_serializing)]
+    pub user_id: i64,
+    pub name: String,
+    pub base_currency: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewPortfolio {
+    pub name: String,
+    pub base_currency: String,
+}
+
+/// Changes to a portfolio, `None` leaving a field as is.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UpdatePortfolio {
+    pub name: Option<String>,
+    pub base_currency: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionKind {
+    Buy,
+    Sell,
+    Dividend,
+    Split,
+    Fee,
+    Deposit,
+    Withdrawal,
+}
+
+/// An entry of the append-only ledger that positions are computed from.
+///
+/// Which of the optional fields are set depends on `kind`:
+///
+/// * `Buy`/`Sell`: `symbol`, `quantity` and per-share `price`, plus an optional `fee`
+/// * `Dividend`: `symbol` and the cash `amount` received
+/// * `Split`: `symbol` and `split_ratio`, the number of new shares per old share
+/// * `Fee`: the cash `amount` charged, optionally against a `symbol`
+/// * `Deposit`/`Withdrawal`: the cash `amount` moved in or out of the portfolio
+#[derive(Debug, Clone, PartialEq, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transaction {
+    #[serde(rename = "id")]
+    pub transaction_id: i64,
+    #[serde(skip_serializing)]
+    pub portfolio_id: i64,
+    #[serde(rename = "type")]
+    pub kind: TransactionKind,
+    pub symbol: Option<String>,
+    pub trade_date: NaiveDate,
+    pub quantity: Option<f64>,
+    pub price: Option<f64>,
+    pub amount: Option<f64>,
+    pub fee: f64,
+    pub split_ratio: Option<f64>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A ledger entry to book, see `Transaction` for which fields each kind uses.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewTransaction {
+    #[serde(rename = "type")]
+    pub kind: TransactionKind,
+    pub symbol: Option<String>,
+    pub trade_date: NaiveDate,
+    pub quantity: Option<f64>,
+    pub price: Option<f64>,
+    pub amount: Option<f64>,
+    #[serde(default)]
+    pub fee: f64,
+    pub split_ratio: Option<f64>,
+    pub note: Option<String>,
+}
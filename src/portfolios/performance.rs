@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use super::ledger::{Holdings, Oversold, EPSILON};
+use super::model::{Transaction, TransactionKind};
+
+/// Daily closes per symbol.
+pub type Closes = BTreeMap<String, BTreeMap<NaiveDate, f64>>;
+
+/// Portfolio value at the end of a day, and the deposits less withdrawals booked on it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyValue {
+    pub date: NaiveDate,
+    pub value: f64,
+    pub net_flow: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Performance {
+    pub series: Vec<DailyValue>,
+    /// Time-weighted return over the whole range, not annualized.
+    pub twr: Option<f64>,
+    /// Money-weighted return, annualized.
+    pub xirr: Option<f64>,
+}
+
+/// Value a portfolio on every calendar day of `from..=to` and compute its returns.
+///
+/// Holdings are valued at the last close on or before each day, falling back to the last trade
+/// price for symbols without bars. Deposits and withdrawals are the only external flows;
+/// dividends and fees are part of the return. Flows are taken to happen at the start of the day.
+pub fn performance(
+    ledger: &[Transaction],
+    closes: &Closes,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Performance, Oversold> {
+    let mut holdings = Holdings::default();
+    let mut last_trade = BTreeMap::new();
+    let mut entries = ledger.iter().peekable();
+
+    while let Some(tx) = entries.next_if(|tx| tx.trade_date < from) {
+        apply(&mut holdings, &mut last_trade, tx)?;
+    }
+
+    let start_value = from
+        .pred_opt()
+        .map_or(0.0, |date| value(&holdings, &last_trade, closes, date));
+    let mut series = Vec::new();
+    let mut flows = Vec::new();
+    if start_value.abs() > EPSILON {
+        flows.push((from, -start_value));
+    }
+
+    let mut growth = None;
+    let mut previous = start_value;
+    for date in from.iter_days().take_while(|date| *date <= to) {
+        let mut net_flow = 0.0;
+        while let Some(tx) = entries.next_if(|tx| tx.trade_date == date) {
+            net_flow += apply(&mut holdings, &mut last_trade, tx)?;
+        }
+
+        let value = value(&holdings, &last_trade, closes, date);
+        // A day starting from nothing has no return to chain.
+        if previous + net_flow > EPSILON {
+            growth = Some(growth.unwrap_or(1.0) * value / (previous + net_flow));
+        }
+        if net_flow.abs() > EPSILON {
+            flows.push((date, -net_flow));
+        }
+
+        series.push(DailyValue {
+            date,
+            value,
+            net_flow,
+        });
+        previous = value;
+    }
+
+    flows.push((to, previous));
+
+    Ok(Performance {
+        series,
+        twr: growth.map(|growth| growth - 1.0),
+        xirr: xirr(&flows),
+    })
+}
+
+/// Apply a ledger entry, returning the external flow it represents.
+fn apply<'a>(
+    holdings: &mut Holdings,
+    last_trade: &mut BTreeMap<&'a str, f64>,
+    tx: &'a Transaction,
+) -> Result<f64, Oversold> {
+    holdings.apply(tx)?;
+
+    Ok(match (tx.kind, &tx.symbol, tx.price) {
+        (TransactionKind::Buy | TransactionKind::Sell, Some(symbol), Some(price)) => {
+            last_trade.insert(symbol, price);
+            0.0
+        }
+        (TransactionKind::Deposit, ..) => tx.amount.unwrap_or_default(),
+        (TransactionKind::Withdrawal, ..) => -tx.amount.unwrap_or_default(),
+        _ => 0.0,
+    })
+}
+
+fn value(
+    holdings: &Holdings,
+    last_trade: &BTreeMap<&str, f64>,
+    closes: &Closes,
+    date: NaiveDate,
+) -> f64 {
+    let positions: f64 = holdings
+        .positions
+        .iter()
+        .map(|(symbol, quantity)| {
+            let close = closes
+                .get(symbol)
+                .and_then(|bars| bars.range(..=date).next_back())
+                .map(|(_, close)| *close);
+            let price = close.or_else(|| last_trade.get(symbol.as_str()).copied());
+            quantity * price.unwrap_or_default()
+        })
+        .sum();
+
+    holdings.cash + positions
+}
+
+/// The annual rate at which dated cash flows, negative for money put in, net to zero.
+///
+/// `None` unless there is money both put in and taken out. Solved by bisection, which is slower
+/// than Newton's method but can't diverge on unusual flow patterns.
+pub fn xirr(flows: &[(NaiveDate, f64)]) -> Option<f64> {
+    let (first, _) = flows.iter().min_by_key(|(date, _)| *date)?;
+    if !flows.iter().any(|(_, amount)| *amount < 0.0)
+        || !flows.iter().any(|(_, amount)| *amount > 0.0)
+    {
+        return None;
+    }
+
+    let npv = |rate: f64| -> f64 {
+        flows
+            .iter()
+            .map(|(date, amount)| {
+                let years = (*date - *first).num_days() as f64 / 365.0;
+                amount / (1.0 + rate).powf(years)
+            })
+            .sum()
+    };
+
+    let (mut low, mut high) = (-0.999_999, 1.0);
+    while npv(high).signum() == npv(low).signum() {
+        high *= 2.0;
+        if high > 1e9 {
+            return None;
+        }
+    }
+
+    for _ in 0..200 {
+        let mid = (low + high) / 2.0;
+        if npv(mid).signum() == npv(low).signum() {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    Some((low + high) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portfolios::ledger::tests::{trade, tx};
+
+    fn date(date: &str) -> NaiveDate {
+        date.parse().unwrap()
+    }
+
+    fn deposit(date: &str, amount: f64) -> Transaction {
+        Transaction {
+            amount: Some(amount),
+            ..tx(TransactionKind::Deposit, date)
+        }
+    }
+
+    #[test]
+    fn twr_ignores_external_flows() {
+        let ledger = vec![
+            deposit("2024-01-01", 1000.0),
+            trade(TransactionKind::Buy, "2024-01-01", "ACME", 10.0, 100.0),
+            deposit("2024-01-03", 1100.0),
+        ];
+        let closes = Closes::from([(
+            "ACME".to_string(),
+            BTreeMap::from([(date("2024-01-02"), 110.0), (date("2024-01-04"), 120.0)]),
+        )]);
+
+        let performance =
+            performance(&ledger, &closes, date("2024-01-01"), date("2024-01-04")).unwrap();
+
+        let values: Vec<f64> = performance.series.iter().map(|day| day.value).collect();
+        assert_eq!(values, [1000.0, 1100.0, 2200.0, 2300.0]);
+        assert_eq!(performance.series[2].net_flow, 1100.0);
+        let twr = performance.twr.unwrap();
+        assert!((twr - (1.1 * 2300.0 / 2200.0 - 1.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn range_starts_from_prior_value() {
+        let ledger = vec![
+            deposit("2024-01-01", 1000.0),
+            trade(TransactionKind::Buy, "2024-01-01", "ACME", 10.0, 100.0),
+        ];
+        let closes = Closes::from([(
+            "ACME".to_string(),
+            BTreeMap::from([(date("2024-01-01"), 100.0), (date("2025-01-01"), 150.0)]),
+        )]);
+
+        let performance =
+            performance(&ledger, &closes, date("2024-06-01"), date("2025-01-01")).unwrap();
+        assert_eq!(performance.series[0].value, 1000.0);
+        assert_eq!(performance.twr, Some(0.5));
+    }
+
+    #[test]
+    fn xirr_annualizes() {
+        let rate = xirr(&[(date("2023-01-01"), -1000.0), (date("2024-01-01"), 1100.0)]).unwrap();
+        assert!((rate - 0.1).abs() < 1e-9);
+
+        assert_eq!(xirr(&[(date("2023-01-01"), -1000.0)]), None);
+    }
+}
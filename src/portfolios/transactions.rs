@@ -0,0 +1,252 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::Router;
+use chrono::Utc;
+
+use super::handlers::fetch_portfolio;
+use super::ledger::{replay, Oversold};
+use super::model::{NewTransaction, Transaction, TransactionKind};
+use crate::app::AppState;
+use crate::auth::AuthUser;
+use crate::common::errors::{Error, Result};
+use crate::common::extract::{Json, Path};
+use crate::stocks::{fetch_stock, normalize_code};
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/api/portfolios/:id/transactions",
+            get(list_transactions).post(create_transaction),
+        )
+        .route("/api/portfolios/:id/positions", get(get_positions))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct TransactionBody<T> {
+    transaction: T,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MultipleTransactionsBody {
+    transactions: Vec<Transaction>,
+    transactions_count: usize,
+}
+
+#[derive(serde::Serialize)]
+struct Position {
+    symbol: String,
+    quantity: f64,
+}
+
+#[derive(serde::Serialize)]
+struct PositionsBody {
+    positions: Vec<Position>,
+    cash: f64,
+}
+
+async fn list_transactions(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(portfolio_id): Path<i64>,
+) -> Result<Json<MultipleTransactionsBody>> {
+    let portfolio = fetch_portfolio(&state, auth_user, portfolio_id).await?;
+    let transactions = state
+        .store
+        .transactions
+        .ledger(portfolio.portfolio_id)
+        .await?;
+
+    Ok(Json(MultipleTransactionsBody {
+        transactions_count: transactions.len(),
+        transactions,
+    }))
+}
+
+async fn create_transaction(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(portfolio_id): Path<i64>,
+    Json(req): Json<TransactionBody<NewTransaction>>,
+) -> Result<(StatusCode, Json<TransactionBody<Transaction>>)> {
+    let portfolio = fetch_portfolio(&state, auth_user, portfolio_id).await?;
+    let new = check_new_transaction(req.transaction)?;
+
+    if let Some(symbol) = &new.symbol {
+        if fetch_stock(&state, symbol).await?.is_none() {
+            return Err(Error::unprocessable_entity([("symbol", "unknown symbol")]));
+        }
+    }
+
+    let transaction = state
+        .store
+        .transactions
+        .book_transaction(portfolio.portfolio_id, &new, Utc::now())
+        .await?
+        .map_err(
+            |Oversold {
+                 held, trade_date, ..
+             }| match new.kind {
+                TransactionKind::Sell => Error::unprocessable_entity([(
+                    "quantity",
+                    format!("exceeds the {held} shares held on {trade_date}"),
+                )]),
+                // A backdated split leaving a later sell short.
+                _ => Error::unprocessable_entity([(
+                    "splitRatio",
+                    format!("leaves only {held} shares for the sell on {trade_date}"),
+                )]),
+            },
+        )?;
+
+    Ok((StatusCode::CREATED, Json(TransactionBody { transaction })))
+}
+
+async fn get_positions(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(portfolio_id): Path<i64>,
+) -> Result<Json<PositionsBody>> {
+    let portfolio = fetch_portfolio(&state, auth_user, portfolio_id).await?;
+    let ledger = state
+        .store
+        .transactions
+        .ledger(portfolio.portfolio_id)
+        .await?;
+
+    // Booking replays the ledger with every new entry, so a stored one failing to is a bug.
+    let holdings = replay(&ledger)
+        .map_err(|e| anyhow::anyhow!("ledger of portfolio {portfolio_id} is oversold: {e:?}"))?;
+
+    Ok(Json(PositionsBody {
+        positions: holdings
+            .positions
+            .into_iter()
+            .map(|(symbol, quantity)| Position { symbol, quantity })
+            .collect(),
+        cash: holdings.cash,
+    }))
+}
+
+/// `new` with its symbol normalized, once it's valid. Whether the symbol is listed is up to the
+/// caller.
+pub(crate) fn check_new_transaction(mut new: NewTransaction) -> Result<NewTransaction> {
+    new.symbol = new.symbol.as_deref().map(normalize_code);
+    validate_transaction(&new)?;
+
+    Ok(new)
+}
+
+fn validate_transaction(new: &NewTransaction) -> Result<()> {
+    let mut errors = Vec::new();
+    let positive = |value: Option<f64>| value.is_some_and(|v| v.is_finite() && v > 0.0);
+
+    let needs_symbol = !matches!(
+        new.kind,
+        TransactionKind::Fee | TransactionKind::Deposit | TransactionKind::Withdrawal
+    );
+    if needs_symbol && new.symbol.as_deref().unwrap_or_default().is_empty() {
+        errors.push(("symbol", "is required for this transaction type"));
+    }
+    if matches!(
+        new.kind,
+        TransactionKind::Deposit | TransactionKind::Withdrawal
+    ) && new.symbol.is_some()
+    {
+        errors.push(("symbol", "must not be set for cash movements"));
+    }
+
+    match new.kind {
+        TransactionKind::Buy | TransactionKind::Sell => {
+            if !positive(new.quantity) {
+                errors.push(("quantity", "must be a positive number"));
+            }
+            if !new.price.is_some_and(|p| p.is_finite() && p >= 0.0) {
+                errors.push(("price", "must be zero or a positive number"));
+            }
+        }
+        TransactionKind::Split => {
+            if !positive(new.split_ratio) {
+                errors.push(("splitRatio", "must be a positive number"));
+            }
+        }
+        TransactionKind::Dividend
+        | TransactionKind::Fee
+        | TransactionKind::Deposit
+        | TransactionKind::Withdrawal => {
+            if !positive(new.amount) {
+                errors.push(("amount", "must be a positive number"));
+            }
+        }
+    }
+
+    if !new.fee.is_finite() || new.fee < 0.0 {
+        errors.push(("fee", "must not be negative"));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::unprocessable_entity(errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{test_session_cookie, Role};
+    use crate::conn::test_database;
+    use crate::testing::{insert_portfolio, insert_stock, insert_user, send};
+
+    fn book(transaction: serde_json::Value) -> Option<serde_json::Value> {
+        Some(serde_json::json!({ "transaction": transaction }))
+    }
+
+    #[tokio::test]
+    async fn rejects_sells_exceeding_holding() {
+        let db = test_database().await;
+        insert_user(&db, 1, "alice", Role::User).await;
+        insert_stock(&db, "ACME", "XNYS", "Acme Corp").await;
+        insert_portfolio(&db, 1, 1, "ISA").await;
+        let alice = test_session_cookie(&db, 1).await;
+        let app = router().with_state(Arc::new(AppState::new(db)));
+        let uri = "/api/portfolios/1/transactions";
+
+        let transaction = serde_json::json!({"type": "buy", "symbol": "acme", "tradeDate": "2024-02-01", "quantity": 10, "price": 5});
+        let (status, _) = send(&app, "POST", uri, &alice, book(transaction)).await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        // Backdated before the buy, so nothing was held yet.
+        let transaction = serde_json::json!({"type": "sell", "symbol": "ACME", "tradeDate": "2024-01-15", "quantity": 1, "price": 5});
+        let (status, body) = send(&app, "POST", uri, &alice, book(transaction)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body["errors"]["quantity"].is_array());
+
+        let transaction = serde_json::json!({"type": "sell", "symbol": "ACME", "tradeDate": "2024-03-01", "quantity": 11, "price": 5});
+        let (status, _) = send(&app, "POST", uri, &alice, book(transaction)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let transaction = serde_json::json!({"type": "sell", "symbol": "ACME", "tradeDate": "2024-03-01", "quantity": 4, "price": 6});
+        let (status, body) = send(&app, "POST", uri, &alice, book(transaction)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["transaction"]["type"], "sell");
+
+        // A backdated 1:4 reverse split would leave 2.5 shares for that sell of 4.
+        let transaction = serde_json::json!({"type": "split", "symbol": "ACME", "tradeDate": "2024-02-15", "splitRatio": 0.25});
+        let (status, body) = send(&app, "POST", uri, &alice, book(transaction)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body["errors"]["splitRatio"].is_array());
+
+        let transaction = serde_json::json!({"type": "deposit", "tradeDate": "2024-03-01"});
+        let (status, body) = send(&app, "POST", uri, &alice, book(transaction)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body["errors"]["amount"].is_array());
+
+        let (_, body) = send(&app, "GET", "/api/portfolios/1/positions", &alice, None).await;
+        assert_eq!(body["positions"][0]["quantity"], 6.0);
+        assert_eq!(body["cash"], -26.0);
+    }
+}
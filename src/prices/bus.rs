@@ -0,0 +1,194 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, watch};
+
+use super::model::Quote;
+
+/// Quotes buffered per subscriber before the slowest one starts missing some.
+pub const QUOTE_CHANNEL_CAPACITY: usize = 4096;
+
+/// Fan-out of quotes to any number of subscribers.
+///
+/// Every quote goes out on a `broadcast` channel, which never blocks the publisher: a subscriber
+/// falling more than `QUOTE_CHANNEL_CAPACITY` quotes behind sees `RecvError::Lagged` instead.
+/// The latest quote per symbol is also kept in a `watch` channel so late joiners start from the
+/// current value rather than waiting for the next tick.
+pub struct QuoteBus {
+    ticks: broadcast::Sender<Tick>,
+    latest: watch::Sender<HashMap<String, Tick>>,
+    published: AtomicU64,
+}
+
+/// A published quote, numbered in publishing order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tick {
+    pub seq: u64,
+    pub quote: Quote,
+}
+
+impl Default for QuoteBus {
+    fn default() -> Self {
+        Self {
+            ticks: broadcast::channel(QUOTE_CHANNEL_CAPACITY).0,
+            latest: watch::Sender::new(HashMap::new()),
+            published: AtomicU64::new(0),
+        }
+    }
+}
+
+impl QuoteBus {
+    /// Publish `quote`, unless it's older than the latest one of its symbol, e.g. an end-of-day
+    /// bar stamped at midnight after a live tick of the same day. A quote as old as the latest
+    /// one replaces it, so corrected bars go out.
+    pub fn publish(&self, quote: Quote) {
+        // Numbering and sending under the lock of `latest` keeps ticks in the order of their
+        // numbers, which `QuoteSubscription::next` relies on.
+        self.latest.send_if_modified(|latest| {
+            if let Some(last) = latest.get(&quote.symbol) {
+                if last.quote.at > quote.at {
+                    return false;
+                }
+            }
+            let tick = Tick {
+                seq: self.published.fetch_add(1, Ordering::Relaxed) + 1,
+                quote,
+            };
+            latest.insert(tick.quote.symbol.clone(), tick.clone());
+            // Only fails when nothing is subscribed.
+            let _ = self.ticks.send(tick);
+            true
+        });
+    }
+
+    /// Every quote published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Tick> {
+        self.ticks.subscribe()
+    }
+
+    pub fn latest(&self, symbol: &str) -> Option<Quote> {
+        self.latest_tick(symbol).map(|tick| tick.quote)
+    }
+
+    fn latest_tick(&self, symbol: &str) -> Option<Tick> {
+        self.latest.borrow().get(symbol).cloned()
+    }
+
+    /// The current quote of each of `symbols`, then every newer one.
+    pub fn subscribe_to(&self, symbols: HashSet<String>) -> QuoteSubscription {
+        // Subscribe before taking the snapshot so nothing published in between is lost; the
+        // duplicates this can cause are skipped by `QuoteSubscription::next`.
+        let ticks = self.subscribe();
+        let pending: VecDeque<Tick> = symbols
+            .iter()
+            .filter_map(|symbol| self.latest_tick(symbol))
+            .collect();
+
+        QuoteSubscription {
+            symbols,
+            pending,
+            ticks,
+            delivered: HashMap::new(),
+        }
+    }
+}
+
+pub struct QuoteSubscription {
+    symbols: HashSet<String>,
+    pending: VecDeque<Tick>,
+    ticks: broadcast::Receiver<Tick>,
+    /// The number of the last tick delivered per symbol.
+    delivered: HashMap<String, u64>,
+}
+
+impl QuoteSubscription {
+    /// The next quote of a subscribed symbol.
+    ///
+    /// `RecvError::Lagged` means quotes were missed because this subscriber fell behind; callers
+    /// should drop it rather than carry on with a gap.
+    pub async fn next(&mut self) -> Result<Quote, RecvError> {
+        loop {
+            let tick = match self.pending.pop_front() {
+                Some(tick) => tick,
+                None => self.ticks.recv().await?,
+            };
+            if !self.symbols.contains(&tick.quote.symbol) {
+                continue;
+            }
+
+            let last = self.delivered.get(&tick.quote.symbol);
+            if last.is_some_and(|last| *last >= tick.seq) {
+                continue;
+            }
+            self.delivered.insert(tick.quote.symbol.clone(), tick.seq);
+            return Ok(tick.quote);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(symbol: &str, price: f64, at: &str) -> Quote {
+        Quote {
+            symbol: symbol.to_string(),
+            price,
+            open: price,
+            previous_close: None,
+            at: at.parse().unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn late_joiners_start_from_the_latest_quote() {
+        let bus = QuoteBus::default();
+        bus.publish(quote("AAPL", 1.0, "2024-01-02T15:00:00Z"));
+        bus.publish(quote("AAPL", 2.0, "2024-01-02T15:01:00Z"));
+        bus.publish(quote("MSFT", 3.0, "2024-01-02T15:01:00Z"));
+
+        let mut subscription = bus.subscribe_to(HashSet::from(["AAPL".to_string()]));
+        bus.publish(quote("MSFT", 4.0, "2024-01-02T15:02:00Z"));
+        bus.publish(quote("AAPL", 5.0, "2024-01-02T15:02:00Z"));
+
+        assert_eq!(subscription.next().await.unwrap().price, 2.0);
+        assert_eq!(subscription.next().await.unwrap().price, 5.0);
+    }
+
+    #[tokio::test]
+    async fn older_quotes_are_dropped_and_corrections_delivered() {
+        let bus = QuoteBus::default();
+        let mut live = bus.subscribe_to(HashSet::from(["AAPL".to_string()]));
+        bus.publish(quote("AAPL", 1.0, "2024-01-02T15:00:00Z"));
+        // An end-of-day bar of the same session, stamped at midnight.
+        bus.publish(quote("AAPL", 0.5, "2024-01-02T00:00:00Z"));
+        assert_eq!(bus.latest("AAPL").unwrap().price, 1.0);
+
+        bus.publish(quote("AAPL", 1.5, "2024-01-02T15:00:00Z"));
+        let mut late = bus.subscribe_to(HashSet::from(["AAPL".to_string()]));
+        assert_eq!(live.next().await.unwrap().price, 1.0);
+        assert_eq!(live.next().await.unwrap().price, 1.5);
+        assert_eq!(late.next().await.unwrap().price, 1.5);
+
+        bus.publish(quote("AAPL", 2.0, "2024-01-02T15:01:00Z"));
+        assert_eq!(live.next().await.unwrap().price, 2.0);
+        assert_eq!(late.next().await.unwrap().price, 2.0);
+    }
+
+    #[tokio::test]
+    async fn slow_subscribers_lag() {
+        let bus = QuoteBus::default();
+        let mut subscription = bus.subscribe_to(HashSet::from(["AAPL".to_string()]));
+
+        let start: chrono::DateTime<chrono::Utc> = "2024-01-02T15:00:00Z".parse().unwrap();
+        for n in 0..=QUOTE_CHANNEL_CAPACITY {
+            bus.publish(Quote {
+                at: start + chrono::Duration::seconds(n as i64),
+                ..quote("AAPL", n as f64, "2024-01-02T15:00:00Z")
+            });
+        }
+
+        assert_eq!(subscription.next().await, Err(RecvError::Lagged(1)));
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::{Arc, Weak};
+
+use tokio::sync::broadcast;
+
+use super::bus::Tick;
+use super::model::Quote;
+use crate::app::AppState;
+use crate::common::cancel::CancellationToken;
+use crate::common::errors::Result;
+use crate::common::observer::{BoxFuture, Observable, Observer};
+
+/// Subject notifying its observers of every quote published on the `QuoteBus`.
+#[derive(Default)]
+pub struct QuoteFeed {
+    observers: Vec<Weak<dyn Observer<Subject = Self>>>,
+    quote: Option<Quote>,
+}
+
+impl QuoteFeed {
+    /// The quote being delivered; `None` only before the first one arrives.
+    pub fn quote(&self) -> Option<&Quote> {
+        self.quote.as_ref()
+    }
+
+    /// Deliver quotes from `QuoteBus::subscribe` until the bus is dropped or `cancel` is
+    /// cancelled.
+    ///
+    /// Observers are notified one quote at a time, so each sees the quotes of a symbol in order.
+    pub async fn run(mut self, mut quotes: broadcast::Receiver<Tick>, cancel: CancellationToken) {
+        loop {
+            let received = tokio::select! {
+                _ = cancel.cancelled() => break,
+                received = quotes.recv() => received,
+            };
+            match received {
+                Ok(tick) => {
+                    self.quote = Some(tick.quote);
+                    self.update().await;
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("quote feed lagged, {skipped} quotes skipped");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+}
+
+impl Observable for QuoteFeed {
+    type Observer = Arc<dyn Observer<Subject = Self>>;
+
+    fn update(&self) -> BoxFuture<'_, ()> {
+        // Collect the live observers outside the future so it doesn't hold the `Weak`s.
+        let observers: Vec<_> = self.observers.iter().flat_map(Weak::upgrade).collect();
+
+        Box::pin(async move {
+            futures::future::join_all(observers.iter().map(|o| o.observe(self))).await;
+        })
+    }
+
+    fn attach(&mut self, observer: Self::Observer) {
+        self.observers.push(Arc::downgrade(&observer));
+    }
+
+    fn detach(&mut self, observer: Self::Observer) {
+        self.observers
+            .retain(|o| !o.ptr_eq(&Arc::downgrade(&observer)));
+    }
+}
+
+/// Publish the latest stored bar of `symbol` as a quote, after bars were written.
+pub(crate) async fn publish_latest_bar(state: &AppState, symbol: &str) -> Result<()> {
+    let bars = state.store.prices.latest_bars(symbol, 2).await?;
+
+    if let Some(latest) = bars.first() {
+        state.quotes.publish(Quote {
+            symbol: latest.symbol.clone(),
+            price: latest.close,
+            open: latest.open,
+            previous_close: bars.get(1).map(|bar| bar.close),
+            at: latest.date.and_time(chrono::NaiveTime::MIN).and_utc(),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<f64>>);
+
+    impl Observer for Recorder {
+        type Subject = QuoteFeed;
+
+        fn observe<'a>(&'a self, feed: &'a QuoteFeed) -> BoxFuture<'a, ()> {
+            Box::pin(async move {
+                self.0.lock().unwrap().extend(feed.quote().map(|q| q.price));
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn notifies_attached_observers() {
+        let (sender, receiver) = broadcast::channel(8);
+        let kept = Arc::new(Recorder::default());
+        let detached = Arc::new(Recorder::default());
+
+        let mut feed = QuoteFeed::default();
+        feed.attach(kept.clone());
+        feed.attach(detached.clone());
+        feed.detach(detached.clone());
+
+        for (seq, price) in [(1, 1.0), (2, 2.0)] {
+            let quote = Quote {
+                symbol: "ACME".to_string(),
+                price,
+                open: 1.0,
+                previous_close: None,
+                at: chrono::Utc::now(),
+            };
+            sender.send(Tick { seq, quote }).unwrap();
+        }
+        drop(sender);
+        feed.run(receiver, CancellationToken::new()).await;
+
+        assert_eq!(*kept.0.lock().unwrap(), [1.0, 2.0]);
+        assert!(detached.0.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn stops_on_cancellation() {
+        let (_sender, receiver) = broadcast::channel::<Tick>(8);
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            QuoteFeed::default().run(receiver, cancel),
+        )
+        .await
+        .expect("feed stops while the bus is still open");
+    }
+}
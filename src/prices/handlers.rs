@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+use axum::extract::{DefaultBodyLimit, State};
+use axum::routing::{get, post};
+use axum::Router;
+use chrono::NaiveDate;
+
+use super::feed::publish_latest_bar;
+use super::import::import_bars;
+use super::model::{Interval, NewBar, PriceBar};
+use super::resample::resample;
+use crate::app::AppState;
+use crate::auth::{Admin, RequireRole};
+use crate::common::errors::{Error, Result};
+use crate::common::extract::{Json, Path, Query};
+use crate::stocks::fetch_stock;
+
+/// Decades of daily bars for one symbol are well above axum's default 2MB body limit.
+const IMPORT_BODY_LIMIT: usize = 32 * 1024 * 1024;
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/stocks/:symbol/bars", get(list_bars).put(upsert_bars))
+        .route(
+            "/api/stocks/:symbol/bars/import",
+            post(import_bars).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(default)]
+struct ListBarsQuery {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    interval: Interval,
+}
+
+#[derive(serde::Serialize)]
+struct BarsBody {
+    symbol: String,
+    interval: Interval,
+    bars: Vec<PriceBar>,
+}
+
+#[derive(serde::Deserialize)]
+struct NewBarsBody {
+    bars: Vec<NewBar>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UpsertBarsBody {
+    symbol: String,
+    bars_count: usize,
+}
+
+async fn list_bars(
+    State(state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+    Query(query): Query<ListBarsQuery>,
+) -> Result<Json<BarsBody>> {
+    let stock = fetch_stock(&state, &symbol).await?.ok_or(Error::NotFound)?;
+
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Err(Error::unprocessable_entity([(
+                "from",
+                "must not be after `to`",
+            )]));
+        }
+    }
+
+    let daily = state
+        .store
+        .prices
+        .bars(&stock.symbol, query.from, query.to)
+        .await?;
+
+    Ok(Json(BarsBody {
+        symbol: stock.symbol,
+        interval: query.interval,
+        bars: resample(daily, query.interval),
+    }))
+}
+
+// Bars are written as a batch in a single transaction, replacing any already stored for
+// the same dates. Only admins write market data, it feeds every quote stream and alert.
+async fn upsert_bars(
+    _admin: RequireRole<Admin>,
+    State(state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+    Json(req): Json<NewBarsBody>,
+) -> Result<Json<UpsertBarsBody>> {
+    let stock = fetch_stock(&state, &symbol).await?.ok_or(Error::NotFound)?;
+
+    let errors: Vec<_> = req
+        .bars
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, bar)| {
+            validate_bar(bar)
+                .err()
+                .map(|message| (format!("bars[{idx}]"), message))
+        })
+        .collect();
+    if !errors.is_empty() {
+        return Err(Error::unprocessable_entity(errors));
+    }
+
+    let mut bars = state.store.prices.write_bars(&stock.symbol).await?;
+    for bar in &req.bars {
+        bars.upsert(bar).await?;
+    }
+    bars.commit().await?;
+    publish_latest_bar(&state, &stock.symbol).await?;
+
+    Ok(Json(UpsertBarsBody {
+        symbol: stock.symbol,
+        bars_count: req.bars.len(),
+    }))
+}
+
+/// Check a bar is internally consistent, returning the reason if it isn't.
+pub(crate) fn validate_bar(bar: &NewBar) -> Result<(), &'static str> {
+    let prices = [bar.open, bar.high, bar.low, bar.close];
+
+    if prices.iter().any(|p| !p.is_finite() || *p <= 0.0) {
+        return Err("prices must be positive numbers");
+    }
+    if bar.adj_close.is_some_and(|p| !p.is_finite() || p <= 0.0) {
+        return Err("adjusted close must be a positive number");
+    }
+    if bar.high < bar.open.max(bar.close) || bar.low > bar.open.min(bar.close) {
+        return Err("high/low must bound open and close");
+    }
+    if bar.volume < 0 {
+        return Err("volume must not be negative");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{test_session_cookie, Role};
+    use crate::conn::test_database;
+    use crate::testing::{insert_stock, insert_user, send};
+    use axum::http::StatusCode;
+
+    #[tokio::test]
+    async fn upsert_then_query_weekly() {
+        let db = test_database().await;
+        insert_stock(&db, "ACME", "XNYS", "Acme Corp").await;
+        insert_user(&db, 1, "root", Role::Admin).await;
+        let admin = test_session_cookie(&db, 1).await;
+        let app = router().with_state(Arc::new(AppState::new(db)));
+
+        let bars = serde_json::json!({"bars": [
+            {"date": "2024-02-05", "open": 10.0, "high": 11.0, "low": 9.0, "close": 10.5, "volume": 10},
+            {"date": "2024-02-06", "open": 10.5, "high": 12.0, "low": 10.0, "close": 11.5, "volume": 20},
+            {"date": "2024-02-12", "open": 11.5, "high": 11.6, "low": 11.0, "close": 11.2, "volume": 5},
+        ]});
+        // Anyone can read bars, only admins write them.
+        let uri = "/api/stocks/acme/bars";
+        let (status, _) = send(&app, "PUT", uri, "", Some(bars.clone())).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(&app, "PUT", uri, &admin, Some(bars)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let uri = "/api/stocks/ACME/bars?interval=1w&to=2024-02-09";
+        let (_, body) = send(&app, "GET", uri, "", None).await;
+
+        assert_eq!(body["interval"], "1w");
+        assert_eq!(body["bars"].as_array().unwrap().len(), 1);
+        assert_eq!(body["bars"][0]["high"], 12.0);
+        assert_eq!(body["bars"][0]["adjClose"], 11.5);
+        assert_eq!(body["bars"][0]["volume"], 30);
+    }
+}
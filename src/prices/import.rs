@@ -0,0 +1,391 @@
+use std::sync::Arc;
+
+use axum::extract::multipart::MultipartRejection;
+use axum::extract::{Multipart, State};
+
+use super::feed::publish_latest_bar;
+use super::handlers::validate_bar;
+use super::model::NewBar;
+use crate::app::AppState;
+use crate::auth::{Admin, RequireRole};
+use crate::common::errors::{Error, Result};
+use crate::common::extract::{Json, Path};
+use crate::stocks::fetch_stock;
+
+/// Name of the multipart field carrying the CSV file.
+const FILE_FIELD: &str = "file";
+
+/// Stop collecting row errors past this, a file that bad needs fixing at the source.
+const MAX_REPORTED_ERRORS: usize = 50;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct ImportBody {
+    symbol: String,
+    bars_count: usize,
+}
+
+/// Why an upload couldn't be imported, reported as a validation error of the file field.
+#[derive(Debug)]
+pub(crate) enum MultipartError {
+    NoName,
+    ReadError,
+    MissingColumn(&'static str),
+    InvalidRow { line: usize, reason: String },
+}
+
+impl std::fmt::Display for MultipartError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MultipartError::NoName => f.write_str("No named field in multipart"),
+            MultipartError::ReadError => f.write_str("Reading multipart error"),
+            MultipartError::MissingColumn(column) => write!(f, "Missing column `{column}`"),
+            MultipartError::InvalidRow { line, reason } => write!(f, "Line {line}: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for MultipartError {}
+
+impl From<MultipartError> for Error {
+    fn from(error: MultipartError) -> Self {
+        Error::unprocessable_entity([(FILE_FIELD, error.to_string())])
+    }
+}
+
+/// Import a Yahoo/Stooq style CSV of daily OHLCV rows for one symbol.
+///
+/// The file is parsed line by line as it streams in, and only once the whole of it is valid are
+/// its rows upserted, in a single transaction. Otherwise nothing is written and the per-row
+/// errors are returned. Writing only after the upload keeps a slow client from holding the
+/// database's write lock.
+pub(super) async fn import_bars(
+    _admin: RequireRole<Admin>,
+    State(state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+    multipart: Result<Multipart, MultipartRejection>,
+) -> Result<Json<ImportBody>> {
+    let mut multipart = multipart?;
+    let stock = fetch_stock(&state, &symbol).await?.ok_or(Error::NotFound)?;
+
+    let mut field = loop {
+        let field = multipart
+            .next_field()
+            .await
+            .map_err(|_| MultipartError::ReadError)?
+            .ok_or_else(|| Error::unprocessable_entity([(FILE_FIELD, "missing CSV file")]))?;
+
+        match field.name() {
+            Some(FILE_FIELD) => break field,
+            Some(_) => continue,
+            None => return Err(MultipartError::NoName.into()),
+        }
+    };
+
+    let mut importer = Importer::default();
+    let mut buffer = Vec::new();
+
+    while let Some(chunk) = field.chunk().await.map_err(|_| MultipartError::ReadError)? {
+        buffer.extend_from_slice(&chunk);
+
+        while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            importer.feed(&line);
+        }
+    }
+    // The last line may not be newline terminated.
+    importer.feed(&buffer);
+
+    let rows = importer.finish()?;
+    let mut bars = state.store.prices.write_bars(&stock.symbol).await?;
+    for bar in &rows {
+        bars.upsert(bar).await?;
+    }
+    bars.commit().await?;
+    publish_latest_bar(&state, &stock.symbol).await?;
+
+    Ok(Json(ImportBody {
+        symbol: stock.symbol,
+        bars_count: rows.len(),
+    }))
+}
+
+#[derive(Default)]
+struct Importer {
+    layout: Option<CsvLayout>,
+    /// Set when the header was unusable, after which rows can't be made sense of.
+    bad_header: bool,
+    line: usize,
+    bars: Vec<NewBar>,
+    errors: Vec<MultipartError>,
+}
+
+impl Importer {
+    /// Parse one raw line, keeping its bar unless an earlier line already failed, in which case
+    /// nothing will be imported anyway and the line is only validated.
+    fn feed(&mut self, raw: &[u8]) {
+        self.line += 1;
+
+        let Ok(text) = std::str::from_utf8(raw) else {
+            self.error(MultipartError::InvalidRow {
+                line: self.line,
+                reason: "not valid UTF-8".to_string(),
+            });
+            return;
+        };
+        let text = text.trim_start_matches('\u{feff}').trim();
+        if text.is_empty() {
+            return;
+        }
+
+        let Some(layout) = &self.layout else {
+            if !self.bad_header {
+                match CsvLayout::from_header(text) {
+                    Ok(layout) => self.layout = Some(layout),
+                    Err(error) => {
+                        self.bad_header = true;
+                        self.error(error);
+                    }
+                }
+            }
+            return;
+        };
+
+        match layout.parse_row(self.line, text) {
+            Ok(bar) if self.errors.is_empty() => self.bars.push(bar),
+            Ok(_) => {}
+            Err(error) => self.error(error),
+        }
+    }
+
+    fn error(&mut self, error: MultipartError) {
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors.push(error);
+        }
+    }
+
+    /// The bars of the file, or every collected error if it was invalid.
+    fn finish(self) -> Result<Vec<NewBar>> {
+        if self.layout.is_none() && self.errors.is_empty() {
+            return Err(MultipartError::MissingColumn("Date").into());
+        }
+        if !self.errors.is_empty() {
+            return Err(Error::unprocessable_entity(
+                self.errors
+                    .into_iter()
+                    .map(|error| (FILE_FIELD, error.to_string())),
+            ));
+        }
+
+        Ok(self.bars)
+    }
+}
+
+/// Column positions resolved from the CSV header.
+///
+/// Yahoo exports `Date,Open,High,Low,Close,Adj Close,Volume` while Stooq exports
+/// `Date,Open,High,Low,Close,Volume`; header names are matched case-insensitively and
+/// ignoring spaces and underscores.
+#[derive(Debug, PartialEq)]
+struct CsvLayout {
+    date: usize,
+    open: usize,
+    high: usize,
+    low: usize,
+    close: usize,
+    adj_close: Option<usize>,
+    volume: Option<usize>,
+}
+
+impl CsvLayout {
+    fn from_header(header: &str) -> Result<Self, MultipartError> {
+        let names: Vec<String> = header
+            .split(',')
+            .map(|name| {
+                name.trim()
+                    .trim_matches('"')
+                    .chars()
+                    .filter(|c| !c.is_whitespace() && *c != '_')
+                    .collect::<String>()
+                    .to_lowercase()
+            })
+            .collect();
+
+        let find = |name: &str| names.iter().position(|n| n == name);
+        let require = |name: &str, column: &'static str| {
+            find(name).ok_or(MultipartError::MissingColumn(column))
+        };
+
+        Ok(Self {
+            date: require("date", "Date")?,
+            open: require("open", "Open")?,
+            high: require("high", "High")?,
+            low: require("low", "Low")?,
+            close: require("close", "Close")?,
+            adj_close: find("adjclose"),
+            volume: find("volume"),
+        })
+    }
+
+    fn parse_row(&self, line: usize, row: &str) -> Result<NewBar, MultipartError> {
+        let invalid = |reason: String| MultipartError::InvalidRow { line, reason };
+        let cells: Vec<&str> = row.split(',').map(|c| c.trim().trim_matches('"')).collect();
+
+        let cell = |idx: usize, column: &str| {
+            cells
+                .get(idx)
+                .copied()
+                .filter(|c| !c.is_empty())
+                .ok_or_else(|| invalid(format!("missing {column}")))
+        };
+        let price = |idx: usize, column: &str| {
+            let value = cell(idx, column)?;
+            value
+                .parse::<f64>()
+                .map_err(|_| invalid(format!("invalid {column} `{value}`")))
+        };
+
+        let date = cell(self.date, "date")?;
+        let bar = NewBar {
+            date: date
+                .parse()
+                .map_err(|_| invalid(format!("invalid date `{date}`")))?,
+            open: price(self.open, "open")?,
+            high: price(self.high, "high")?,
+            low: price(self.low, "low")?,
+            close: price(self.close, "close")?,
+            adj_close: self
+                .adj_close
+                .map(|idx| price(idx, "adjusted close"))
+                .transpose()?,
+            volume: match self.volume {
+                // Some sources write volumes as floats, e.g. `1234.0`.
+                Some(idx) => {
+                    let value = cell(idx, "volume")?;
+                    value
+                        .parse::<i64>()
+                        .or_else(|_| value.parse::<f64>().map(|v| v.round() as i64))
+                        .map_err(|_| invalid(format!("invalid volume `{value}`")))?
+                }
+                None => 0,
+            },
+        };
+
+        validate_bar(&bar).map_err(|reason| invalid(reason.to_string()))?;
+
+        Ok(bar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{test_session_cookie, Role};
+    use crate::conn::test_database;
+    use crate::testing::{insert_stock, insert_user, send_body};
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use axum::Router;
+
+    async fn upload(app: &Router, cookie: &str, csv: &str) -> (StatusCode, serde_json::Value) {
+        let body = format!(
+            "--BOUNDARY\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"acme.csv\"\r\n\
+             Content-Type: text/csv\r\n\r\n\
+             {csv}\r\n\
+             --BOUNDARY--\r\n"
+        );
+        let headers = [
+            ("cookie", cookie),
+            ("content-type", "multipart/form-data; boundary=BOUNDARY"),
+        ];
+        let uri = "/api/stocks/ACME/bars/import";
+        let response = send_body(app, "POST", uri, &headers, Body::from(body)).await;
+        (response.status, response.body)
+    }
+
+    #[tokio::test]
+    async fn imports_all_or_nothing() {
+        let db = test_database().await;
+        insert_stock(&db, "ACME", "XNYS", "Acme Corp").await;
+        insert_user(&db, 1, "root", Role::Admin).await;
+        let admin = test_session_cookie(&db, 1).await;
+        let app = crate::prices::router().with_state(Arc::new(AppState::new(db.clone())));
+        let count = || async {
+            sqlx::query_scalar::<_, i64>("SELECT count(*) FROM price_bar")
+                .fetch_one(&db)
+                .await
+                .unwrap()
+        };
+
+        let valid = "Date,Open,High,Low,Close,Volume\r\n2024-01-02,1,2,0.5,1.5,10\r\n2024-01-03,1.5,2,1,1,1";
+        let (status, _) = upload(&app, "", valid).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(count().await, 0);
+
+        let invalid =
+            "Date,Open,High,Low,Close,Volume\r\n2024-01-02,1,2,0.5,1.5,10\r\n2024-01-03,x,2,1,1,1";
+        let (status, _) = upload(&app, &admin, invalid).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(count().await, 0);
+
+        let (status, _) = upload(&app, &admin, valid).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(count().await, 2);
+
+        // A bad header is reported once, not again for every row.
+        let bad_header = "Date,Open,High,Close\r\n2024-01-04,1,2,1.5\r\n2024-01-05,1,2,1.5";
+        let (status, body) = upload(&app, &admin, bad_header).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            body["errors"]["file"],
+            serde_json::json!(["Missing column `Low`"])
+        );
+    }
+
+    #[test]
+    fn yahoo_and_stooq_headers() {
+        let yahoo = CsvLayout::from_header("Date,Open,High,Low,Close,Adj Close,Volume").unwrap();
+        assert_eq!(yahoo.adj_close, Some(5));
+        assert_eq!(yahoo.volume, Some(6));
+
+        let stooq =
+            CsvLayout::from_header("\"date\",\"open\",\"high\",\"low\",\"close\",\"volume\"")
+                .unwrap();
+        assert_eq!(stooq.adj_close, None);
+        assert_eq!(stooq.volume, Some(5));
+
+        assert!(matches!(
+            CsvLayout::from_header("Date,Open,High,Close"),
+            Err(MultipartError::MissingColumn("Low"))
+        ));
+    }
+
+    #[test]
+    fn parses_and_validates_rows() {
+        let layout = CsvLayout::from_header("Date,Open,High,Low,Close,Adj Close,Volume").unwrap();
+
+        let bar = layout
+            .parse_row(
+                2,
+                "2024-01-02,187.15,188.44,183.89,185.64,184.94,82488700.0",
+            )
+            .unwrap();
+        assert_eq!(bar.date.to_string(), "2024-01-02");
+        assert_eq!(bar.adj_close, Some(184.94));
+        assert_eq!(bar.volume, 82488700);
+
+        let error = layout
+            .parse_row(3, "2024-01-03,null,null,null,null,null,null")
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Line 3: invalid open `null`");
+
+        let error = layout
+            .parse_row(4, "2024-01-04,10,9,8,9.5,9.5,100")
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Line 4: high/low must bound open and close"
+        );
+    }
+}
@@ -0,0 +1,20 @@
+pub mod bus;
+pub mod feed;
+mod handlers;
+mod import;
+pub mod model;
+pub mod resample;
+mod stream;
+
+pub(crate) use handlers::validate_bar;
+pub(crate) use model::NewBar;
+
+use std::sync::Arc;
+
+use axum::Router;
+
+use crate::app::AppState;
+
+pub fn router() -> Router<Arc<AppState>> {
+    handlers::router().merge(stream::router())
+}
@@ -0,0 +1,64 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// One daily OHLCV bar, keyed by `(symbol, date)`.
+#[derive(Debug, Clone, PartialEq, FromRow, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceBar {
+    #[serde(skip_serializing)]
+    pub symbol: String,
+    pub date: NaiveDate,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub adj_close: f64,
+    pub volume: i64,
+}
+
+/// A daily bar to store, as sent by clients; the symbol comes from the path.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewBar {
+    pub date: NaiveDate,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    /// Defaults to `close` for sources that don't adjust for splits and dividends.
+    pub adj_close: Option<f64>,
+    #[serde(default)]
+    pub volume: i64,
+}
+
+/// Bar size requested by clients; stored bars are always daily and resampled on read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Interval {
+    #[default]
+    #[serde(rename = "1d")]
+    Daily,
+    #[serde(rename = "1w")]
+    Weekly,
+    #[serde(rename = "1m")]
+    Monthly,
+}
+
+/// The latest traded price of a symbol during a session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Quote {
+    pub symbol: String,
+    pub price: f64,
+    /// Opening price of the session the quote belongs to.
+    pub open: f64,
+    pub previous_close: Option<f64>,
+    pub at: DateTime<Utc>,
+}
+
+impl Quote {
+    /// The trading session, taken as the UTC calendar day.
+    pub fn session(&self) -> NaiveDate {
+        self.at.date_naive()
+    }
+}
@@ -0,0 +1,105 @@
+use chrono::Datelike;
+
+use super::model::{Interval, PriceBar};
+
+/// Aggregate date-ordered daily bars into bars of the given interval.
+///
+/// Weeks are ISO weeks and months are calendar months. Each aggregated bar is dated with the
+/// first trading day of its period, and takes the first open, the last (adjusted) close, the
+/// extreme high/low and the total volume of the period.
+pub fn resample(daily: Vec<PriceBar>, interval: Interval) -> Vec<PriceBar> {
+    let period: fn(&PriceBar) -> (i32, u32) = match interval {
+        Interval::Daily => return daily,
+        Interval::Weekly => |bar: &PriceBar| {
+            let week = bar.date.iso_week();
+            (week.year(), week.week())
+        },
+        Interval::Monthly => |bar: &PriceBar| (bar.date.year(), bar.date.month()),
+    };
+
+    let mut resampled: Vec<PriceBar> = Vec::new();
+    let mut current_period = None;
+
+    for bar in daily {
+        let bar_period = period(&bar);
+
+        match resampled.last_mut() {
+            Some(last) if current_period == Some(bar_period) => {
+                last.high = last.high.max(bar.high);
+                last.low = last.low.min(bar.low);
+                last.close = bar.close;
+                last.adj_close = bar.adj_close;
+                last.volume += bar.volume;
+            }
+            _ => {
+                current_period = Some(bar_period);
+                resampled.push(bar);
+            }
+        }
+    }
+
+    resampled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn bar(date: &str, open: f64, high: f64, low: f64, close: f64, volume: i64) -> PriceBar {
+        PriceBar {
+            symbol: "TEST".to_string(),
+            date: date.parse::<NaiveDate>().unwrap(),
+            open,
+            high,
+            low,
+            close,
+            adj_close: close,
+            volume,
+        }
+    }
+
+    fn bars() -> Vec<PriceBar> {
+        vec![
+            // Thu/Fri of ISO week 5 spanning the January/February boundary.
+            bar("2024-01-31", 10.0, 12.0, 9.0, 11.0, 100),
+            bar("2024-02-01", 11.0, 13.0, 10.5, 12.5, 200),
+            bar("2024-02-02", 12.5, 12.8, 8.0, 9.0, 300),
+            // Mon of ISO week 6.
+            bar("2024-02-05", 9.0, 9.5, 8.5, 9.2, 50),
+        ]
+    }
+
+    #[test]
+    fn daily_is_passthrough() {
+        assert_eq!(resample(bars(), Interval::Daily), bars());
+    }
+
+    #[test]
+    fn weekly_groups_by_iso_week() {
+        let weekly = resample(bars(), Interval::Weekly);
+
+        assert_eq!(weekly.len(), 2);
+        assert_eq!(weekly[0].date.to_string(), "2024-01-31");
+        assert_eq!(weekly[0].open, 10.0);
+        assert_eq!(weekly[0].high, 13.0);
+        assert_eq!(weekly[0].low, 8.0);
+        assert_eq!(weekly[0].close, 9.0);
+        assert_eq!(weekly[0].volume, 600);
+        assert_eq!(weekly[1], bars()[3]);
+    }
+
+    #[test]
+    fn monthly_groups_by_calendar_month() {
+        let monthly = resample(bars(), Interval::Monthly);
+
+        assert_eq!(monthly.len(), 2);
+        assert_eq!(monthly[0], bars()[0]);
+        assert_eq!(monthly[1].date.to_string(), "2024-02-01");
+        assert_eq!(monthly[1].open, 11.0);
+        assert_eq!(monthly[1].high, 13.0);
+        assert_eq!(monthly[1].low, 8.0);
+        assert_eq!(monthly[1].close, 9.2);
+        assert_eq!(monthly[1].volume, 550);
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::ws::rejection::WebSocketUpgradeRejection;
+use axum::extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use futures::{Stream, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+
+use super::bus::QuoteSubscription;
+use crate::app::AppState;
+use crate::common::cancel::CancellationToken;
+use crate::common::errors::{Error, Result};
+use crate::common::extract::Query;
+use crate::stocks::normalize_code;
+
+const MAX_SYMBOLS: usize = 50;
+
+/// A client not accepting a message for this long is dropped rather than left to lag.
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Close code asking the client to reconnect later, used when it fell behind the feed.
+const TRY_AGAIN_LATER: u16 = 1013;
+
+/// Close code telling the client the server is going away, on shutdown.
+const GOING_AWAY: u16 = 1001;
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/ws/quotes", get(ws_quotes))
+        .route("/sse/quotes", get(sse_quotes))
+}
+
+#[derive(serde::Deserialize)]
+struct StreamQuery {
+    /// Comma separated, e.g. `?symbols=AAPL,MSFT`.
+    symbols: String,
+}
+
+impl StreamQuery {
+    fn subscribe(&self, state: &AppState) -> Result<QuoteSubscription> {
+        let symbols: HashSet<String> = self
+            .symbols
+            .split(',')
+            .map(normalize_code)
+            .filter(|symbol| !symbol.is_empty())
+            .collect();
+
+        if symbols.is_empty() || symbols.len() > MAX_SYMBOLS {
+            return Err(Error::unprocessable_entity([(
+                "symbols",
+                format!("must list 1-{MAX_SYMBOLS} symbols"),
+            )]));
+        }
+
+        Ok(state.quotes.subscribe_to(symbols))
+    }
+}
+
+async fn ws_quotes(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<StreamQuery>,
+    ws: Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
+) -> Result<Response> {
+    let ws = ws?;
+    let subscription = query.subscribe(&state)?;
+
+    let shutdown = state.shutdown.clone();
+    Ok(ws.on_upgrade(|socket| stream_to_socket(socket, subscription, shutdown)))
+}
+
+// Each quote is sent as a JSON text message. Messages from the client are ignored apart from
+// closing the connection.
+async fn stream_to_socket(
+    mut socket: WebSocket,
+    mut subscription: QuoteSubscription,
+    shutdown: CancellationToken,
+) {
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => {
+                let close = CloseFrame {
+                    code: GOING_AWAY,
+                    reason: "server shutting down".into(),
+                };
+                let _ = tokio::time::timeout(SEND_TIMEOUT, socket.send(Message::Close(Some(close))))
+                    .await;
+                return;
+            }
+            quote = subscription.next() => {
+                let quote = match quote {
+                    Ok(quote) => quote,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::debug!("dropping websocket quote subscriber {skipped} quotes behind");
+                        let close = CloseFrame {
+                            code: TRY_AGAIN_LATER,
+                            reason: "too slow, quotes were missed".into(),
+                        };
+                        let _ = tokio::time::timeout(
+                            SEND_TIMEOUT,
+                            socket.send(Message::Close(Some(close))),
+                        )
+                        .await;
+                        return;
+                    }
+                    Err(RecvError::Closed) => return,
+                };
+
+                let text = serde_json::to_string(&quote).expect("quotes serialize to JSON");
+                match tokio::time::timeout(SEND_TIMEOUT, socket.send(Message::Text(text))).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(_)) => return,
+                    Err(_) => {
+                        tracing::debug!("dropping websocket quote subscriber not reading");
+                        return;
+                    }
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+// Each quote is a `quote` event with JSON data. A subscriber falling behind gets a final
+// `lagged` event and the stream ends, EventSource clients then reconnect from the latest values.
+// Streams also end on shutdown, so they don't hold it up.
+async fn sse_quotes(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<StreamQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let subscription = query.subscribe(&state)?;
+
+    let events = futures::stream::unfold(Some(subscription), |subscription| async move {
+        let mut subscription = subscription?;
+        match subscription.next().await {
+            Ok(quote) => {
+                let event = Event::default()
+                    .event("quote")
+                    .json_data(&quote)
+                    .expect("quotes serialize to JSON");
+                Some((Ok(event), Some(subscription)))
+            }
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::debug!("dropping SSE quote subscriber {skipped} quotes behind");
+                let event = Event::default().event("lagged").data(skipped.to_string());
+                Some((Ok(event), None))
+            }
+            Err(RecvError::Closed) => None,
+        }
+    });
+
+    let shutdown = state.shutdown.clone();
+    let events = events.take_until(async move { shutdown.cancelled().await });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conn::test_database;
+    use crate::prices::model::Quote;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    async fn next_event(body: &mut Body) -> String {
+        let frame = body.frame().await.unwrap().unwrap().into_data().unwrap();
+        String::from_utf8(frame.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn sse_sends_latest_then_new_quotes() {
+        let db = test_database().await;
+        let state = Arc::new(AppState::new(db));
+        let app = router().with_state(state.clone());
+
+        let quote = |symbol: &str, price: f64, at: &str| Quote {
+            symbol: symbol.to_string(),
+            price,
+            open: price,
+            previous_close: None,
+            at: at.parse().unwrap(),
+        };
+        state
+            .quotes
+            .publish(quote("AAPL", 1.0, "2024-01-02T15:00:00Z"));
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/sse/quotes?symbols=")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = app
+            .oneshot(
+                Request::get("/sse/quotes?symbols=aapl")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut body = response.into_body();
+
+        assert!(next_event(&mut body).await.contains(r#""price":1.0"#));
+
+        state
+            .quotes
+            .publish(quote("MSFT", 2.0, "2024-01-02T15:01:00Z"));
+        state
+            .quotes
+            .publish(quote("AAPL", 3.0, "2024-01-02T15:01:00Z"));
+        let event = next_event(&mut body).await;
+        assert!(event.starts_with("event: quote"));
+        assert!(event.contains(r#""price":3.0"#));
+
+        state.shutdown.cancel();
+        assert!(body.frame().await.is_none());
+    }
+}
@@ -0,0 +1,334 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::Router;
+use chrono::Utc;
+
+use super::model::{ListingStatus, NewStock, Stock, StockFilter, UpdateStock};
+use crate::app::AppState;
+use crate::auth::{Admin, RequireRole};
+use crate::common::errors::{Error, Result};
+use crate::common::extract::{Json, Path, Query};
+use crate::common::paging::Paging;
+use crate::store::StoreError;
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/stocks", get(list_stocks).post(create_stock))
+        .route(
+            "/api/stocks/:symbol",
+            get(get_stock).put(update_stock).delete(delete_stock),
+        )
+}
+
+/// A wrapper type for all requests/responses from these routes.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct StockBody<T> {
+    stock: T,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MultipleStocksBody {
+    stocks: Vec<Stock>,
+    stocks_count: usize,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(default)]
+struct ListStocksQuery {
+    exchange: Option<String>,
+    sector: Option<String>,
+    status: Option<ListingStatus>,
+    page: Option<usize>,
+    limit: Option<usize>,
+}
+
+async fn list_stocks(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ListStocksQuery>,
+) -> Result<Json<MultipleStocksBody>> {
+    let paging = Paging {
+        page: query.page,
+        limit: query.limit,
+    };
+    let (limit, offset) = paging.limit_offset(50, 500);
+
+    let filter = StockFilter {
+        exchange: query.exchange.as_deref().map(normalize_code),
+        sector: query.sector,
+        status: query.status,
+        limit,
+        offset,
+    };
+    let stocks = state.store.stocks.list_stocks(&filter).await?;
+
+    Ok(Json(MultipleStocksBody {
+        stocks_count: stocks.len(),
+        stocks,
+    }))
+}
+
+async fn get_stock(
+    State(state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+) -> Result<Json<StockBody<Stock>>> {
+    let stock = fetch_stock(&state, &symbol).await?.ok_or(Error::NotFound)?;
+
+    Ok(Json(StockBody { stock }))
+}
+
+async fn create_stock(
+    _admin: RequireRole<Admin>,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<StockBody<NewStock>>,
+) -> Result<(StatusCode, Json<StockBody<Stock>>)> {
+    let new = check_new_stock(req.stock)?;
+
+    let stock = state
+        .store
+        .stocks
+        .create_stock(&new, Utc::now())
+        .await
+        .map_err(|e| match e {
+            StoreError::Conflict(_) => {
+                Error::unprocessable_entity([("symbol", "symbol already listed")])
+            }
+            e => e.into(),
+        })?;
+
+    Ok((StatusCode::CREATED, Json(StockBody { stock })))
+}
+
+// Partial update: only the fields present in the body are changed.
+async fn update_stock(
+    _admin: RequireRole<Admin>,
+    State(state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+    Json(req): Json<StockBody<UpdateStock>>,
+) -> Result<Json<StockBody<Stock>>> {
+    let update = UpdateStock {
+        exchange: req.stock.exchange.as_deref().map(normalize_code),
+        name: req
+            .stock
+            .name
+            .as_deref()
+            .map(|name| name.trim().to_string()),
+        currency: req.stock.currency.as_deref().map(normalize_code),
+        ..req.stock
+    };
+
+    validate_stock(
+        None,
+        update.exchange.as_deref(),
+        update.name.as_deref(),
+        update.currency.as_deref(),
+        update.lot_size,
+    )?;
+
+    let stock = state
+        .store
+        .stocks
+        .update_stock(&normalize_code(&symbol), &update, Utc::now())
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    Ok(Json(StockBody { stock }))
+}
+
+// Deleting also drops the stock's price bars, watchlist entries and alerts. Stocks someone
+// traded stay in their ledgers for good, those can only be delisted.
+async fn delete_stock(
+    _admin: RequireRole<Admin>,
+    State(state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+) -> Result<StatusCode> {
+    let deleted = match state
+        .store
+        .stocks
+        .delete_stock(&normalize_code(&symbol))
+        .await
+    {
+        Err(StoreError::InUse(field)) => {
+            return Err(Error::unprocessable_entity([(
+                field,
+                "has ledger entries, set its status to delisted instead",
+            )]))
+        }
+        deleted => deleted?,
+    };
+    if !deleted {
+        return Err(Error::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub(crate) async fn fetch_stock(state: &AppState, symbol: &str) -> Result<Option<Stock>> {
+    Ok(state.store.stocks.stock(&normalize_code(symbol)).await?)
+}
+
+/// Tickers, exchange MICs and currency codes are stored upper-cased.
+pub(crate) fn normalize_code(code: &str) -> String {
+    code.trim().to_uppercase()
+}
+
+/// `new` with its codes normalized and its name trimmed, once it's valid.
+pub(crate) fn check_new_stock(new: NewStock) -> Result<NewStock> {
+    let new = NewStock {
+        symbol: normalize_code(&new.symbol),
+        exchange: normalize_code(&new.exchange),
+        name: new.name.trim().to_string(),
+        currency: normalize_code(&new.currency),
+        ..new
+    };
+
+    validate_stock(
+        Some(&new.symbol),
+        Some(&new.exchange),
+        Some(&new.name),
+        Some(&new.currency),
+        Some(new.lot_size),
+    )?;
+
+    Ok(new)
+}
+
+fn validate_stock(
+    symbol: Option<&str>,
+    exchange: Option<&str>,
+    name: Option<&str>,
+    currency: Option<&str>,
+    lot_size: Option<i64>,
+) -> Result<()> {
+    let mut errors = Vec::new();
+
+    if let Some(symbol) = symbol {
+        let valid = !symbol.is_empty()
+            && symbol.len() <= 16
+            && symbol
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+        if !valid {
+            errors.push(("symbol", "must be 1-16 letters, digits, '.' or '-'"));
+        }
+    }
+    if exchange.is_some_and(str::is_empty) {
+        errors.push(("exchange", "can't be blank"));
+    }
+    if name.is_some_and(|name| name.trim().is_empty()) {
+        errors.push(("name", "can't be blank"));
+    }
+    if let Some(currency) = currency {
+        if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+            errors.push(("currency", "must be a 3-letter ISO 4217 code"));
+        }
+    }
+    if lot_size.is_some_and(|lot_size| lot_size < 1) {
+        errors.push(("lotSize", "must be at least 1"));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::unprocessable_entity(errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{test_session_cookie, Role};
+    use crate::conn::test_database;
+    use crate::testing::{insert_portfolio, insert_stock, insert_user, send};
+
+    /// The app, with the session cookie of an admin and of a plain user.
+    async fn app() -> (Router, String, String) {
+        let db = test_database().await;
+        insert_user(&db, 1, "root", Role::Admin).await;
+        insert_user(&db, 2, "alice", Role::User).await;
+        let admin = test_session_cookie(&db, 1).await;
+        let user = test_session_cookie(&db, 2).await;
+        (
+            router().with_state(Arc::new(AppState::new(db))),
+            admin,
+            user,
+        )
+    }
+
+    #[tokio::test]
+    async fn create_get_update_delete() {
+        let (app, admin, user) = app().await;
+        let aapl = serde_json::json!({"stock": {
+            "symbol": "aapl", "exchange": "xnas", "name": "Apple Inc.", "currency": "usd"
+        }});
+
+        // Only admins edit the catalog.
+        let (status, _) = send(&app, "POST", "/api/stocks", &user, Some(aapl.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, body) = send(&app, "POST", "/api/stocks", &admin, Some(aapl.clone())).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["stock"]["symbol"], "AAPL");
+        assert_eq!(body["stock"]["status"], "listed");
+
+        let (status, _) = send(&app, "POST", "/api/stocks", &admin, Some(aapl)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let update = serde_json::json!({"stock": {"sector": "Technology", "status": "suspended"}});
+        let (_, body) = send(&app, "PUT", "/api/stocks/AAPL", &admin, Some(update)).await;
+        assert_eq!(body["stock"]["sector"], "Technology");
+        assert_eq!(body["stock"]["status"], "suspended");
+        assert_eq!(body["stock"]["name"], "Apple Inc.");
+
+        let (_, body) = send(&app, "GET", "/api/stocks?status=suspended", "", None).await;
+        assert_eq!(body["stocksCount"], 1);
+
+        let (status, _) = send(&app, "DELETE", "/api/stocks/aapl", &admin, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let (status, _) = send(&app, "GET", "/api/stocks/AAPL", "", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn traded_stocks_are_only_delisted() {
+        let db = test_database().await;
+        insert_user(&db, 1, "root", Role::Admin).await;
+        insert_stock(&db, "ACME", "XNYS", "Acme Corp").await;
+        insert_portfolio(&db, 1, 1, "ISA").await;
+        sqlx::query(
+            "INSERT INTO \"transaction\" (portfolio_id, kind, symbol, trade_date, quantity, price, created_at)
+             VALUES (1, 'buy', 'ACME', '2024-01-02', 10, 5, '2024-01-02T00:00:00Z')",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        let admin = test_session_cookie(&db, 1).await;
+        let app = router().with_state(Arc::new(AppState::new(db)));
+
+        let (status, body) = send(&app, "DELETE", "/api/stocks/ACME", &admin, None).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body["errors"]["symbol"].is_array());
+
+        let (status, _) = send(&app, "GET", "/api/stocks/ACME", "", None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_fields() {
+        let (app, admin, _) = app().await;
+        let invalid = serde_json::json!({"stock": {
+            "symbol": "", "exchange": "XLON", "name": " ", "currency": "pounds", "lotSize": 0
+        }});
+        let (status, body) = send(&app, "POST", "/api/stocks", &admin, Some(invalid)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let errors = &body["errors"];
+        for field in ["symbol", "name", "currency", "lotSize"] {
+            assert!(errors.get(field).is_some(), "missing error for {field}");
+        }
+    }
+}
@@ -0,0 +1,5 @@
+mod handlers;
+pub mod model;
+
+pub use handlers::router;
+pub(crate) use handlers::{check_new_stock, fetch_stock, normalize_code};
@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Whether an instrument can currently be traded on its exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ListingStatus {
+    #[default]
+    Listed,
+    Suspended,
+    Delisted,
+}
+
+/// An instrument in the catalog, keyed by its ticker symbol.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stock {
+    pub symbol: String,
+    pub exchange: String,
+    pub name: String,
+    pub currency: String,
+    pub sector: Option<String>,
+    pub lot_size: i64,
+    pub status: ListingStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An instrument to list, its codes normalized.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewStock {
+    pub symbol: String,
+    pub exchange: String,
+    pub name: String,
+    pub currency: String,
+    pub sector: Option<String>,
+    #[serde(default = "default_lot_size")]
+    pub lot_size: i64,
+    #[serde(default)]
+    pub status: ListingStatus,
+}
+
+fn default_lot_size() -> i64 {
+    1
+}
+
+/// Changes to an instrument, `None` leaving a field as is.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UpdateStock {
+    pub exchange: Option<String>,
+    pub name: Option<String>,
+    pub currency: Option<String>,
+    pub sector: Option<String>,
+    pub lot_size: Option<i64>,
+    pub status: Option<ListingStatus>,
+}
+
+/// Which page of which stocks to list, ordered by symbol.
+#[derive(Debug, Clone, Default)]
+pub struct StockFilter {
+    pub exchange: Option<String>,
+    pub sector: Option<String>,
+    pub status: Option<ListingStatus>,
+    pub limit: i64,
+    pub offset: i64,
+}
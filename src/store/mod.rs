@@ -0,0 +1,706 @@
+mod postgres;
+mod sqlite;
+
+#[cfg(test)]
+mod conformance;
+
+pub use postgres::PgStore;
+pub use sqlite::SqliteStore;
+
+use std::sync::Arc;
+
+use axum::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{PgPool, SqlitePool};
+
+use crate::admin::model::{ManagedUser, UpdateUser, UserFilter};
+use crate::alerts::model::{AlertEvent, AlertRule, NewAlert};
+use crate::auth::model::{ApiKey, Role, Scopes, Session, User};
+use crate::common::errors::{violates_constraint, Error};
+use crate::jobs::JobStatus;
+use crate::portfolios::ledger::Oversold;
+use crate::portfolios::model::{
+    NewPortfolio, NewTransaction, Portfolio, Transaction, UpdatePortfolio,
+};
+use crate::prices::model::{NewBar, PriceBar};
+use crate::stocks::model::{NewStock, Stock, StockFilter, UpdateStock};
+use crate::watchlists::model::{Watchlist, WatchlistEntry};
+
+/// Storage of accounts and credentials.
+#[async_trait]
+pub trait UserRepo: Send + Sync {
+    /// `StoreError::Conflict` with `username` or `email` when either is taken.
+    async fn create_user(&self, new: &Registration, now: DateTime<Utc>) -> StoreResult<User>;
+
+    async fn user(&self, user_id: i64) -> StoreResult<Option<User>>;
+
+    /// The credentials of the user with `email`, compared case-insensitively.
+    async fn credentials(&self, email: &str) -> StoreResult<Option<Credentials>>;
+
+    /// The user with `email`, compared case-insensitively.
+    async fn user_by_email(&self, email: &str) -> StoreResult<Option<User>>;
+
+    /// The role of `user_id`, `None` when there's no such user or they're suspended.
+    async fn active_role(&self, user_id: i64) -> StoreResult<Option<Role>>;
+
+    async fn password_hash(&self, user_id: i64) -> StoreResult<Option<String>>;
+
+    /// Replace the password of `user_id`, logging them out of every session but `keep_session`
+    /// and revoking their refresh tokens in the same database transaction.
+    async fn set_password(
+        &self,
+        user_id: i64,
+        password_hash: &str,
+        keep_session: Option<i64>,
+        now: DateTime<Utc>,
+    ) -> StoreResult<()>;
+
+    /// Mark `email` verified, if it's still the address of `user_id`.
+    async fn verify_email(&self, user_id: i64, email: &str, now: DateTime<Utc>) -> StoreResult<()>;
+
+    async fn list_users(&self, filter: &UserFilter) -> StoreResult<Vec<ManagedUser>>;
+
+    async fn managed_user(&self, user_id: i64) -> StoreResult<Option<ManagedUser>>;
+
+    /// Whether there was such a user. Changing their role or suspending them logs them out
+    /// everywhere, in the same database transaction.
+    async fn update_user(
+        &self,
+        user_id: i64,
+        update: &UpdateUser,
+        now: DateTime<Utc>,
+    ) -> StoreResult<bool>;
+
+    /// Whether there was such a user, deleted along with everything they own.
+    async fn delete_user(&self, user_id: i64) -> StoreResult<bool>;
+}
+
+/// Storage of logins: browser sessions, API clients' refresh tokens and redeemed email tokens.
+/// Tokens are only ever stored hashed.
+#[async_trait]
+pub trait SessionRepo: Send + Sync {
+    async fn create_session(
+        &self,
+        token_hash: &[u8],
+        user_id: i64,
+        user_agent: Option<&str>,
+        now: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> StoreResult<()>;
+
+    /// The session of `token_hash`, unless it expired by `now`.
+    async fn session(&self, token_hash: &[u8], now: DateTime<Utc>) -> StoreResult<Option<Session>>;
+
+    /// Unexpired sessions of `user_id`, most recently used first.
+    async fn list_sessions(&self, user_id: i64, now: DateTime<Utc>) -> StoreResult<Vec<Session>>;
+
+    async fn touch_session(
+        &self,
+        session_id: i64,
+        last_seen_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> StoreResult<()>;
+
+    /// Replace the token of a session.
+    async fn set_session_token(&self, session_id: i64, token_hash: &[u8]) -> StoreResult<()>;
+
+    async fn delete_session_by_token(&self, token_hash: &[u8]) -> StoreResult<()>;
+
+    /// Whether the user had such a session.
+    async fn delete_session(&self, user_id: i64, session_id: i64) -> StoreResult<bool>;
+
+    /// Log `user_id` out of every session but `keep`, and revoke all their refresh tokens, in
+    /// one database transaction.
+    async fn log_out_everywhere(
+        &self,
+        user_id: i64,
+        keep: Option<i64>,
+        now: DateTime<Utc>,
+    ) -> StoreResult<()>;
+
+    /// Delete sessions expired by `now`, returning how many.
+    async fn prune_sessions(&self, now: DateTime<Utc>) -> StoreResult<u64>;
+
+    async fn create_refresh_token(
+        &self,
+        token_hash: &[u8],
+        family: &str,
+        user_id: i64,
+        now: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> StoreResult<()>;
+
+    async fn refresh_token(&self, token_hash: &[u8]) -> StoreResult<Option<RefreshToken>>;
+
+    /// Mark a refresh token used and store `token_hash` as the next of its family, in one
+    /// database transaction that also checks its user is still active.
+    async fn rotate_refresh_token(
+        &self,
+        token: &RefreshToken,
+        token_hash: &[u8],
+        now: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> StoreResult<Rotation>;
+
+    async fn revoke_token_family(&self, family: &str, now: DateTime<Utc>) -> StoreResult<()>;
+
+    /// Delete refresh tokens expired by `now`, returning how many.
+    async fn prune_refresh_tokens(&self, now: DateTime<Utc>) -> StoreResult<u64>;
+
+    /// Record the email token `jti` as redeemed, `false` if it already was.
+    async fn use_email_token(&self, jti: &str, expires_at: DateTime<Utc>) -> StoreResult<bool>;
+
+    /// Forget redeemed email tokens expired by `now`, returning how many.
+    async fn prune_email_tokens(&self, now: DateTime<Utc>) -> StoreResult<u64>;
+}
+
+/// Storage of second factors: authenticators, recovery codes and the login challenges asking
+/// for either.
+#[async_trait]
+pub trait TwoFactorRepo: Send + Sync {
+    /// The authenticator of `user_id`, whether confirmed or still being enrolled.
+    async fn totp(&self, user_id: i64) -> StoreResult<Option<Totp>>;
+
+    /// Start enrolling an authenticator with `secret`, replacing an unconfirmed one.
+    async fn enroll_totp(&self, user_id: i64, secret: &[u8], now: DateTime<Utc>)
+        -> StoreResult<()>;
+
+    /// Confirm the authenticator with a code of `step`, replacing the user's recovery codes
+    /// with `recovery_codes` in the same database transaction.
+    async fn enable_totp(
+        &self,
+        user_id: i64,
+        step: i64,
+        recovery_codes: &[Vec<u8>],
+        now: DateTime<Utc>,
+    ) -> StoreResult<()>;
+
+    /// Remove the authenticator and recovery codes of `user_id`.
+    async fn disable_totp(&self, user_id: i64) -> StoreResult<()>;
+
+    /// Accept a code of `step`, `false` when one of it or a later step was accepted already.
+    async fn use_totp_step(&self, user_id: i64, step: i64) -> StoreResult<bool>;
+
+    /// Count a wrong code entered from a session, returning the count so far. `None` without
+    /// an enabled authenticator, or once `max_attempts` is reached.
+    async fn count_failed_code(&self, user_id: i64, max_attempts: i64) -> StoreResult<Option<i64>>;
+
+    async fn reset_failed_codes(&self, user_id: i64) -> StoreResult<()>;
+
+    /// Replace the recovery codes of `user_id` with ones of the hashes `recovery_codes`.
+    async fn replace_recovery_codes(
+        &self,
+        user_id: i64,
+        recovery_codes: &[Vec<u8>],
+    ) -> StoreResult<()>;
+
+    /// Use up the unused recovery code of `code_hash`, `false` if there's none.
+    async fn use_recovery_code(
+        &self,
+        user_id: i64,
+        code_hash: &[u8],
+        now: DateTime<Utc>,
+    ) -> StoreResult<bool>;
+
+    async fn create_challenge(
+        &self,
+        token_hash: &[u8],
+        user_id: i64,
+        expires_at: DateTime<Utc>,
+    ) -> StoreResult<()>;
+
+    /// Count an answer to the challenge of `token_hash`, returning the challenge's id and user.
+    /// `None` once it expired or ran out of `max_attempts`.
+    async fn attempt_challenge(
+        &self,
+        token_hash: &[u8],
+        now: DateTime<Utc>,
+        max_attempts: i64,
+    ) -> StoreResult<Option<(i64, i64)>>;
+
+    async fn delete_challenge(&self, login_challenge_id: i64) -> StoreResult<()>;
+
+    /// Delete challenges expired by `now`, returning how many.
+    async fn prune_challenges(&self, now: DateTime<Utc>) -> StoreResult<u64>;
+}
+
+/// Storage of users' API keys, stored hashed.
+#[async_trait]
+pub trait ApiKeyRepo: Send + Sync {
+    async fn list_api_keys(&self, user_id: i64) -> StoreResult<Vec<ApiKey>>;
+
+    async fn create_api_key(
+        &self,
+        user_id: i64,
+        name: &str,
+        prefix: &str,
+        token_hash: &[u8],
+        scopes: &Scopes,
+        now: DateTime<Utc>,
+    ) -> StoreResult<ApiKey>;
+
+    /// Whether the user had such a key.
+    async fn delete_api_key(&self, user_id: i64, api_key_id: i64) -> StoreResult<bool>;
+
+    /// The owner of the key of `token_hash`, unless they're suspended.
+    async fn api_key_owner(&self, token_hash: &[u8]) -> StoreResult<Option<ApiKeyOwner>>;
+
+    /// Count a request made with the key of `token_hash`.
+    async fn count_api_key_use(&self, token_hash: &[u8], now: DateTime<Utc>) -> StoreResult<()>;
+}
+
+/// Storage of daily price bars, by normalized symbol.
+#[async_trait]
+pub trait PriceRepo: Send + Sync {
+    /// The bars between `from` and `to` inclusive, either unbounded when unset, by date.
+    async fn bars(
+        &self,
+        symbol: &str,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> StoreResult<Vec<PriceBar>>;
+
+    /// The last `count` bars, latest first.
+    async fn latest_bars(&self, symbol: &str, count: i64) -> StoreResult<Vec<PriceBar>>;
+
+    /// The unadjusted close of the last bar on or before `as_of`, or the last bar stored at all.
+    async fn latest_close(
+        &self,
+        symbol: &str,
+        as_of: Option<NaiveDate>,
+    ) -> StoreResult<Option<f64>>;
+
+    /// Every close up to `to` of the symbols in a portfolio's ledger, as `(symbol, date, close)`.
+    async fn portfolio_closes(
+        &self,
+        portfolio_id: i64,
+        to: NaiveDate,
+    ) -> StoreResult<Vec<(String, NaiveDate, f64)>>;
+
+    /// Start writing bars of `symbol` in one database transaction.
+    async fn write_bars(&self, symbol: &str) -> StoreResult<Box<dyn BarWriter>>;
+}
+
+/// Bars of one symbol being written in a database transaction, rolled back unless committed.
+#[async_trait]
+pub trait BarWriter: Send {
+    /// Insert a bar, overwriting the stored one of the same date.
+    async fn upsert(&mut self, bar: &NewBar) -> StoreResult<()>;
+
+    async fn commit(self: Box<Self>) -> StoreResult<()>;
+}
+
+/// Storage of watchlists, always scoped to the user owning them, and of their entries.
+#[async_trait]
+pub trait WatchlistRepo: Send + Sync {
+    /// Ordered by name.
+    async fn list_watchlists(
+        &self,
+        user_id: i64,
+        limit: i64,
+        offset: i64,
+    ) -> StoreResult<Vec<Watchlist>>;
+
+    async fn watchlist(&self, user_id: i64, watchlist_id: i64) -> StoreResult<Option<Watchlist>>;
+
+    /// `StoreError::Conflict` with `name` when the user already has a watchlist of that name.
+    async fn create_watchlist(
+        &self,
+        user_id: i64,
+        name: &str,
+        now: DateTime<Utc>,
+    ) -> StoreResult<Watchlist>;
+
+    /// `None` when the user has no such watchlist, `StoreError::Conflict` as when creating one.
+    async fn rename_watchlist(
+        &self,
+        user_id: i64,
+        watchlist_id: i64,
+        name: &str,
+        now: DateTime<Utc>,
+    ) -> StoreResult<Option<Watchlist>>;
+
+    /// Whether the user had such a watchlist, deleted along with its entries.
+    async fn delete_watchlist(&self, user_id: i64, watchlist_id: i64) -> StoreResult<bool>;
+
+    /// The entries of a watchlist in the user's order.
+    async fn entries(&self, watchlist_id: i64) -> StoreResult<Vec<WatchlistEntry>>;
+
+    /// Add `symbol` at `position`, see `watchlists::model::place`. `StoreError::Conflict` with
+    /// `symbol` when it's on the watchlist already.
+    async fn add_entry(
+        &self,
+        watchlist_id: i64,
+        symbol: &str,
+        note: Option<&str>,
+        position: Option<usize>,
+        now: DateTime<Utc>,
+    ) -> StoreResult<WatchlistEntry>;
+
+    /// Change the note of an entry and move it to `position`, either left as is when unset.
+    /// `None` when the symbol isn't on the watchlist.
+    async fn update_entry(
+        &self,
+        watchlist_id: i64,
+        symbol: &str,
+        note: Option<&str>,
+        position: Option<usize>,
+    ) -> StoreResult<Option<WatchlistEntry>>;
+
+    /// Whether the symbol was on the watchlist.
+    async fn remove_entry(&self, watchlist_id: i64, symbol: &str) -> StoreResult<bool>;
+
+    /// Put the entries in the order of `symbols`, returning them. `None` unless `symbols` lists
+    /// every symbol on the watchlist once, see `watchlists::model::is_reordering`.
+    async fn reorder_entries(
+        &self,
+        watchlist_id: i64,
+        symbols: &[String],
+    ) -> StoreResult<Option<Vec<WatchlistEntry>>>;
+}
+
+/// Storage of alert rules, scoped to the user owning them, and the events of rules firing.
+#[async_trait]
+pub trait AlertRepo: Send + Sync {
+    /// Ordered by symbol.
+    async fn list_alerts(&self, user_id: i64) -> StoreResult<Vec<AlertRule>>;
+
+    async fn create_alert(
+        &self,
+        user_id: i64,
+        new: &NewAlert,
+        now: DateTime<Utc>,
+    ) -> StoreResult<AlertRule>;
+
+    /// Whether the user had such a rule.
+    async fn delete_alert(&self, user_id: i64, alert_rule_id: i64) -> StoreResult<bool>;
+
+    /// Events of the user's rules, most recent first.
+    async fn list_events(
+        &self,
+        user_id: i64,
+        limit: i64,
+        offset: i64,
+    ) -> StoreResult<Vec<AlertEvent>>;
+
+    /// Every user's rules on `symbol`.
+    async fn rules_for(&self, symbol: &str) -> StoreResult<Vec<AlertRule>>;
+
+    /// Record a rule firing, `false` when it already fired in `session`.
+    async fn record_event(
+        &self,
+        alert_rule_id: i64,
+        session: NaiveDate,
+        price: f64,
+        now: DateTime<Utc>,
+    ) -> StoreResult<bool>;
+
+    /// The symbols with any rules on them.
+    async fn alerted_symbols(&self) -> StoreResult<Vec<String>>;
+}
+
+/// Storage of the attempts at running scheduled jobs.
+#[async_trait]
+pub trait JobRepo: Send + Sync {
+    /// Record an attempt as running, returning its id.
+    async fn start_job_run(
+        &self,
+        job_name: &str,
+        scheduled_for: DateTime<Utc>,
+        attempt: i64,
+        now: DateTime<Utc>,
+    ) -> StoreResult<i64>;
+
+    async fn finish_job_run(
+        &self,
+        job_run_id: i64,
+        status: JobStatus,
+        error: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> StoreResult<()>;
+
+    /// Delete runs finished before `before`, returning how many.
+    async fn prune_job_runs(&self, before: DateTime<Utc>) -> StoreResult<u64>;
+}
+
+/// Storage of the stock catalog. Symbols are expected normalized, see `stocks::normalize_code`.
+#[async_trait]
+pub trait StockRepo: Send + Sync {
+    async fn list_stocks(&self, filter: &StockFilter) -> StoreResult<Vec<Stock>>;
+
+    async fn stock(&self, symbol: &str) -> StoreResult<Option<Stock>>;
+
+    /// The symbols of every listed stock, ordered.
+    async fn listed_symbols(&self) -> StoreResult<Vec<String>>;
+
+    /// `StoreError::Conflict` with `symbol` when it's already listed.
+    async fn create_stock(&self, new: &NewStock, now: DateTime<Utc>) -> StoreResult<Stock>;
+
+    /// `None` when there's no such stock.
+    async fn update_stock(
+        &self,
+        symbol: &str,
+        update: &UpdateStock,
+        now: DateTime<Utc>,
+    ) -> StoreResult<Option<Stock>>;
+
+    /// Whether there was such a stock. Its price bars, watchlist entries and alerts go with it,
+    /// but a stock in a ledger can only be delisted: `StoreError::InUse` with `symbol`.
+    async fn delete_stock(&self, symbol: &str) -> StoreResult<bool>;
+}
+
+/// Storage of portfolios, always scoped to the user owning them.
+#[async_trait]
+pub trait PortfolioRepo: Send + Sync {
+    /// Ordered by name.
+    async fn list_portfolios(&self, user_id: i64) -> StoreResult<Vec<Portfolio>>;
+
+    async fn portfolio(&self, user_id: i64, portfolio_id: i64) -> StoreResult<Option<Portfolio>>;
+
+    /// `StoreError::Conflict` with `name` when the user already has a portfolio of that name.
+    async fn create_portfolio(
+        &self,
+        user_id: i64,
+        new: &NewPortfolio,
+        now: DateTime<Utc>,
+    ) -> StoreResult<Portfolio>;
+
+    /// Create a portfolio along with its ledger, booked in order in one database transaction:
+    /// nothing is created when `name` is taken or an entry sells more than was held.
+    async fn create_portfolio_with_ledger(
+        &self,
+        user_id: i64,
+        new: &NewPortfolio,
+        ledger: &[NewTransaction],
+        now: DateTime<Utc>,
+    ) -> StoreResult<Result<Portfolio, Oversold>>;
+
+    /// `None` when the user has no such portfolio.
+    async fn update_portfolio(
+        &self,
+        user_id: i64,
+        portfolio_id: i64,
+        update: &UpdatePortfolio,
+        now: DateTime<Utc>,
+    ) -> StoreResult<Option<Portfolio>>;
+
+    /// Whether the user had such a portfolio, deleted along with its ledger.
+    async fn delete_portfolio(&self, user_id: i64, portfolio_id: i64) -> StoreResult<bool>;
+}
+
+/// Storage of portfolio ledgers, which are append-only.
+#[async_trait]
+pub trait TransactionRepo: Send + Sync {
+    /// A portfolio's ledger in replay order: by trade date, then booking order.
+    async fn ledger(&self, portfolio_id: i64) -> StoreResult<Vec<Transaction>>;
+
+    /// Append `new` to the ledger of `portfolio_id`, unless it's a sell of more than was held
+    /// on its trade date.
+    ///
+    /// The check and the insert happen in one database transaction, so concurrent sells can't
+    /// both pass it.
+    async fn book_transaction(
+        &self,
+        portfolio_id: i64,
+        new: &NewTransaction,
+        now: DateTime<Utc>,
+    ) -> StoreResult<Result<Transaction, Oversold>>;
+}
+
+/// A new account, its password already hashed.
+#[derive(Debug, Clone)]
+pub struct Registration {
+    pub username: String,
+    pub email: String,
+    pub password_hash: String,
+}
+
+/// What logging in checks.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Credentials {
+    pub user_id: i64,
+    pub password_hash: String,
+    pub suspended: bool,
+}
+
+/// What refreshing API client tokens checks.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct RefreshToken {
+    pub refresh_token_id: i64,
+    pub family: String,
+    pub user_id: i64,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// How rotating a refresh token went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    /// The next token is stored, for a user of this role.
+    Rotated(Role),
+    /// The token was used or revoked already.
+    Reused,
+    /// Its user was deleted or suspended since, nothing is stored.
+    Inactive,
+}
+
+/// What checking codes of an authenticator app needs.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Totp {
+    pub secret: Vec<u8>,
+    /// Unset until the first code confirmed the enrollment.
+    pub enabled_at: Option<DateTime<Utc>>,
+    pub last_used_step: Option<i64>,
+}
+
+/// Whom a request with an API key is made by.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ApiKeyOwner {
+    pub user_id: i64,
+    pub role: Role,
+    /// As stored, see `Scopes`.
+    pub scopes: String,
+}
+
+pub type StoreResult<T> = Result<T, StoreError>;
+
+#[derive(thiserror::Error, Debug)]
+pub enum StoreError {
+    /// A unique field's value is already taken, e.g. a listed symbol.
+    #[error("{0} already taken")]
+    Conflict(&'static str),
+
+    /// Other rows still reference this one, e.g. ledger entries referencing a stock.
+    #[error("{0} still in use")]
+    InUse(&'static str),
+
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+/// Conflicts not handled by the caller are still a validation error of that field.
+impl From<StoreError> for Error {
+    fn from(error: StoreError) -> Self {
+        match error {
+            StoreError::Conflict(field) => Error::unprocessable_entity([(field, "already taken")]),
+            StoreError::InUse(field) => Error::unprocessable_entity([(field, "still in use")]),
+            StoreError::Database(error) => Error::Sqlx(error),
+        }
+    }
+}
+
+impl StoreError {
+    /// `error` as a conflict on the field of the unique constraint it violates, if any of
+    /// `constraints`, given as `(constraint, field)`.
+    ///
+    /// Postgres reports constraint names, SQLite only the offending columns, e.g.
+    /// `UNIQUE constraint failed: stock.symbol`, so each backend passes its own.
+    fn on_conflict(error: sqlx::Error, constraints: &[(&str, &'static str)]) -> Self {
+        if let sqlx::Error::Database(dbe) = &error {
+            if let Some((_, field)) = constraints
+                .iter()
+                .find(|(constraint, _)| violates_constraint(&**dbe, constraint))
+            {
+                return StoreError::Conflict(field);
+            }
+        }
+        StoreError::Database(error)
+    }
+
+    /// `error` as the row of `field` being in use if it violates a foreign key.
+    fn on_in_use(error: sqlx::Error, field: &'static str) -> Self {
+        match &error {
+            sqlx::Error::Database(dbe) if dbe.is_foreign_key_violation() => {
+                StoreError::InUse(field)
+            }
+            _ => StoreError::Database(error),
+        }
+    }
+}
+
+/// The repositories the app stores all its data with, on SQLite or Postgres.
+#[derive(Clone)]
+pub struct Store {
+    pub users: Arc<dyn UserRepo>,
+    pub stocks: Arc<dyn StockRepo>,
+    pub portfolios: Arc<dyn PortfolioRepo>,
+    pub transactions: Arc<dyn TransactionRepo>,
+    pub sessions: Arc<dyn SessionRepo>,
+    pub two_factor: Arc<dyn TwoFactorRepo>,
+    pub api_keys: Arc<dyn ApiKeyRepo>,
+    pub prices: Arc<dyn PriceRepo>,
+    pub watchlists: Arc<dyn WatchlistRepo>,
+    pub alerts: Arc<dyn AlertRepo>,
+    pub jobs: Arc<dyn JobRepo>,
+}
+
+/// Which database a URL points to, by its scheme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Sqlite,
+    Postgres,
+}
+
+impl Backend {
+    pub fn of(url: &str) -> Option<Self> {
+        let (scheme, _) = url.split_once(':')?;
+        match scheme {
+            "sqlite" => Some(Self::Sqlite),
+            "postgres" | "postgresql" => Some(Self::Postgres),
+            _ => None,
+        }
+    }
+}
+
+impl Store {
+    pub fn sqlite(pool: SqlitePool) -> Self {
+        Self::from_repos(Arc::new(SqliteStore::new(pool)))
+    }
+
+    pub fn postgres(pool: PgPool) -> Self {
+        Self::from_repos(Arc::new(PgStore::new(pool)))
+    }
+
+    fn from_repos<R>(repos: Arc<R>) -> Self
+    where
+        R: UserRepo
+            + StockRepo
+            + PortfolioRepo
+            + TransactionRepo
+            + SessionRepo
+            + TwoFactorRepo
+            + ApiKeyRepo
+            + PriceRepo
+            + WatchlistRepo
+            + AlertRepo
+            + JobRepo
+            + 'static,
+    {
+        Self {
+            users: repos.clone(),
+            stocks: repos.clone(),
+            portfolios: repos.clone(),
+            transactions: repos.clone(),
+            sessions: repos.clone(),
+            two_factor: repos.clone(),
+            api_keys: repos.clone(),
+            prices: repos.clone(),
+            watchlists: repos.clone(),
+            alerts: repos.clone(),
+            jobs: repos,
+        }
+    }
+}
+
+impl From<SqlitePool> for Store {
+    fn from(pool: SqlitePool) -> Self {
+        Self::sqlite(pool)
+    }
+}
+
+impl From<PgPool> for Store {
+    fn from(pool: PgPool) -> Self {
+        Self::postgres(pool)
+    }
+}
@@ -0,0 +1,488 @@
+mod alerts;
+mod auth;
+mod jobs;
+mod prices;
+mod watchlists;
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+
+use self::auth::log_out_everywhere;
+use super::{
+    Credentials, PortfolioRepo, Registration, StockRepo, StoreError, StoreResult, TransactionRepo,
+    UserRepo,
+};
+use crate::admin::model::{ManagedUser, UpdateUser, UserFilter};
+use crate::auth::model::{Role, User};
+use crate::portfolios::ledger::{check_booking, Oversold};
+use crate::portfolios::model::{
+    NewPortfolio, NewTransaction, Portfolio, Transaction, UpdatePortfolio,
+};
+use crate::stocks::model::{NewStock, Stock, StockFilter, UpdateStock};
+
+/// The repositories on Postgres, with the schema of `migrations_postgres`.
+pub struct PgStore {
+    db: PgPool,
+}
+
+impl PgStore {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+}
+
+const USER_COLUMNS: &str = "user_id, username, email, role, created_at, email_verified_at";
+
+#[async_trait]
+impl UserRepo for PgStore {
+    async fn create_user(&self, new: &Registration, now: DateTime<Utc>) -> StoreResult<User> {
+        let query = format!(
+            r#"INSERT INTO "user" (username, email, password_hash, created_at)
+             VALUES ($1, $2, $3, $4)
+             RETURNING {USER_COLUMNS}"#
+        );
+
+        sqlx::query_as(&query)
+            .bind(&new.username)
+            .bind(&new.email)
+            .bind(&new.password_hash)
+            .bind(now)
+            .fetch_one(&self.db)
+            .await
+            .map_err(|e| {
+                StoreError::on_conflict(
+                    e,
+                    &[
+                        ("user_username_key", "username"),
+                        ("user_username_lower_key", "username"),
+                        ("user_email_key", "email"),
+                        ("user_email_lower_key", "email"),
+                    ],
+                )
+            })
+    }
+
+    async fn user(&self, user_id: i64) -> StoreResult<Option<User>> {
+        let query = format!(r#"SELECT {USER_COLUMNS} FROM "user" WHERE user_id = $1"#);
+        Ok(sqlx::query_as(&query)
+            .bind(user_id)
+            .fetch_optional(&self.db)
+            .await?)
+    }
+
+    async fn credentials(&self, email: &str) -> StoreResult<Option<Credentials>> {
+        const QUERY: &str = r#"SELECT user_id, password_hash, suspended_at IS NOT NULL AS suspended
+             FROM "user" WHERE lower(email) = lower($1)"#;
+
+        Ok(sqlx::query_as(QUERY)
+            .bind(email)
+            .fetch_optional(&self.db)
+            .await?)
+    }
+
+    async fn user_by_email(&self, email: &str) -> StoreResult<Option<User>> {
+        let query = format!(r#"SELECT {USER_COLUMNS} FROM "user" WHERE lower(email) = lower($1)"#);
+        Ok(sqlx::query_as(&query)
+            .bind(email)
+            .fetch_optional(&self.db)
+            .await?)
+    }
+
+    async fn active_role(&self, user_id: i64) -> StoreResult<Option<Role>> {
+        Ok(sqlx::query_scalar(
+            r#"SELECT role FROM "user" WHERE user_id = $1 AND suspended_at IS NULL"#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.db)
+        .await?)
+    }
+
+    async fn password_hash(&self, user_id: i64) -> StoreResult<Option<String>> {
+        Ok(
+            sqlx::query_scalar(r#"SELECT password_hash FROM "user" WHERE user_id = $1"#)
+                .bind(user_id)
+                .fetch_optional(&self.db)
+                .await?,
+        )
+    }
+
+    async fn set_password(
+        &self,
+        user_id: i64,
+        password_hash: &str,
+        keep_session: Option<i64>,
+        now: DateTime<Utc>,
+    ) -> StoreResult<()> {
+        let mut tx = self.db.begin().await?;
+        sqlx::query(r#"UPDATE "user" SET password_hash = $1 WHERE user_id = $2"#)
+            .bind(password_hash)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        log_out_everywhere(&mut tx, user_id, keep_session, now).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn verify_email(&self, user_id: i64, email: &str, now: DateTime<Utc>) -> StoreResult<()> {
+        sqlx::query(
+            r#"UPDATE "user" SET email_verified_at = coalesce(email_verified_at, $1)
+             WHERE user_id = $2 AND email = $3"#,
+        )
+        .bind(now)
+        .bind(user_id)
+        .bind(email)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_users(&self, filter: &UserFilter) -> StoreResult<Vec<ManagedUser>> {
+        let query = format!(
+            "{MANAGED_USER}
+             WHERE ($1::text IS NULL OR role = $1)
+               AND ($2::boolean IS NULL OR (suspended_at IS NOT NULL) = $2)
+             ORDER BY user_id
+             LIMIT $3 OFFSET $4"
+        );
+
+        Ok(sqlx::query_as(&query)
+            .bind(filter.role)
+            .bind(filter.suspended)
+            .bind(filter.limit)
+            .bind(filter.offset)
+            .fetch_all(&self.db)
+            .await?)
+    }
+
+    async fn managed_user(&self, user_id: i64) -> StoreResult<Option<ManagedUser>> {
+        Ok(
+            sqlx::query_as(&format!("{MANAGED_USER} WHERE user_id = $1"))
+                .bind(user_id)
+                .fetch_optional(&self.db)
+                .await?,
+        )
+    }
+
+    async fn update_user(
+        &self,
+        user_id: i64,
+        update: &UpdateUser,
+        now: DateTime<Utc>,
+    ) -> StoreResult<bool> {
+        const QUERY: &str = r#"UPDATE "user"
+             SET role = coalesce($1, role),
+                 suspended_at = CASE WHEN $2::boolean THEN coalesce(suspended_at, $3)
+                                     WHEN NOT $2::boolean THEN NULL
+                                     ELSE suspended_at END
+             WHERE user_id = $4"#;
+
+        let mut tx = self.db.begin().await?;
+        let rows_affected = sqlx::query(QUERY)
+            .bind(update.role)
+            .bind(update.suspended)
+            .bind(now)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        if rows_affected == 0 {
+            return Ok(false);
+        }
+
+        if update.suspended == Some(true) || update.role.is_some() {
+            log_out_everywhere(&mut tx, user_id, None, now).await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn delete_user(&self, user_id: i64) -> StoreResult<bool> {
+        let rows_affected = sqlx::query(r#"DELETE FROM "user" WHERE user_id = $1"#)
+            .bind(user_id)
+            .execute(&self.db)
+            .await?
+            .rows_affected();
+        Ok(rows_affected > 0)
+    }
+}
+
+const MANAGED_USER: &str = r#"SELECT user_id, username, email, role, created_at, email_verified_at, suspended_at
+     FROM "user""#;
+
+#[async_trait]
+impl StockRepo for PgStore {
+    async fn list_stocks(&self, filter: &StockFilter) -> StoreResult<Vec<Stock>> {
+        const QUERY: &str = "SELECT * FROM stock
+             WHERE ($1::text IS NULL OR exchange = $1)
+               AND ($2::text IS NULL OR sector = $2)
+               AND ($3::text IS NULL OR status = $3)
+             ORDER BY symbol
+             LIMIT $4 OFFSET $5";
+
+        Ok(sqlx::query_as(QUERY)
+            .bind(&filter.exchange)
+            .bind(&filter.sector)
+            .bind(filter.status)
+            .bind(filter.limit)
+            .bind(filter.offset)
+            .fetch_all(&self.db)
+            .await?)
+    }
+
+    async fn stock(&self, symbol: &str) -> StoreResult<Option<Stock>> {
+        Ok(sqlx::query_as("SELECT * FROM stock WHERE symbol = $1")
+            .bind(symbol)
+            .fetch_optional(&self.db)
+            .await?)
+    }
+
+    async fn listed_symbols(&self) -> StoreResult<Vec<String>> {
+        Ok(
+            sqlx::query_scalar("SELECT symbol FROM stock WHERE status = 'listed' ORDER BY symbol")
+                .fetch_all(&self.db)
+                .await?,
+        )
+    }
+
+    async fn create_stock(&self, new: &NewStock, now: DateTime<Utc>) -> StoreResult<Stock> {
+        const QUERY: &str = "INSERT INTO stock
+             (symbol, exchange, name, currency, sector, lot_size, status, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $8)
+             RETURNING *";
+
+        sqlx::query_as(QUERY)
+            .bind(&new.symbol)
+            .bind(&new.exchange)
+            .bind(&new.name)
+            .bind(&new.currency)
+            .bind(&new.sector)
+            .bind(new.lot_size)
+            .bind(new.status)
+            .bind(now)
+            .fetch_one(&self.db)
+            .await
+            .map_err(|e| StoreError::on_conflict(e, &[("stock_pkey", "symbol")]))
+    }
+
+    async fn update_stock(
+        &self,
+        symbol: &str,
+        update: &UpdateStock,
+        now: DateTime<Utc>,
+    ) -> StoreResult<Option<Stock>> {
+        const QUERY: &str = "UPDATE stock
+             SET exchange = coalesce($1, exchange),
+                 name = coalesce($2, name),
+                 currency = coalesce($3, currency),
+                 sector = coalesce($4, sector),
+                 lot_size = coalesce($5, lot_size),
+                 status = coalesce($6, status),
+                 updated_at = $7
+             WHERE symbol = $8
+             RETURNING *";
+
+        Ok(sqlx::query_as(QUERY)
+            .bind(&update.exchange)
+            .bind(&update.name)
+            .bind(&update.currency)
+            .bind(&update.sector)
+            .bind(update.lot_size)
+            .bind(update.status)
+            .bind(now)
+            .bind(symbol)
+            .fetch_optional(&self.db)
+            .await?)
+    }
+
+    async fn delete_stock(&self, symbol: &str) -> StoreResult<bool> {
+        let rows_affected = sqlx::query("DELETE FROM stock WHERE symbol = $1")
+            .bind(symbol)
+            .execute(&self.db)
+            .await
+            .map_err(|e| StoreError::on_in_use(e, "symbol"))?
+            .rows_affected();
+        Ok(rows_affected > 0)
+    }
+}
+
+#[async_trait]
+impl PortfolioRepo for PgStore {
+    async fn list_portfolios(&self, user_id: i64) -> StoreResult<Vec<Portfolio>> {
+        Ok(
+            sqlx::query_as("SELECT * FROM portfolio WHERE user_id = $1 ORDER BY name")
+                .bind(user_id)
+                .fetch_all(&self.db)
+                .await?,
+        )
+    }
+
+    async fn portfolio(&self, user_id: i64, portfolio_id: i64) -> StoreResult<Option<Portfolio>> {
+        Ok(
+            sqlx::query_as("SELECT * FROM portfolio WHERE portfolio_id = $1 AND user_id = $2")
+                .bind(portfolio_id)
+                .bind(user_id)
+                .fetch_optional(&self.db)
+                .await?,
+        )
+    }
+
+    async fn create_portfolio(
+        &self,
+        user_id: i64,
+        new: &NewPortfolio,
+        now: DateTime<Utc>,
+    ) -> StoreResult<Portfolio> {
+        insert_portfolio(&mut *self.db.acquire().await?, user_id, new, now).await
+    }
+
+    async fn create_portfolio_with_ledger(
+        &self,
+        user_id: i64,
+        new: &NewPortfolio,
+        ledger: &[NewTransaction],
+        now: DateTime<Utc>,
+    ) -> StoreResult<Result<Portfolio, Oversold>> {
+        let mut tx = self.db.begin().await?;
+
+        let portfolio = insert_portfolio(&mut tx, user_id, new, now).await?;
+        for entry in ledger {
+            if let Err(oversold) = book(&mut tx, portfolio.portfolio_id, entry, now).await? {
+                return Ok(Err(oversold));
+            }
+        }
+
+        tx.commit().await?;
+        Ok(Ok(portfolio))
+    }
+
+    async fn update_portfolio(
+        &self,
+        user_id: i64,
+        portfolio_id: i64,
+        update: &UpdatePortfolio,
+        now: DateTime<Utc>,
+    ) -> StoreResult<Option<Portfolio>> {
+        const QUERY: &str = "UPDATE portfolio
+             SET name = coalesce($1, name),
+                 base_currency = coalesce($2, base_currency),
+                 updated_at = $3
+             WHERE portfolio_id = $4 AND user_id = $5
+             RETURNING *";
+
+        sqlx::query_as(QUERY)
+            .bind(&update.name)
+            .bind(&update.base_currency)
+            .bind(now)
+            .bind(portfolio_id)
+            .bind(user_id)
+            .fetch_optional(&self.db)
+            .await
+            .map_err(|e| StoreError::on_conflict(e, &[("portfolio_user_id_name_key", "name")]))
+    }
+
+    async fn delete_portfolio(&self, user_id: i64, portfolio_id: i64) -> StoreResult<bool> {
+        let rows_affected =
+            sqlx::query("DELETE FROM portfolio WHERE portfolio_id = $1 AND user_id = $2")
+                .bind(portfolio_id)
+                .bind(user_id)
+                .execute(&self.db)
+                .await?
+                .rows_affected();
+        Ok(rows_affected > 0)
+    }
+}
+
+async fn insert_portfolio(
+    conn: &mut PgConnection,
+    user_id: i64,
+    new: &NewPortfolio,
+    now: DateTime<Utc>,
+) -> StoreResult<Portfolio> {
+    const QUERY: &str =
+        "INSERT INTO portfolio (user_id, name, base_currency, created_at, updated_at)
+         VALUES ($1, $2, $3, $4, $4)
+         RETURNING *";
+
+    sqlx::query_as(QUERY)
+        .bind(user_id)
+        .bind(&new.name)
+        .bind(&new.base_currency)
+        .bind(now)
+        .fetch_one(conn)
+        .await
+        .map_err(|e| StoreError::on_conflict(e, &[("portfolio_user_id_name_key", "name")]))
+}
+
+const LEDGER: &str =
+    r#"SELECT * FROM "transaction" WHERE portfolio_id = $1 ORDER BY trade_date, transaction_id"#;
+
+#[async_trait]
+impl TransactionRepo for PgStore {
+    async fn ledger(&self, portfolio_id: i64) -> StoreResult<Vec<Transaction>> {
+        Ok(sqlx::query_as(LEDGER)
+            .bind(portfolio_id)
+            .fetch_all(&self.db)
+            .await?)
+    }
+
+    async fn book_transaction(
+        &self,
+        portfolio_id: i64,
+        new: &NewTransaction,
+        now: DateTime<Utc>,
+    ) -> StoreResult<Result<Transaction, Oversold>> {
+        let mut tx = self.db.begin().await?;
+        let booked = book(&mut tx, portfolio_id, new, now).await?;
+        if booked.is_ok() {
+            tx.commit().await?;
+        }
+        Ok(booked)
+    }
+}
+
+/// Append `new` to the ledger of `portfolio_id` unless it oversells, within the caller's database
+/// transaction.
+async fn book(
+    conn: &mut PgConnection,
+    portfolio_id: i64,
+    new: &NewTransaction,
+    now: DateTime<Utc>,
+) -> StoreResult<Result<Transaction, Oversold>> {
+    const QUERY: &str = r#"INSERT INTO "transaction"
+         (portfolio_id, kind, symbol, trade_date, quantity, price, amount, fee, split_ratio, note, created_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+         RETURNING *"#;
+
+    // Bookings on the same portfolio wait for each other, each checking the ledger as left by
+    // the one before.
+    sqlx::query("SELECT 1 FROM portfolio WHERE portfolio_id = $1 FOR UPDATE")
+        .bind(portfolio_id)
+        .execute(&mut *conn)
+        .await?;
+    let ledger = sqlx::query_as(LEDGER)
+        .bind(portfolio_id)
+        .fetch_all(&mut *conn)
+        .await?;
+    if let Err(oversold) = check_booking(ledger, new) {
+        return Ok(Err(oversold));
+    }
+
+    let transaction = sqlx::query_as(QUERY)
+        .bind(portfolio_id)
+        .bind(new.kind)
+        .bind(&new.symbol)
+        .bind(new.trade_date)
+        .bind(new.quantity)
+        .bind(new.price)
+        .bind(new.amount)
+        .bind(new.fee)
+        .bind(new.split_ratio)
+        .bind(&new.note)
+        .bind(now)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(Ok(transaction))
+}
@@ -0,0 +1,108 @@
+use axum::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+
+use super::PgStore;
+use crate::alerts::model::{AlertEvent, AlertRule, NewAlert};
+use crate::store::{AlertRepo, StoreResult};
+
+#[async_trait]
+impl AlertRepo for PgStore {
+    async fn list_alerts(&self, user_id: i64) -> StoreResult<Vec<AlertRule>> {
+        Ok(sqlx::query_as(
+            "SELECT * FROM alert_rule WHERE user_id = $1 ORDER BY symbol, alert_rule_id",
+        )
+        .bind(user_id)
+        .fetch_all(&self.db)
+        .await?)
+    }
+
+    async fn create_alert(
+        &self,
+        user_id: i64,
+        new: &NewAlert,
+        now: DateTime<Utc>,
+    ) -> StoreResult<AlertRule> {
+        const QUERY: &str =
+            "INSERT INTO alert_rule (user_id, symbol, condition, threshold, note, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             RETURNING *";
+
+        Ok(sqlx::query_as(QUERY)
+            .bind(user_id)
+            .bind(&new.symbol)
+            .bind(new.condition)
+            .bind(new.threshold)
+            .bind(&new.note)
+            .bind(now)
+            .fetch_one(&self.db)
+            .await?)
+    }
+
+    async fn delete_alert(&self, user_id: i64, alert_rule_id: i64) -> StoreResult<bool> {
+        let rows_affected =
+            sqlx::query("DELETE FROM alert_rule WHERE alert_rule_id = $1 AND user_id = $2")
+                .bind(alert_rule_id)
+                .bind(user_id)
+                .execute(&self.db)
+                .await?
+                .rows_affected();
+        Ok(rows_affected > 0)
+    }
+
+    async fn list_events(
+        &self,
+        user_id: i64,
+        limit: i64,
+        offset: i64,
+    ) -> StoreResult<Vec<AlertEvent>> {
+        const QUERY: &str = "SELECT alert_event.* FROM alert_event
+             JOIN alert_rule USING (alert_rule_id)
+             WHERE alert_rule.user_id = $1
+             ORDER BY triggered_at DESC, alert_event_id DESC
+             LIMIT $2 OFFSET $3";
+
+        Ok(sqlx::query_as(QUERY)
+            .bind(user_id)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.db)
+            .await?)
+    }
+
+    async fn rules_for(&self, symbol: &str) -> StoreResult<Vec<AlertRule>> {
+        Ok(sqlx::query_as("SELECT * FROM alert_rule WHERE symbol = $1")
+            .bind(symbol)
+            .fetch_all(&self.db)
+            .await?)
+    }
+
+    async fn record_event(
+        &self,
+        alert_rule_id: i64,
+        session: NaiveDate,
+        price: f64,
+        now: DateTime<Utc>,
+    ) -> StoreResult<bool> {
+        const QUERY: &str = "INSERT INTO alert_event (alert_rule_id, session, price, triggered_at)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (alert_rule_id, session) DO NOTHING";
+
+        let rows_affected = sqlx::query(QUERY)
+            .bind(alert_rule_id)
+            .bind(session)
+            .bind(price)
+            .bind(now)
+            .execute(&self.db)
+            .await?
+            .rows_affected();
+        Ok(rows_affected == 1)
+    }
+
+    async fn alerted_symbols(&self) -> StoreResult<Vec<String>> {
+        Ok(
+            sqlx::query_scalar("SELECT DISTINCT symbol FROM alert_rule ORDER BY symbol")
+                .fetch_all(&self.db)
+                .await?,
+        )
+    }
+}
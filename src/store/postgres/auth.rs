@@ -0,0 +1,560 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+
+use super::PgStore;
+use crate::auth::model::{ApiKey, Scopes, Session};
+use crate::store::{
+    ApiKeyOwner, ApiKeyRepo, RefreshToken, Rotation, SessionRepo, StoreResult, Totp, TwoFactorRepo,
+};
+
+const SESSION_COLUMNS: &str =
+    "session_id, user_id, user_agent, created_at, last_seen_at, expires_at";
+
+#[async_trait]
+impl SessionRepo for PgStore {
+    async fn create_session(
+        &self,
+        token_hash: &[u8],
+        user_id: i64,
+        user_agent: Option<&str>,
+        now: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> StoreResult<()> {
+        const QUERY: &str = "INSERT INTO session
+             (token_hash, user_id, user_agent, created_at, last_seen_at, expires_at)
+             VALUES ($1, $2, $3, $4, $4, $5)";
+
+        sqlx::query(QUERY)
+            .bind(token_hash)
+            .bind(user_id)
+            .bind(user_agent)
+            .bind(now)
+            .bind(expires_at)
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn session(&self, token_hash: &[u8], now: DateTime<Utc>) -> StoreResult<Option<Session>> {
+        let query = format!(
+            "SELECT {SESSION_COLUMNS} FROM session WHERE token_hash = $1 AND expires_at > $2"
+        );
+        Ok(sqlx::query_as(&query)
+            .bind(token_hash)
+            .bind(now)
+            .fetch_optional(&self.db)
+            .await?)
+    }
+
+    async fn list_sessions(&self, user_id: i64, now: DateTime<Utc>) -> StoreResult<Vec<Session>> {
+        let query = format!(
+            "SELECT {SESSION_COLUMNS} FROM session
+             WHERE user_id = $1 AND expires_at > $2
+             ORDER BY last_seen_at DESC, session_id DESC"
+        );
+        Ok(sqlx::query_as(&query)
+            .bind(user_id)
+            .bind(now)
+            .fetch_all(&self.db)
+            .await?)
+    }
+
+    async fn touch_session(
+        &self,
+        session_id: i64,
+        last_seen_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> StoreResult<()> {
+        sqlx::query("UPDATE session SET last_seen_at = $1, expires_at = $2 WHERE session_id = $3")
+            .bind(last_seen_at)
+            .bind(expires_at)
+            .bind(session_id)
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_session_token(&self, session_id: i64, token_hash: &[u8]) -> StoreResult<()> {
+        sqlx::query("UPDATE session SET token_hash = $1 WHERE session_id = $2")
+            .bind(token_hash)
+            .bind(session_id)
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_session_by_token(&self, token_hash: &[u8]) -> StoreResult<()> {
+        sqlx::query("DELETE FROM session WHERE token_hash = $1")
+            .bind(token_hash)
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_session(&self, user_id: i64, session_id: i64) -> StoreResult<bool> {
+        let rows_affected =
+            sqlx::query("DELETE FROM session WHERE session_id = $1 AND user_id = $2")
+                .bind(session_id)
+                .bind(user_id)
+                .execute(&self.db)
+                .await?
+                .rows_affected();
+        Ok(rows_affected > 0)
+    }
+
+    async fn log_out_everywhere(
+        &self,
+        user_id: i64,
+        keep: Option<i64>,
+        now: DateTime<Utc>,
+    ) -> StoreResult<()> {
+        let mut tx = self.db.begin().await?;
+        log_out_everywhere(&mut tx, user_id, keep, now).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn prune_sessions(&self, now: DateTime<Utc>) -> StoreResult<u64> {
+        Ok(sqlx::query("DELETE FROM session WHERE expires_at <= $1")
+            .bind(now)
+            .execute(&self.db)
+            .await?
+            .rows_affected())
+    }
+
+    async fn create_refresh_token(
+        &self,
+        token_hash: &[u8],
+        family: &str,
+        user_id: i64,
+        now: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> StoreResult<()> {
+        let mut conn = self.db.acquire().await?;
+        create_refresh_token(&mut conn, token_hash, family, user_id, now, expires_at).await
+    }
+
+    async fn refresh_token(&self, token_hash: &[u8]) -> StoreResult<Option<RefreshToken>> {
+        const QUERY: &str =
+            "SELECT refresh_token_id, family, user_id, expires_at, used_at, revoked_at
+             FROM refresh_token WHERE token_hash = $1";
+
+        Ok(sqlx::query_as(QUERY)
+            .bind(token_hash)
+            .fetch_optional(&self.db)
+            .await?)
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        token: &RefreshToken,
+        token_hash: &[u8],
+        now: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> StoreResult<Rotation> {
+        let mut tx = self.db.begin().await?;
+        let rows_affected = sqlx::query(
+            "UPDATE refresh_token SET used_at = $1
+             WHERE refresh_token_id = $2 AND used_at IS NULL AND revoked_at IS NULL",
+        )
+        .bind(now)
+        .bind(token.refresh_token_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if rows_affected == 0 {
+            return Ok(Rotation::Reused);
+        }
+
+        // Locking the user row, a suspension committing meanwhile revokes the new token too.
+        let role = sqlx::query_scalar(
+            r#"SELECT role FROM "user" WHERE user_id = $1 AND suspended_at IS NULL FOR SHARE"#,
+        )
+        .bind(token.user_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(role) = role else {
+            return Ok(Rotation::Inactive);
+        };
+
+        create_refresh_token(
+            &mut tx,
+            token_hash,
+            &token.family,
+            token.user_id,
+            now,
+            expires_at,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(Rotation::Rotated(role))
+    }
+
+    async fn revoke_token_family(&self, family: &str, now: DateTime<Utc>) -> StoreResult<()> {
+        sqlx::query(
+            "UPDATE refresh_token SET revoked_at = $1 WHERE family = $2 AND revoked_at IS NULL",
+        )
+        .bind(now)
+        .bind(family)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn prune_refresh_tokens(&self, now: DateTime<Utc>) -> StoreResult<u64> {
+        Ok(
+            sqlx::query("DELETE FROM refresh_token WHERE expires_at <= $1")
+                .bind(now)
+                .execute(&self.db)
+                .await?
+                .rows_affected(),
+        )
+    }
+
+    async fn use_email_token(&self, jti: &str, expires_at: DateTime<Utc>) -> StoreResult<bool> {
+        let rows_affected = sqlx::query(
+            "INSERT INTO used_email_token (jti, expires_at) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        )
+        .bind(jti)
+        .bind(expires_at)
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+        Ok(rows_affected == 1)
+    }
+
+    async fn prune_email_tokens(&self, now: DateTime<Utc>) -> StoreResult<u64> {
+        Ok(
+            sqlx::query("DELETE FROM used_email_token WHERE expires_at <= $1")
+                .bind(now)
+                .execute(&self.db)
+                .await?
+                .rows_affected(),
+        )
+    }
+}
+
+/// Delete the sessions of `user_id` but `keep` and revoke their refresh tokens, within the
+/// caller's database transaction.
+async fn create_refresh_token(
+    conn: &mut PgConnection,
+    token_hash: &[u8],
+    family: &str,
+    user_id: i64,
+    now: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+) -> StoreResult<()> {
+    const QUERY: &str = "INSERT INTO refresh_token
+         (token_hash, family, user_id, created_at, expires_at)
+         VALUES ($1, $2, $3, $4, $5)";
+
+    sqlx::query(QUERY)
+        .bind(token_hash)
+        .bind(family)
+        .bind(user_id)
+        .bind(now)
+        .bind(expires_at)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+pub(super) async fn log_out_everywhere(
+    conn: &mut PgConnection,
+    user_id: i64,
+    keep: Option<i64>,
+    now: DateTime<Utc>,
+) -> StoreResult<()> {
+    sqlx::query(
+        "DELETE FROM session WHERE user_id = $1 AND ($2::bigint IS NULL OR session_id != $2)",
+    )
+    .bind(user_id)
+    .bind(keep)
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query(
+        "UPDATE refresh_token SET revoked_at = $1 WHERE user_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(now)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+#[async_trait]
+impl TwoFactorRepo for PgStore {
+    async fn totp(&self, user_id: i64) -> StoreResult<Option<Totp>> {
+        Ok(
+            sqlx::query_as(
+                "SELECT secret, enabled_at, last_used_step FROM totp WHERE user_id = $1",
+            )
+            .bind(user_id)
+            .fetch_optional(&self.db)
+            .await?,
+        )
+    }
+
+    async fn enroll_totp(
+        &self,
+        user_id: i64,
+        secret: &[u8],
+        now: DateTime<Utc>,
+    ) -> StoreResult<()> {
+        sqlx::query(
+            "INSERT INTO totp (user_id, secret, created_at) VALUES ($1, $2, $3)
+             ON CONFLICT (user_id) DO UPDATE
+             SET secret = excluded.secret, created_at = excluded.created_at, last_used_step = NULL",
+        )
+        .bind(user_id)
+        .bind(secret)
+        .bind(now)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn enable_totp(
+        &self,
+        user_id: i64,
+        step: i64,
+        recovery_codes: &[Vec<u8>],
+        now: DateTime<Utc>,
+    ) -> StoreResult<()> {
+        let mut tx = self.db.begin().await?;
+        sqlx::query("UPDATE totp SET enabled_at = $1, last_used_step = $2 WHERE user_id = $3")
+            .bind(now)
+            .bind(step)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        replace_recovery_codes(&mut tx, user_id, recovery_codes).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn disable_totp(&self, user_id: i64) -> StoreResult<()> {
+        let mut tx = self.db.begin().await?;
+        sqlx::query("DELETE FROM totp WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM recovery_code WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn use_totp_step(&self, user_id: i64, step: i64) -> StoreResult<bool> {
+        let rows_affected = sqlx::query(
+            "UPDATE totp SET last_used_step = $1
+             WHERE user_id = $2 AND (last_used_step IS NULL OR last_used_step < $1)",
+        )
+        .bind(step)
+        .bind(user_id)
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+        Ok(rows_affected == 1)
+    }
+
+    async fn count_failed_code(&self, user_id: i64, max_attempts: i64) -> StoreResult<Option<i64>> {
+        const QUERY: &str = "UPDATE totp SET failed_attempts = failed_attempts + 1
+             WHERE user_id = $1 AND enabled_at IS NOT NULL AND failed_attempts < $2
+             RETURNING failed_attempts";
+
+        Ok(sqlx::query_scalar(QUERY)
+            .bind(user_id)
+            .bind(max_attempts)
+            .fetch_optional(&self.db)
+            .await?)
+    }
+
+    async fn reset_failed_codes(&self, user_id: i64) -> StoreResult<()> {
+        sqlx::query("UPDATE totp SET failed_attempts = 0 WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn replace_recovery_codes(
+        &self,
+        user_id: i64,
+        recovery_codes: &[Vec<u8>],
+    ) -> StoreResult<()> {
+        let mut tx = self.db.begin().await?;
+        replace_recovery_codes(&mut tx, user_id, recovery_codes).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn use_recovery_code(
+        &self,
+        user_id: i64,
+        code_hash: &[u8],
+        now: DateTime<Utc>,
+    ) -> StoreResult<bool> {
+        let rows_affected = sqlx::query(
+            "UPDATE recovery_code SET used_at = $1
+             WHERE user_id = $2 AND code_hash = $3 AND used_at IS NULL",
+        )
+        .bind(now)
+        .bind(user_id)
+        .bind(code_hash)
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+        Ok(rows_affected == 1)
+    }
+
+    async fn create_challenge(
+        &self,
+        token_hash: &[u8],
+        user_id: i64,
+        expires_at: DateTime<Utc>,
+    ) -> StoreResult<()> {
+        sqlx::query(
+            "INSERT INTO login_challenge (token_hash, user_id, expires_at) VALUES ($1, $2, $3)",
+        )
+        .bind(token_hash)
+        .bind(user_id)
+        .bind(expires_at)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn attempt_challenge(
+        &self,
+        token_hash: &[u8],
+        now: DateTime<Utc>,
+        max_attempts: i64,
+    ) -> StoreResult<Option<(i64, i64)>> {
+        const QUERY: &str = "UPDATE login_challenge SET attempts = attempts + 1
+             WHERE token_hash = $1 AND expires_at > $2 AND attempts < $3
+             RETURNING login_challenge_id, user_id";
+
+        Ok(sqlx::query_as(QUERY)
+            .bind(token_hash)
+            .bind(now)
+            .bind(max_attempts)
+            .fetch_optional(&self.db)
+            .await?)
+    }
+
+    async fn delete_challenge(&self, login_challenge_id: i64) -> StoreResult<()> {
+        sqlx::query("DELETE FROM login_challenge WHERE login_challenge_id = $1")
+            .bind(login_challenge_id)
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn prune_challenges(&self, now: DateTime<Utc>) -> StoreResult<u64> {
+        Ok(
+            sqlx::query("DELETE FROM login_challenge WHERE expires_at <= $1")
+                .bind(now)
+                .execute(&self.db)
+                .await?
+                .rows_affected(),
+        )
+    }
+}
+
+async fn replace_recovery_codes(
+    conn: &mut PgConnection,
+    user_id: i64,
+    recovery_codes: &[Vec<u8>],
+) -> StoreResult<()> {
+    sqlx::query("DELETE FROM recovery_code WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    for code_hash in recovery_codes {
+        sqlx::query("INSERT INTO recovery_code (user_id, code_hash) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(code_hash)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+const API_KEY_COLUMNS: &str =
+    "api_key_id, name, prefix, scopes, created_at, last_used_at, request_count";
+
+#[async_trait]
+impl ApiKeyRepo for PgStore {
+    async fn list_api_keys(&self, user_id: i64) -> StoreResult<Vec<ApiKey>> {
+        let query =
+            format!("SELECT {API_KEY_COLUMNS} FROM api_key WHERE user_id = $1 ORDER BY api_key_id");
+        Ok(sqlx::query_as(&query)
+            .bind(user_id)
+            .fetch_all(&self.db)
+            .await?)
+    }
+
+    async fn create_api_key(
+        &self,
+        user_id: i64,
+        name: &str,
+        prefix: &str,
+        token_hash: &[u8],
+        scopes: &Scopes,
+        now: DateTime<Utc>,
+    ) -> StoreResult<ApiKey> {
+        let query = format!(
+            "INSERT INTO api_key (user_id, name, prefix, token_hash, scopes, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             RETURNING {API_KEY_COLUMNS}"
+        );
+
+        Ok(sqlx::query_as(&query)
+            .bind(user_id)
+            .bind(name)
+            .bind(prefix)
+            .bind(token_hash)
+            .bind(scopes.to_string())
+            .bind(now)
+            .fetch_one(&self.db)
+            .await?)
+    }
+
+    async fn delete_api_key(&self, user_id: i64, api_key_id: i64) -> StoreResult<bool> {
+        let rows_affected =
+            sqlx::query("DELETE FROM api_key WHERE api_key_id = $1 AND user_id = $2")
+                .bind(api_key_id)
+                .bind(user_id)
+                .execute(&self.db)
+                .await?
+                .rows_affected();
+        Ok(rows_affected > 0)
+    }
+
+    async fn api_key_owner(&self, token_hash: &[u8]) -> StoreResult<Option<ApiKeyOwner>> {
+        const QUERY: &str = r#"SELECT api_key.user_id, "user".role, api_key.scopes
+             FROM api_key JOIN "user" USING (user_id)
+             WHERE api_key.token_hash = $1 AND "user".suspended_at IS NULL"#;
+
+        Ok(sqlx::query_as(QUERY)
+            .bind(token_hash)
+            .fetch_optional(&self.db)
+            .await?)
+    }
+
+    async fn count_api_key_use(&self, token_hash: &[u8], now: DateTime<Utc>) -> StoreResult<()> {
+        sqlx::query(
+            "UPDATE api_key SET last_used_at = $1, request_count = request_count + 1
+             WHERE token_hash = $2",
+        )
+        .bind(now)
+        .bind(token_hash)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+}
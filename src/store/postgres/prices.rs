@@ -0,0 +1,119 @@
+use axum::async_trait;
+use chrono::NaiveDate;
+use sqlx::{Postgres, Transaction};
+
+use super::PgStore;
+use crate::prices::model::{NewBar, PriceBar};
+use crate::store::{BarWriter, PriceRepo, StoreResult};
+
+#[async_trait]
+impl PriceRepo for PgStore {
+    async fn bars(
+        &self,
+        symbol: &str,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> StoreResult<Vec<PriceBar>> {
+        const QUERY: &str = "SELECT * FROM price_bar
+             WHERE symbol = $1
+               AND ($2::date IS NULL OR date >= $2)
+               AND ($3::date IS NULL OR date <= $3)
+             ORDER BY date";
+
+        Ok(sqlx::query_as(QUERY)
+            .bind(symbol)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.db)
+            .await?)
+    }
+
+    async fn latest_bars(&self, symbol: &str, count: i64) -> StoreResult<Vec<PriceBar>> {
+        Ok(
+            sqlx::query_as("SELECT * FROM price_bar WHERE symbol = $1 ORDER BY date DESC LIMIT $2")
+                .bind(symbol)
+                .bind(count)
+                .fetch_all(&self.db)
+                .await?,
+        )
+    }
+
+    async fn latest_close(
+        &self,
+        symbol: &str,
+        as_of: Option<NaiveDate>,
+    ) -> StoreResult<Option<f64>> {
+        const QUERY: &str = "SELECT close FROM price_bar
+             WHERE symbol = $1 AND ($2::date IS NULL OR date <= $2)
+             ORDER BY date DESC
+             LIMIT 1";
+
+        Ok(sqlx::query_scalar(QUERY)
+            .bind(symbol)
+            .bind(as_of)
+            .fetch_optional(&self.db)
+            .await?)
+    }
+
+    async fn portfolio_closes(
+        &self,
+        portfolio_id: i64,
+        to: NaiveDate,
+    ) -> StoreResult<Vec<(String, NaiveDate, f64)>> {
+        const QUERY: &str = r#"SELECT symbol, date, close FROM price_bar
+             WHERE symbol IN (SELECT symbol FROM "transaction" WHERE portfolio_id = $1)
+               AND date <= $2"#;
+
+        Ok(sqlx::query_as(QUERY)
+            .bind(portfolio_id)
+            .bind(to)
+            .fetch_all(&self.db)
+            .await?)
+    }
+
+    async fn write_bars(&self, symbol: &str) -> StoreResult<Box<dyn BarWriter>> {
+        Ok(Box::new(PgBarWriter {
+            tx: self.db.begin().await?,
+            symbol: symbol.to_string(),
+        }))
+    }
+}
+
+struct PgBarWriter {
+    tx: Transaction<'static, Postgres>,
+    symbol: String,
+}
+
+#[async_trait]
+impl BarWriter for PgBarWriter {
+    async fn upsert(&mut self, bar: &NewBar) -> StoreResult<()> {
+        const QUERY: &str = "INSERT INTO price_bar
+             (symbol, date, open, high, low, close, adj_close, volume)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+             ON CONFLICT (symbol, date) DO UPDATE
+             SET open = excluded.open,
+                 high = excluded.high,
+                 low = excluded.low,
+                 close = excluded.close,
+                 adj_close = excluded.adj_close,
+                 volume = excluded.volume";
+
+        sqlx::query(QUERY)
+            .bind(&self.symbol)
+            .bind(bar.date)
+            .bind(bar.open)
+            .bind(bar.high)
+            .bind(bar.low)
+            .bind(bar.close)
+            .bind(bar.adj_close.unwrap_or(bar.close))
+            .bind(bar.volume)
+            .execute(&mut *self.tx)
+            .await?;
+        Ok(())
+    }
+
+    async fn commit(self: Box<Self>) -> StoreResult<()> {
+        self.tx.commit().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,262 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+
+use super::PgStore;
+use crate::store::{StoreError, StoreResult, WatchlistRepo};
+use crate::watchlists::model::{is_reordering, place, Watchlist, WatchlistEntry};
+
+const NAME_CONSTRAINT: (&str, &str) = ("watchlist_user_id_name_key", "name");
+
+const ENTRY: &str = "SELECT * FROM watchlist_entry WHERE watchlist_id = $1 AND symbol = $2";
+
+#[async_trait]
+impl WatchlistRepo for PgStore {
+    async fn list_watchlists(
+        &self,
+        user_id: i64,
+        limit: i64,
+        offset: i64,
+    ) -> StoreResult<Vec<Watchlist>> {
+        Ok(sqlx::query_as(
+            "SELECT * FROM watchlist WHERE user_id = $1 ORDER BY name LIMIT $2 OFFSET $3",
+        )
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.db)
+        .await?)
+    }
+
+    async fn watchlist(&self, user_id: i64, watchlist_id: i64) -> StoreResult<Option<Watchlist>> {
+        Ok(
+            sqlx::query_as("SELECT * FROM watchlist WHERE watchlist_id = $1 AND user_id = $2")
+                .bind(watchlist_id)
+                .bind(user_id)
+                .fetch_optional(&self.db)
+                .await?,
+        )
+    }
+
+    async fn create_watchlist(
+        &self,
+        user_id: i64,
+        name: &str,
+        now: DateTime<Utc>,
+    ) -> StoreResult<Watchlist> {
+        const QUERY: &str = "INSERT INTO watchlist (user_id, name, created_at, updated_at)
+             VALUES ($1, $2, $3, $3)
+             RETURNING *";
+
+        sqlx::query_as(QUERY)
+            .bind(user_id)
+            .bind(name)
+            .bind(now)
+            .fetch_one(&self.db)
+            .await
+            .map_err(|e| StoreError::on_conflict(e, &[NAME_CONSTRAINT]))
+    }
+
+    async fn rename_watchlist(
+        &self,
+        user_id: i64,
+        watchlist_id: i64,
+        name: &str,
+        now: DateTime<Utc>,
+    ) -> StoreResult<Option<Watchlist>> {
+        const QUERY: &str = "UPDATE watchlist SET name = $1, updated_at = $2
+             WHERE watchlist_id = $3 AND user_id = $4
+             RETURNING *";
+
+        sqlx::query_as(QUERY)
+            .bind(name)
+            .bind(now)
+            .bind(watchlist_id)
+            .bind(user_id)
+            .fetch_optional(&self.db)
+            .await
+            .map_err(|e| StoreError::on_conflict(e, &[NAME_CONSTRAINT]))
+    }
+
+    async fn delete_watchlist(&self, user_id: i64, watchlist_id: i64) -> StoreResult<bool> {
+        let rows_affected =
+            sqlx::query("DELETE FROM watchlist WHERE watchlist_id = $1 AND user_id = $2")
+                .bind(watchlist_id)
+                .bind(user_id)
+                .execute(&self.db)
+                .await?
+                .rows_affected();
+        Ok(rows_affected > 0)
+    }
+
+    async fn entries(&self, watchlist_id: i64) -> StoreResult<Vec<WatchlistEntry>> {
+        load_entries(&mut *self.db.acquire().await?, watchlist_id).await
+    }
+
+    async fn add_entry(
+        &self,
+        watchlist_id: i64,
+        symbol: &str,
+        note: Option<&str>,
+        position: Option<usize>,
+        now: DateTime<Utc>,
+    ) -> StoreResult<WatchlistEntry> {
+        const QUERY: &str =
+            "INSERT INTO watchlist_entry (watchlist_id, symbol, position, note, added_at)
+             VALUES ($1, $2, $3, $4, $5)";
+
+        let mut tx = self.db.begin().await?;
+        lock(&mut tx, watchlist_id).await?;
+        let mut order = load_order(&mut tx, watchlist_id).await?;
+
+        sqlx::query(QUERY)
+            .bind(watchlist_id)
+            .bind(symbol)
+            .bind(order.len() as i64)
+            .bind(note)
+            .bind(now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| StoreError::on_conflict(e, &[("watchlist_entry_pkey", "symbol")]))?;
+
+        place(&mut order, symbol, position);
+        write_order(&mut tx, watchlist_id, &order).await?;
+        let entry = sqlx::query_as(ENTRY)
+            .bind(watchlist_id)
+            .bind(symbol)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(entry)
+    }
+
+    async fn update_entry(
+        &self,
+        watchlist_id: i64,
+        symbol: &str,
+        note: Option<&str>,
+        position: Option<usize>,
+    ) -> StoreResult<Option<WatchlistEntry>> {
+        let mut tx = self.db.begin().await?;
+        lock(&mut tx, watchlist_id).await?;
+
+        let rows_affected = sqlx::query(
+            "UPDATE watchlist_entry SET note = coalesce($1, note)
+             WHERE watchlist_id = $2 AND symbol = $3",
+        )
+        .bind(note)
+        .bind(watchlist_id)
+        .bind(symbol)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if rows_affected == 0 {
+            return Ok(None);
+        }
+
+        if position.is_some() {
+            let mut order = load_order(&mut tx, watchlist_id).await?;
+            place(&mut order, symbol, position);
+            write_order(&mut tx, watchlist_id, &order).await?;
+        }
+
+        let entry = sqlx::query_as(ENTRY)
+            .bind(watchlist_id)
+            .bind(symbol)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(Some(entry))
+    }
+
+    async fn remove_entry(&self, watchlist_id: i64, symbol: &str) -> StoreResult<bool> {
+        let mut tx = self.db.begin().await?;
+        lock(&mut tx, watchlist_id).await?;
+
+        let rows_affected =
+            sqlx::query("DELETE FROM watchlist_entry WHERE watchlist_id = $1 AND symbol = $2")
+                .bind(watchlist_id)
+                .bind(symbol)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        if rows_affected == 0 {
+            return Ok(false);
+        }
+
+        // Close the gap so positions stay 0..len.
+        let order = load_order(&mut tx, watchlist_id).await?;
+        write_order(&mut tx, watchlist_id, &order).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn reorder_entries(
+        &self,
+        watchlist_id: i64,
+        symbols: &[String],
+    ) -> StoreResult<Option<Vec<WatchlistEntry>>> {
+        let mut tx = self.db.begin().await?;
+        lock(&mut tx, watchlist_id).await?;
+
+        let current = load_order(&mut tx, watchlist_id).await?;
+        if !is_reordering(&current, symbols) {
+            return Ok(None);
+        }
+
+        write_order(&mut tx, watchlist_id, symbols).await?;
+        let entries = load_entries(&mut tx, watchlist_id).await?;
+        tx.commit().await?;
+        Ok(Some(entries))
+    }
+}
+
+/// Changes to the entries of a watchlist wait for each other, each renumbering the order as left
+/// by the one before.
+async fn lock(conn: &mut PgConnection, watchlist_id: i64) -> StoreResult<()> {
+    sqlx::query("SELECT 1 FROM watchlist WHERE watchlist_id = $1 FOR UPDATE")
+        .bind(watchlist_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn load_entries(
+    conn: &mut PgConnection,
+    watchlist_id: i64,
+) -> StoreResult<Vec<WatchlistEntry>> {
+    Ok(sqlx::query_as(
+        "SELECT * FROM watchlist_entry WHERE watchlist_id = $1 ORDER BY position, added_at",
+    )
+    .bind(watchlist_id)
+    .fetch_all(conn)
+    .await?)
+}
+
+async fn load_order(conn: &mut PgConnection, watchlist_id: i64) -> StoreResult<Vec<String>> {
+    Ok(sqlx::query_scalar(
+        "SELECT symbol FROM watchlist_entry WHERE watchlist_id = $1 ORDER BY position, added_at",
+    )
+    .bind(watchlist_id)
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Renumber the entries of a watchlist to follow `order`.
+async fn write_order(
+    conn: &mut PgConnection,
+    watchlist_id: i64,
+    order: &[String],
+) -> StoreResult<()> {
+    for (position, symbol) in order.iter().enumerate() {
+        sqlx::query(
+            "UPDATE watchlist_entry SET position = $1 WHERE watchlist_id = $2 AND symbol = $3",
+        )
+        .bind(position as i64)
+        .bind(watchlist_id)
+        .bind(symbol)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+
+use super::SqliteStore;
+use crate::jobs::JobStatus;
+use crate::store::{JobRepo, StoreResult};
+
+#[async_trait]
+impl JobRepo for SqliteStore {
+    async fn start_job_run(
+        &self,
+        job_name: &str,
+        scheduled_for: DateTime<Utc>,
+        attempt: i64,
+        now: DateTime<Utc>,
+    ) -> StoreResult<i64> {
+        const QUERY: &str =
+            "INSERT INTO job_run (job_name, scheduled_for, attempt, status, started_at)
+             VALUES ($1, $2, $3, $4, $5)
+             RETURNING job_run_id";
+
+        Ok(sqlx::query_scalar(QUERY)
+            .bind(job_name)
+            .bind(scheduled_for)
+            .bind(attempt)
+            .bind(JobStatus::Running)
+            .bind(now)
+            .fetch_one(&self.db)
+            .await?)
+    }
+
+    async fn finish_job_run(
+        &self,
+        job_run_id: i64,
+        status: JobStatus,
+        error: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> StoreResult<()> {
+        sqlx::query(
+            "UPDATE job_run SET status = $1, error = $2, finished_at = $3 WHERE job_run_id = $4",
+        )
+        .bind(status)
+        .bind(error)
+        .bind(now)
+        .bind(job_run_id)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn prune_job_runs(&self, before: DateTime<Utc>) -> StoreResult<u64> {
+        Ok(sqlx::query("DELETE FROM job_run WHERE finished_at < $1")
+            .bind(before)
+            .execute(&self.db)
+            .await?
+            .rows_affected())
+    }
+}
@@ -0,0 +1,132 @@
+use axum::body::Body;
+use axum::http::header::SET_COOKIE;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use http_body_util::BodyExt;
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+
+use crate::auth::{hash_password, Role};
+
+/// A response as handler tests look at it.
+pub(crate) struct TestResponse {
+    pub status: StatusCode,
+    /// The `name=value` part of `Set-Cookie`, as a browser would send it back.
+    pub cookie: Option<String>,
+    /// `Null` when empty or not JSON.
+    pub body: serde_json::Value,
+}
+
+/// Send `method uri` to `app` with `headers` and a JSON `body`.
+pub(crate) async fn send_with(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    headers: &[(&str, &str)],
+    body: Option<serde_json::Value>,
+) -> TestResponse {
+    let headers = [&[("content-type", "application/json")], headers].concat();
+    let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+    send_body(app, method, uri, &headers, body).await
+}
+
+/// Send `method uri` to `app` with `headers` and a `body` of any type, e.g. a multipart form.
+pub(crate) async fn send_body(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    headers: &[(&str, &str)],
+    body: Body,
+) -> TestResponse {
+    let mut request = Request::builder().method(method).uri(uri);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let request = request.body(body).unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let cookie = response.headers().get(SET_COOKIE).map(|value| {
+        let value = value.to_str().unwrap();
+        value.split(';').next().unwrap().to_string()
+    });
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+
+    TestResponse {
+        status,
+        cookie,
+        body: serde_json::from_slice(&bytes).unwrap_or_default(),
+    }
+}
+
+/// Send `method uri` to `app` with the session `cookie`, empty for none, and a JSON `body`.
+pub(crate) async fn send(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let response = send_with(app, method, uri, &[("cookie", cookie)], body).await;
+    (response.status, response.body)
+}
+
+/// Insert user `user_id` as `username`, mailed at `<username>@example.com`, with `role` and no
+/// password to log in with.
+pub(crate) async fn insert_user(db: &SqlitePool, user_id: i64, username: &str, role: Role) {
+    sqlx::query(
+        "INSERT INTO user (user_id, username, email, password_hash, role)
+         VALUES ($1, $2, $3, '', $4)",
+    )
+    .bind(user_id)
+    .bind(username)
+    .bind(format!("{username}@example.com"))
+    .bind(role)
+    .execute(db)
+    .await
+    .unwrap();
+}
+
+/// Like [`insert_user`], for a plain user who logs in with `password`.
+pub(crate) async fn insert_user_with_password(
+    db: &SqlitePool,
+    user_id: i64,
+    username: &str,
+    password: &str,
+) {
+    insert_user(db, user_id, username, Role::User).await;
+    sqlx::query("UPDATE user SET password_hash = $1 WHERE user_id = $2")
+        .bind(hash_password(password.to_string()).await.unwrap())
+        .bind(user_id)
+        .execute(db)
+        .await
+        .unwrap();
+}
+
+/// Insert the USD stock `symbol` named `name`, listed on `exchange`.
+pub(crate) async fn insert_stock(db: &SqlitePool, symbol: &str, exchange: &str, name: &str) {
+    sqlx::query(
+        "INSERT INTO stock (symbol, exchange, name, currency, created_at, updated_at)
+         VALUES ($1, $2, $3, 'USD', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+    )
+    .bind(symbol)
+    .bind(exchange)
+    .bind(name)
+    .execute(db)
+    .await
+    .unwrap();
+}
+
+/// Insert the USD portfolio `portfolio_id` of `user_id`, named `name`.
+pub(crate) async fn insert_portfolio(db: &SqlitePool, portfolio_id: i64, user_id: i64, name: &str) {
+    sqlx::query(
+        "INSERT INTO portfolio (portfolio_id, user_id, name, base_currency, created_at, updated_at)
+         VALUES ($1, $2, $3, 'USD', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+    )
+    .bind(portfolio_id)
+    .bind(user_id)
+    .bind(name)
+    .execute(db)
+    .await
+    .unwrap();
+}
@@ -0,0 +1,393 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::{get, post, put};
+use axum::Router;
+use chrono::Utc;
+
+use super::model::{Watchlist, WatchlistEntry};
+use crate::app::AppState;
+use crate::auth::AuthUser;
+use crate::common::errors::{Error, Result};
+use crate::common::extract::{Json, Path, Query};
+use crate::common::paging::Paging;
+use crate::stocks::{fetch_stock, normalize_code};
+use crate::store::StoreError;
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/api/watchlists",
+            get(list_watchlists).post(create_watchlist),
+        )
+        .route(
+            "/api/watchlists/:id",
+            get(get_watchlist)
+                .put(update_watchlist)
+                .delete(delete_watchlist),
+        )
+        .route("/api/watchlists/:id/order", put(reorder_watchlist))
+        .route("/api/watchlists/:id/entries", post(add_entry))
+        .route(
+            "/api/watchlists/:id/entries/:symbol",
+            put(update_entry).delete(remove_entry),
+        )
+}
+
+/// A wrapper type for all requests/responses from these routes.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct WatchlistBody<T> {
+    watchlist: T,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct EntryBody<T> {
+    entry: T,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MultipleWatchlistsBody {
+    watchlists: Vec<Watchlist>,
+    watchlists_count: usize,
+}
+
+#[derive(serde::Serialize)]
+struct WatchlistWithEntries {
+    #[serde(flatten)]
+    watchlist: Watchlist,
+    entries: Vec<WatchlistEntry>,
+}
+
+#[derive(serde::Deserialize)]
+struct NewWatchlist {
+    name: String,
+}
+
+#[derive(serde::Deserialize)]
+struct NewEntry {
+    symbol: String,
+    note: Option<String>,
+    /// Where to insert the symbol, appended at the end when unset.
+    position: Option<usize>,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(default)]
+struct UpdateEntry {
+    note: Option<String>,
+    position: Option<usize>,
+}
+
+#[derive(serde::Deserialize)]
+struct OrderBody {
+    symbols: Vec<String>,
+}
+
+async fn list_watchlists(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Query(paging): Query<Paging>,
+) -> Result<Json<MultipleWatchlistsBody>> {
+    let (limit, offset) = paging.limit_offset(10, 100);
+
+    let watchlists = state
+        .store
+        .watchlists
+        .list_watchlists(auth_user.user_id, limit, offset)
+        .await?;
+
+    Ok(Json(MultipleWatchlistsBody {
+        watchlists_count: watchlists.len(),
+        watchlists,
+    }))
+}
+
+async fn get_watchlist(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(watchlist_id): Path<i64>,
+) -> Result<Json<WatchlistBody<WatchlistWithEntries>>> {
+    let watchlist = fetch_watchlist(&state, auth_user, watchlist_id).await?;
+    let entries = state.store.watchlists.entries(watchlist_id).await?;
+
+    Ok(Json(WatchlistBody {
+        watchlist: WatchlistWithEntries { watchlist, entries },
+    }))
+}
+
+async fn create_watchlist(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<WatchlistBody<NewWatchlist>>,
+) -> Result<(StatusCode, Json<WatchlistBody<Watchlist>>)> {
+    let name = req.watchlist.name.trim();
+    validate_name(name)?;
+
+    let watchlist = state
+        .store
+        .watchlists
+        .create_watchlist(auth_user.user_id, name, Utc::now())
+        .await
+        .map_err(name_conflict)?;
+
+    Ok((StatusCode::CREATED, Json(WatchlistBody { watchlist })))
+}
+
+async fn update_watchlist(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(watchlist_id): Path<i64>,
+    Json(req): Json<WatchlistBody<NewWatchlist>>,
+) -> Result<Json<WatchlistBody<Watchlist>>> {
+    let name = req.watchlist.name.trim();
+    validate_name(name)?;
+
+    let watchlist = state
+        .store
+        .watchlists
+        .rename_watchlist(auth_user.user_id, watchlist_id, name, Utc::now())
+        .await
+        .map_err(name_conflict)?
+        .ok_or(Error::NotFound)?;
+
+    Ok(Json(WatchlistBody { watchlist }))
+}
+
+async fn delete_watchlist(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(watchlist_id): Path<i64>,
+) -> Result<StatusCode> {
+    let deleted = state
+        .store
+        .watchlists
+        .delete_watchlist(auth_user.user_id, watchlist_id)
+        .await?;
+
+    if !deleted {
+        return Err(Error::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// The new order must name every symbol on the list exactly once.
+async fn reorder_watchlist(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(watchlist_id): Path<i64>,
+    Json(req): Json<OrderBody>,
+) -> Result<Json<WatchlistBody<WatchlistWithEntries>>> {
+    let watchlist = fetch_watchlist(&state, auth_user, watchlist_id).await?;
+    let order: Vec<String> = req.symbols.iter().map(|s| normalize_code(s)).collect();
+
+    let entries = state
+        .store
+        .watchlists
+        .reorder_entries(watchlist_id, &order)
+        .await?
+        .ok_or_else(|| {
+            Error::unprocessable_entity([(
+                "symbols",
+                "must list every symbol on the watchlist once",
+            )])
+        })?;
+
+    Ok(Json(WatchlistBody {
+        watchlist: WatchlistWithEntries { watchlist, entries },
+    }))
+}
+
+async fn add_entry(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(watchlist_id): Path<i64>,
+    Json(req): Json<EntryBody<NewEntry>>,
+) -> Result<(StatusCode, Json<EntryBody<WatchlistEntry>>)> {
+    fetch_watchlist(&state, auth_user, watchlist_id).await?;
+    let new = req.entry;
+    let symbol = normalize_code(&new.symbol);
+
+    if fetch_stock(&state, &symbol).await?.is_none() {
+        return Err(Error::unprocessable_entity([("symbol", "unknown symbol")]));
+    }
+
+    let entry = state
+        .store
+        .watchlists
+        .add_entry(
+            watchlist_id,
+            &symbol,
+            new.note.as_deref(),
+            new.position,
+            Utc::now(),
+        )
+        .await
+        .map_err(|error| match error {
+            StoreError::Conflict(_) => {
+                Error::unprocessable_entity([("symbol", "already on the watchlist")])
+            }
+            error => error.into(),
+        })?;
+
+    Ok((StatusCode::CREATED, Json(EntryBody { entry })))
+}
+
+async fn update_entry(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path((watchlist_id, symbol)): Path<(i64, String)>,
+    Json(req): Json<EntryBody<UpdateEntry>>,
+) -> Result<Json<EntryBody<WatchlistEntry>>> {
+    fetch_watchlist(&state, auth_user, watchlist_id).await?;
+    let symbol = normalize_code(&symbol);
+
+    let entry = state
+        .store
+        .watchlists
+        .update_entry(
+            watchlist_id,
+            &symbol,
+            req.entry.note.as_deref(),
+            req.entry.position,
+        )
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    Ok(Json(EntryBody { entry }))
+}
+
+async fn remove_entry(
+    auth_user: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path((watchlist_id, symbol)): Path<(i64, String)>,
+) -> Result<StatusCode> {
+    fetch_watchlist(&state, auth_user, watchlist_id).await?;
+    let symbol = normalize_code(&symbol);
+
+    let removed = state
+        .store
+        .watchlists
+        .remove_entry(watchlist_id, &symbol)
+        .await?;
+
+    if !removed {
+        return Err(Error::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Load a watchlist owned by `auth_user`, other users' lists being reported as not found.
+async fn fetch_watchlist(
+    state: &AppState,
+    auth_user: AuthUser,
+    watchlist_id: i64,
+) -> Result<Watchlist> {
+    state
+        .store
+        .watchlists
+        .watchlist(auth_user.user_id, watchlist_id)
+        .await?
+        .ok_or(Error::NotFound)
+}
+
+fn name_conflict(error: StoreError) -> Error {
+    match error {
+        StoreError::Conflict(_) => Error::unprocessable_entity([("name", "name already used")]),
+        error => error.into(),
+    }
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.len() > 64 {
+        return Err(Error::unprocessable_entity([(
+            "name",
+            "must be 1-64 characters",
+        )]));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{test_session_cookie, Role};
+    use crate::conn::test_database;
+    use crate::testing::{insert_stock, insert_user, send};
+
+    #[tokio::test]
+    async fn entries_keep_their_order() {
+        let db = test_database().await;
+        insert_user(&db, 1, "alice", Role::User).await;
+        insert_user(&db, 2, "bob", Role::User).await;
+        for (symbol, name) in [("AAA", "A"), ("BBB", "B"), ("CCC", "C")] {
+            insert_stock(&db, symbol, "XNYS", name).await;
+        }
+        let alice = test_session_cookie(&db, 1).await;
+        let bob = test_session_cookie(&db, 2).await;
+        let app = router().with_state(Arc::new(AppState::new(db)));
+
+        let (status, body) = send(
+            &app,
+            "POST",
+            "/api/watchlists",
+            &alice,
+            Some(serde_json::json!({"watchlist": {"name": "Tech"}})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let uri = format!("/api/watchlists/{}", body["watchlist"]["id"]);
+        let entries = format!("{uri}/entries");
+
+        for symbol in ["aaa", "bbb"] {
+            let entry = serde_json::json!({"entry": {"symbol": symbol}});
+            let (status, _) = send(&app, "POST", &entries, &alice, Some(entry)).await;
+            assert_eq!(status, StatusCode::CREATED);
+        }
+        let entry =
+            serde_json::json!({"entry": {"symbol": "CCC", "note": "earnings", "position": 0}});
+        let (_, body) = send(&app, "POST", &entries, &alice, Some(entry.clone())).await;
+        assert_eq!(body["entry"]["position"], 0);
+
+        let (status, _) = send(&app, "POST", &entries, &alice, Some(entry)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let update = serde_json::json!({"entry": {"position": 5}});
+        let (_, body) = send(&app, "PUT", &format!("{entries}/CCC"), &alice, Some(update)).await;
+        assert_eq!(body["entry"]["position"], 2);
+        assert_eq!(body["entry"]["note"], "earnings");
+
+        let (status, _) = send(&app, "DELETE", &format!("{entries}/AAA"), &alice, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let order = serde_json::json!({"symbols": ["BBB"]});
+        let (status, _) = send(&app, "PUT", &format!("{uri}/order"), &alice, Some(order)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let order = serde_json::json!({"symbols": ["ccc", "BBB"]});
+        let (status, body) = send(&app, "PUT", &format!("{uri}/order"), &alice, Some(order)).await;
+        assert_eq!(status, StatusCode::OK);
+        let symbols: Vec<_> = body["watchlist"]["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| (entry["symbol"].clone(), entry["position"].clone()))
+            .collect();
+        assert_eq!(
+            symbols,
+            [
+                (serde_json::json!("CCC"), serde_json::json!(0)),
+                (serde_json::json!("BBB"), serde_json::json!(1))
+            ]
+        );
+
+        let (status, _) = send(&app, "GET", &uri, &bob, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (_, body) = send(&app, "GET", "/api/watchlists", &bob, None).await;
+        assert_eq!(body["watchlistsCount"], 0);
+    }
+}
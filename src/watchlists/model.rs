@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+/// A named, ordered list of symbols a user keeps an eye on.
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Watchlist {
+    #[serde(rename = "id")]
+    pub watchlist_id: i64,
+    #[serde(skip_serializing)]
+    pub user_id: i64,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A symbol on a watchlist, with its 0-based position in the user's chosen order.
+#[derive(Debug, Clone, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchlistEntry {
+    #[serde(skip_serializing)]
+    pub watchlist_id: i64,
+    pub symbol: String,
+    pub position: i64,
+    pub note: Option<String>,
+    pub added_at: DateTime<Utc>,
+}
+
+/// Move `symbol` to `position` of `order`, appending it when `position` is past the end or
+/// unset. `symbol` may or may not be in `order` already.
+pub fn place(order: &mut Vec<String>, symbol: &str, position: Option<usize>) {
+    order.retain(|s| s != symbol);
+    let at = position.unwrap_or(order.len()).min(order.len());
+    order.insert(at, symbol.to_string());
+}
+
+/// Whether `requested` lists every symbol of `current` exactly once, in any order.
+pub fn is_reordering(current: &[String], requested: &[String]) -> bool {
+    let mut current = current.to_vec();
+    let mut requested = requested.to_vec();
+    current.sort();
+    requested.sort();
+    current == requested
+}